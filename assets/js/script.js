@@ -27,3 +27,36 @@ document.querySelector("#changeTheme").addEventListener('change', (e) => {
   //change theme
   document.querySelector('html').setAttribute("data-bs-theme", e.target.value);
 });
+
+// @description Add the clicked tag to the comma separated tags of a book form
+document.querySelectorAll("[data-add-tag]").forEach((button) => {
+  button.addEventListener('click', (_e) => {
+    const input = document.getElementById(button.dataset.tagInput);
+    const tags = input.value.split(",").map(tag => tag.trim()).filter(tag => tag !== "");
+    const tag = button.dataset.addTag;
+
+    if (!tags.some(other => other.toLowerCase() === tag.toLowerCase())) {
+      tags.push(tag);
+    }
+    input.value = tags.join(", ");
+  });
+});
+
+// @description Suggest the known authors for the name being typed in a comma separated list
+const authorNames = Array.from(
+  document.querySelector("#author-names")?.content.querySelectorAll("option") ?? []
+).map(option => option.value);
+
+document.querySelectorAll("[data-author-input]").forEach((input) => {
+  input.addEventListener('input', (_e) => {
+    const datalist = document.getElementById(input.getAttribute("list"));
+    const separator = input.value.lastIndexOf(",");
+    const typed = input.value.slice(separator + 1).trim().toLowerCase();
+    const before = separator === -1 ? "" : input.value.slice(0, separator + 1) + " ";
+
+    datalist.replaceChildren(...authorNames
+      .filter(name => typed !== "" && name.toLowerCase().includes(typed))
+      .slice(0, 10)
+      .map(name => new Option(before + name)));
+  });
+});
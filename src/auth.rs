@@ -0,0 +1,182 @@
+use axum::{
+    Json,
+    extract::{FromRequestParts, Request, State},
+    http::{Method, StatusCode, request::Parts},
+    middleware::Next,
+    response::{IntoResponse, Redirect, Response},
+};
+use axum_extra::extract::CookieJar;
+use snafu::prelude::*;
+
+use crate::{
+    csrf::SessionCsrfToken,
+    models::{book, borrow_request, session::SessionOperator, user},
+    routes::api::ErrorBody,
+    state::AppState,
+};
+
+#[derive(Snafu, Debug)]
+#[snafu(visibility(pub))]
+pub enum AuthError {
+    /// The current user is not allowed to do this
+    #[snafu(display("You are not allowed to do this"))]
+    Forbidden,
+}
+
+/// Name of the cookie holding the session token
+pub const SESSION_COOKIE: &str = "bookforge_session";
+
+/// User logged in for the current request, inserted by [`authenticate`]
+#[derive(Clone, Debug)]
+pub struct CurrentUser(pub Option<user::Model>);
+
+impl<S: Send + Sync> FromRequestParts<S> for CurrentUser {
+    type Rejection = std::convert::Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(parts
+            .extensions
+            .get::<CurrentUser>()
+            .cloned()
+            .unwrap_or(CurrentUser(None)))
+    }
+}
+
+/// Actions restricted to some users
+pub enum Permission<'a> {
+    /// Add a book owned by the given user
+    CreateBookFor(i32),
+    /// Edit or delete a book
+    EditBook(&'a book::Model),
+    /// Mark the loan of a book as returned
+    ReturnBook(&'a book::Model),
+    /// Approve or decline the requests to borrow a book
+    AnswerBorrowRequest(&'a book::Model),
+    /// Withdraw a request to borrow a book
+    CancelBorrowRequest(&'a borrow_request::Model),
+    /// Reorder and clear the waitlist of a book
+    ManageWaitlist(&'a book::Model),
+    /// Create, delete and change the role of users
+    ManageUsers,
+    /// Edit the name and the password of the given user
+    EditUser(i32),
+    /// Rename and delete the tags shared by every book
+    ManageTags,
+    /// Rename the authors and merge the duplicates
+    ManageAuthors,
+}
+
+impl Permission<'_> {
+    fn allows(&self, user: &user::Model) -> bool {
+        if user.is_admin() {
+            return true;
+        }
+
+        match self {
+            Permission::CreateBookFor(owner_id) => *owner_id == user.id,
+            Permission::EditBook(book) => book.owner_id == user.id,
+            Permission::ReturnBook(book) => {
+                book.owner_id == user.id || book.current_holder_id == Some(user.id)
+            }
+            Permission::AnswerBorrowRequest(book) => book.owner_id == user.id,
+            Permission::CancelBorrowRequest(request) => request.requester_id == user.id,
+            Permission::ManageWaitlist(book) => book.owner_id == user.id,
+            Permission::ManageUsers => false,
+            Permission::EditUser(user_id) => *user_id == user.id,
+            Permission::ManageTags => false,
+            Permission::ManageAuthors => false,
+        }
+    }
+}
+
+/// Checks that the current user has the permission.
+///
+/// Anonymous users are never granted anything, the first admin
+/// is created from the command line with `bookforge user add --admin`.
+///
+/// # Errors
+/// Returns `AuthError::Forbidden` if the permission is not granted.
+pub fn authorize(
+    current_user: &Option<user::Model>,
+    permission: Permission<'_>,
+) -> Result<(), AuthError> {
+    let allowed = current_user
+        .as_ref()
+        .is_some_and(|user| permission.allows(user));
+
+    ensure!(allowed, ForbiddenSnafu);
+
+    Ok(())
+}
+
+/// Middleware loading the user from the session cookie and
+/// rejecting anonymous requests on protected routes.
+///
+/// Runs before [`crate::csrf::protect`], which needs the CSRF token of the session.
+///
+/// Anonymous HTML requests are redirected to the login page,
+/// anonymous API requests get a 401 JSON error.
+pub async fn authenticate(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let session = match jar.get(SESSION_COOKIE) {
+        Some(cookie) => SessionOperator::new(state.clone())
+            .find_with_user(cookie.value())
+            .await
+            .unwrap_or_else(|error| {
+                log::error!("Failed to load session: {:?}", error);
+                None
+            }),
+        None => None,
+    };
+    let current_user = session.map(|(session, user)| {
+        // The forms of the session are checked against its own token
+        request
+            .extensions_mut()
+            .insert(SessionCsrfToken(session.csrf_token));
+        user
+    });
+
+    if current_user.is_none() && requires_login(&state, request.method(), request.uri().path()) {
+        if request.uri().path().starts_with("/api/") {
+            let body = ErrorBody {
+                status: StatusCode::UNAUTHORIZED.as_u16(),
+                error: "Authentication required".to_string(),
+            };
+            return (StatusCode::UNAUTHORIZED, Json(body)).into_response();
+        }
+
+        return Redirect::to("/login").into_response();
+    }
+
+    request.extensions_mut().insert(CurrentUser(current_user));
+
+    next.run(request).await
+}
+
+/// 256 bits of randomness, hex encoded, used for session and CSRF tokens
+pub fn random_token() -> String {
+    rand::random::<[u8; 32]>()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Whether an anonymous user is refused on this route
+fn requires_login(state: &AppState, method: &Method, path: &str) -> bool {
+    if path == "/login" || path.starts_with("/assets/") {
+        return false;
+    }
+
+    if method != Method::GET && method != Method::HEAD {
+        true
+    } else if path.ends_with("/new") || path.ends_with("/edit") {
+        // forms only used to mutate something
+        true
+    } else {
+        !state.config.auth.public_catalogue
+    }
+}
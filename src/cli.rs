@@ -0,0 +1,449 @@
+use std::io::{BufRead, Write};
+
+use camino::Utf8PathBuf;
+use clap::{Args, Parser, Subcommand};
+use sea_orm_migration::MigratorTrait;
+use snafu::prelude::*;
+
+use crate::build_app;
+use crate::import::{self, ImportError, ImportFormat};
+use crate::migrations::Migrator;
+use crate::models::backup::{BackupError, BackupOperator};
+use crate::models::book::{BookError, BookOperator};
+use crate::models::user::{self, Role, UserError, UserOperator};
+use crate::routes::{book::books_csv, user::UserForm};
+use crate::state::{
+    AppState,
+    config::{AppConfig, ConfigError},
+    error::AppStateError,
+    listener::ListenerError,
+};
+
+#[derive(Snafu, Debug)]
+#[snafu(visibility(pub))]
+pub enum CliError {
+    #[snafu(display("Failed to initialize AppState"))]
+    State { source: AppStateError },
+    #[snafu(display("Config Error"))]
+    Config { source: ConfigError },
+    #[snafu(display("Listener Error"))]
+    Listener { source: ListenerError },
+    #[snafu(display("Failed to listen on the socket"))]
+    Bind { source: std::io::Error },
+    #[snafu(display("Migration Error"))]
+    Migration { source: sea_orm::DbErr },
+    #[snafu(display("User Error"))]
+    User { source: UserError },
+    #[snafu(display("Book Error"))]
+    Book { source: BookError },
+    #[snafu(display("Import Error"))]
+    Import { source: ImportError },
+    #[snafu(display("Backup Error"))]
+    Backup { source: BackupError },
+    #[snafu(display("Failed to write the backup"))]
+    Json { source: serde_json::Error },
+    #[snafu(display("Failed to read or write {path}"))]
+    IO {
+        path: Utf8PathBuf,
+        source: std::io::Error,
+    },
+    /// No user matches the id or the name given on the command line
+    #[snafu(display("No user matches {user}"))]
+    UnknownUser { user: String },
+    /// `--password-stdin` was given but the standard input has no password
+    #[snafu(display("The password read from the standard input is empty"))]
+    EmptyPassword,
+    /// Some rows of an import can't be imported, nothing was written
+    #[snafu(display("{count} rows can't be imported, nothing was imported"))]
+    InvalidRows { count: usize },
+}
+
+/// Self-hosted library to share books between friends
+#[derive(Parser, Debug)]
+#[command(name = "bookforge", version, about)]
+pub struct Cli {
+    /// Configuration file, `$BOOKFORGE_CONFIG` or the user config directory by default
+    #[arg(short, long, global = true)]
+    pub config: Option<Utf8PathBuf>,
+    /// Starts the web server when no command is given
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Start the web server
+    Serve,
+    /// Apply or revert the database migrations
+    #[command(subcommand)]
+    Migrate(MigrateCommand),
+    /// Manage the users
+    #[command(subcommand)]
+    User(UserCommand),
+    /// Import or export the books
+    #[command(subcommand)]
+    Book(BookCommand),
+    /// Save or restore the whole library as a JSON archive
+    #[command(subcommand)]
+    Backup(BackupCommand),
+    /// Inspect the configuration
+    #[command(subcommand)]
+    Config(ConfigCommand),
+}
+
+#[derive(Subcommand, Debug)]
+pub enum MigrateCommand {
+    /// Apply every pending migration
+    Up,
+    /// Revert the last applied migrations
+    Down {
+        /// Number of migrations to revert
+        #[arg(short, long, default_value_t = 1)]
+        steps: u32,
+    },
+    /// List the migrations and whether they are applied
+    Status,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum UserCommand {
+    /// Create a user
+    Add(AddUserArgs),
+    /// List every user
+    List,
+    /// Delete a user with their books, they stay anonymously in the loans of the others
+    Delete {
+        /// Id or name of the user
+        user: String,
+    },
+}
+
+#[derive(Args, Debug)]
+pub struct AddUserArgs {
+    /// Name of the new user
+    pub name: String,
+    /// Make the user an admin
+    #[arg(long)]
+    pub admin: bool,
+    /// Read the password from the first line of the standard input,
+    /// so it doesn't end up in the shell history
+    #[arg(long)]
+    pub password_stdin: bool,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum BookCommand {
+    /// Import books exported from BookForge or another application
+    Import(ImportArgs),
+    /// Write every book as CSV
+    Export {
+        /// File to write, the standard output by default
+        #[arg(short, long)]
+        output: Option<Utf8PathBuf>,
+    },
+}
+
+#[derive(Args, Debug)]
+pub struct ImportArgs {
+    /// File to import
+    pub path: Utf8PathBuf,
+    /// Application the file comes from:
+    /// book_forge, goodreads, library_thing or calibre
+    #[arg(short, long, default_value = "book_forge", value_parser = parse_format)]
+    pub format: ImportFormat,
+    /// Id or name of the owner of the books without owner
+    #[arg(short, long)]
+    pub owner: Option<String>,
+    /// Only show what would be imported
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum BackupCommand {
+    /// Write the backup of the library
+    Export {
+        /// File to write, the standard output by default
+        #[arg(short, long)]
+        output: Option<Utf8PathBuf>,
+    },
+    /// Replace the whole library with a backup
+    Restore {
+        /// Backup file written by `bookforge backup export`
+        path: Utf8PathBuf,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ConfigCommand {
+    /// Print the configuration in use, with the default values filled in
+    /// and the secrets masked
+    Show,
+    /// Check that the configuration can be loaded
+    Check,
+}
+
+/// Runs a command, the web server by default
+pub async fn run(cli: Cli) -> Result<(), CliError> {
+    let config_path = cli.config;
+
+    match cli.command.unwrap_or(Command::Serve) {
+        Command::Serve => serve(config_path).await,
+        Command::Migrate(command) => migrate(config_path, command).await,
+        Command::User(command) => user(config_path, command).await,
+        Command::Book(command) => book(config_path, command).await,
+        Command::Backup(command) => backup(config_path, command).await,
+        Command::Config(command) => config(config_path, command).await,
+    }
+}
+
+async fn serve(config_path: Option<Utf8PathBuf>) -> Result<(), CliError> {
+    let app_state = AppState::new(config_path).await.context(StateSnafu)?;
+
+    // Nothing can be changed from the web interface before someone logs in
+    if !UserOperator::new(app_state.clone())
+        .any_with_password()
+        .await
+        .context(UserSnafu)?
+    {
+        log::warn!(
+            "No user can log in yet, create an admin with `bookforge user add --admin --password-stdin <name>`"
+        );
+    }
+
+    let app = build_app(app_state.clone());
+
+    let addr = app_state
+        .config
+        .listener
+        .socket_addr()
+        .context(ListenerSnafu)?;
+    let listener = tokio::net::TcpListener::bind(addr)
+        .await
+        .context(BindSnafu)?;
+
+    let _ = axum::serve(listener, app).await;
+
+    Ok(())
+}
+
+async fn migrate(
+    config_path: Option<Utf8PathBuf>,
+    command: MigrateCommand,
+) -> Result<(), CliError> {
+    let state = AppState::without_migrations(config_path)
+        .await
+        .context(StateSnafu)?;
+
+    match command {
+        MigrateCommand::Up => Migrator::up(&state.db, None)
+            .await
+            .context(MigrationSnafu)?,
+        MigrateCommand::Down { steps } => Migrator::down(&state.db, Some(steps))
+            .await
+            .context(MigrationSnafu)?,
+        MigrateCommand::Status => {
+            for migration in Migrator::get_migration_with_status(&state.db)
+                .await
+                .context(MigrationSnafu)?
+            {
+                println!("{}\t{}", migration.status(), migration.name());
+            }
+        }
+    }
+
+    Ok(())
+}
+
+async fn user(config_path: Option<Utf8PathBuf>, command: UserCommand) -> Result<(), CliError> {
+    let state = AppState::new(config_path).await.context(StateSnafu)?;
+    let operator = UserOperator::new(state);
+
+    match command {
+        UserCommand::Add(args) => {
+            let password = if args.password_stdin {
+                let mut password = String::new();
+                std::io::stdin()
+                    .lock()
+                    .read_line(&mut password)
+                    .context(IOSnafu { path: "-" })?;
+
+                // Only the line break, the password is kept as typed
+                let password = password
+                    .strip_suffix('\n')
+                    .map(|password| password.strip_suffix('\r').unwrap_or(password))
+                    .unwrap_or(&password);
+                ensure!(!password.is_empty(), EmptyPasswordSnafu);
+
+                Some(password.to_string())
+            } else {
+                None
+            };
+            let role = if args.admin {
+                Role::Admin
+            } else {
+                Role::Member
+            };
+
+            let user = operator
+                .create(UserForm {
+                    name: args.name,
+                    password,
+                    role: Some(role),
+                })
+                .await
+                .context(UserSnafu)?;
+
+            println!("Created user {} ({})", user.name, user.id);
+        }
+        UserCommand::List => {
+            for user in operator.all().await.context(UserSnafu)? {
+                println!("{}\t{}\t{}", user.id, user.name, user.role.as_str());
+            }
+        }
+        UserCommand::Delete { user } => {
+            let user = find_user(&operator, &user).await?;
+            operator.delete(user.id).await.context(UserSnafu)?;
+
+            println!("Deleted user {} ({})", user.name, user.id);
+        }
+    }
+
+    Ok(())
+}
+
+async fn book(config_path: Option<Utf8PathBuf>, command: BookCommand) -> Result<(), CliError> {
+    let state = AppState::new(config_path).await.context(StateSnafu)?;
+
+    match command {
+        BookCommand::Import(args) => {
+            let content = std::fs::read(&args.path).context(IOSnafu {
+                path: args.path.clone(),
+            })?;
+            let books = import::parse(args.format, &content, args.owner)
+                .await
+                .context(ImportSnafu)?;
+
+            let users = UserOperator::new(state.clone())
+                .all()
+                .await
+                .context(UserSnafu)?;
+            let book_operator = BookOperator::new(state);
+            let existing = book_operator.all().await.context(BookSnafu)?;
+
+            let rows = import::preview(books, &users, &existing);
+            for row in &rows {
+                let status = match (&row.form, row.duplicate_of) {
+                    (Err(_), _) => row.errors().join(", "),
+                    (Ok(_), Some(duplicate_id)) => {
+                        format!("already in the library ({})", duplicate_id)
+                    }
+                    (Ok(_), None) => "ok".to_string(),
+                };
+                println!("{}\t{}\t{}", row.line, row.book.title, status);
+            }
+
+            let invalid_count = rows.iter().filter(|row| !row.is_valid()).count();
+            ensure!(
+                invalid_count == 0,
+                InvalidRowsSnafu {
+                    count: invalid_count
+                }
+            );
+
+            if !args.dry_run {
+                let forms = rows
+                    .into_iter()
+                    .filter(|row| row.is_new())
+                    .filter_map(|row| row.form.ok())
+                    .collect();
+                let books = book_operator.import(forms).await.context(BookSnafu)?;
+
+                println!("Imported {} books", books.len());
+            }
+        }
+        BookCommand::Export { output } => {
+            let csv = books_csv(state, None, None).await.context(StateSnafu)?;
+            write_output(output, &csv)?;
+        }
+    }
+
+    Ok(())
+}
+
+async fn backup(config_path: Option<Utf8PathBuf>, command: BackupCommand) -> Result<(), CliError> {
+    let state = AppState::new(config_path).await.context(StateSnafu)?;
+    let operator = BackupOperator::new(state);
+
+    match command {
+        BackupCommand::Export { output } => {
+            let backup = operator.export().await.context(BackupSnafu)?;
+            let json = serde_json::to_vec_pretty(&backup).context(JsonSnafu)?;
+            write_output(output, &json)?;
+        }
+        BackupCommand::Restore { path } => {
+            let json = std::fs::read(&path).context(IOSnafu { path: path.clone() })?;
+            let backup = BackupOperator::parse(&json).context(BackupSnafu)?;
+            let summary = operator.restore(backup).await.context(BackupSnafu)?;
+
+            println!(
+                "Restored {} users, {} books and {} loans from {}",
+                summary.users, summary.books, summary.loans, path
+            );
+        }
+    }
+
+    Ok(())
+}
+
+async fn config(config_path: Option<Utf8PathBuf>, command: ConfigCommand) -> Result<(), CliError> {
+    let path = AppConfig::resolve_path(config_path.clone());
+    let config = AppConfig::new(config_path).await.context(ConfigSnafu)?;
+
+    match command {
+        ConfigCommand::Show => {
+            println!("# {}", path);
+            println!(
+                "{}",
+                toml::to_string(&config.redacted()).unwrap_or_default()
+            );
+        }
+        ConfigCommand::Check => {
+            config.listener.socket_addr().context(ListenerSnafu)?;
+
+            println!("{} is valid", path);
+        }
+    }
+
+    Ok(())
+}
+
+/// Finds a user by id, or by name
+async fn find_user(operator: &UserOperator, value: &str) -> Result<user::Model, CliError> {
+    let users = operator.all().await.context(UserSnafu)?;
+
+    users
+        .into_iter()
+        .find(|user| value.parse::<i32>() == Ok(user.id) || user.name == value)
+        .context(UnknownUserSnafu { user: value })
+}
+
+/// Writes to the file, or to the standard output without file
+fn write_output(output: Option<Utf8PathBuf>, content: &[u8]) -> Result<(), CliError> {
+    match output {
+        Some(path) => std::fs::write(&path, content).context(IOSnafu { path }),
+        None => std::io::stdout()
+            .write_all(content)
+            .context(IOSnafu { path: "-" }),
+    }
+}
+
+fn parse_format(name: &str) -> Result<ImportFormat, String> {
+    ImportFormat::from_name(name).ok_or_else(|| {
+        let names: Vec<&str> = ImportFormat::all()
+            .iter()
+            .map(ImportFormat::as_str)
+            .collect();
+        format!("expected one of {}", names.join(", "))
+    })
+}
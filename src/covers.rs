@@ -0,0 +1,284 @@
+//! Book covers, kept in the [`Storage`](crate::storage::Storage): the original
+//! file in `covers/originals/` and a fixed-size JPEG thumbnail in `covers/thumbnails/`.
+//!
+//! The name of a cover is the hash of the original, so books with the same
+//! cover share the files.
+
+use std::io::Cursor;
+
+use image::{ImageFormat, imageops::FilterType};
+use sea_orm::ActiveValue::Set;
+use sea_orm::entity::prelude::*;
+use snafu::prelude::*;
+
+use crate::models::book;
+use crate::state::AppState;
+use crate::storage::{self, StorageError};
+
+/// Size of the thumbnails shown in the book list, a usual 2:3 cover ratio
+pub const THUMBNAIL_WIDTH: u32 = 200;
+pub const THUMBNAIL_HEIGHT: u32 = 300;
+
+/// Biggest cover accepted, uploaded or downloaded
+pub const MAX_COVER_SIZE: usize = 10 * 1024 * 1024;
+
+const ORIGINALS: &str = "covers/originals";
+const THUMBNAILS: &str = "covers/thumbnails";
+
+#[derive(Debug, Snafu)]
+#[snafu(visibility(pub))]
+pub enum CoverError {
+    /// Db Error from SeaOrm
+    #[snafu(display("Database error"))]
+    DB { source: sea_orm::DbErr },
+    /// Error of the storage backend
+    #[snafu(display("Storage error: {source}"))]
+    Storage { source: StorageError },
+    /// The file is not an image we can read
+    #[snafu(display("The cover is not a JPEG, PNG, GIF or WebP image"))]
+    Image { source: image::ImageError },
+    #[snafu(display("The cover is bigger than {MAX_COVER_SIZE} bytes"))]
+    TooLarge,
+    /// The cover URL of the metadata provider can't be downloaded
+    #[snafu(display("Could not download the cover {url}"))]
+    Download { url: String, source: reqwest::Error },
+    /// The cover URL is not on a host of `metadata.cover_hosts`
+    #[snafu(display("The cover {url} is not hosted by a metadata provider"))]
+    ForbiddenHost { url: String },
+    /// The book has no cover
+    #[snafu(display("Book {id} has no cover"))]
+    NotFound { id: i32 },
+    #[snafu(display("Thumbnail generation was interrupted"))]
+    Task { source: tokio::task::JoinError },
+}
+
+/// Version of the cover to serve
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CoverSize {
+    Original,
+    Thumbnail,
+}
+
+/// Cover decoded and resized by [`decode`], ready to be saved
+#[derive(Debug)]
+pub struct Cover {
+    bytes: Vec<u8>,
+    extension: &'static str,
+    thumbnail: Vec<u8>,
+}
+
+/// Checks the image and generates its thumbnail, before anything is saved
+pub async fn decode(bytes: Vec<u8>) -> Result<Cover, CoverError> {
+    ensure!(bytes.len() <= MAX_COVER_SIZE, TooLargeSnafu);
+
+    let format = image::guess_format(&bytes).context(ImageSnafu)?;
+    let extension = format.extensions_str().first().copied().unwrap_or("img");
+    let thumbnail = thumbnail(bytes.clone(), format).await?;
+
+    Ok(Cover {
+        bytes,
+        extension,
+        thumbnail,
+    })
+}
+
+/// Saves the image as the cover of the book, replacing the previous one
+pub async fn save(
+    state: &AppState,
+    book: book::Model,
+    cover: Cover,
+) -> Result<book::Model, CoverError> {
+    let name = storage::content_name(&cover.bytes, cover.extension);
+
+    // Already stored for another book
+    let thumbnail_key = key(&name, CoverSize::Thumbnail);
+    if !state
+        .storage
+        .exists(&thumbnail_key)
+        .await
+        .context(StorageSnafu)?
+    {
+        state
+            .storage
+            .put(&thumbnail_key, &cover.thumbnail)
+            .await
+            .context(StorageSnafu)?;
+    }
+    storage::put_content(
+        state.storage.as_ref(),
+        ORIGINALS,
+        &cover.bytes,
+        cover.extension,
+    )
+    .await
+    .context(StorageSnafu)?;
+
+    let previous = book.cover.clone();
+    let mut book: book::ActiveModel = book.into();
+    book.cover = Set(Some(name.clone()));
+    let book = book.update(&state.db).await.context(DBSnafu)?;
+
+    if let Some(previous) = previous
+        && previous != name
+    {
+        remove_files(state, &previous).await?;
+    }
+
+    Ok(book)
+}
+
+/// Downloads the cover URL given by a metadata provider and saves it.
+///
+/// Only the hosts of `metadata.cover_hosts` are reached, redirects included,
+/// and the body is read until [`MAX_COVER_SIZE`].
+pub async fn download(
+    state: &AppState,
+    book: book::Model,
+    url: &str,
+) -> Result<book::Model, CoverError> {
+    let metadata = state.config.metadata.clone();
+    let allowed = move |url: &reqwest::Url| {
+        matches!(url.scheme(), "http" | "https")
+            && url
+                .host_str()
+                .is_some_and(|host| metadata.allows_cover_host(host))
+    };
+
+    let parsed = reqwest::Url::parse(url).ok();
+    ensure!(
+        parsed.as_ref().is_some_and(&allowed),
+        ForbiddenHostSnafu { url }
+    );
+
+    let client = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::custom(move |attempt| {
+            if attempt.previous().len() >= 10 {
+                attempt.error("too many redirects")
+            } else if allowed(attempt.url()) {
+                attempt.follow()
+            } else {
+                attempt.error("redirected outside of the cover hosts")
+            }
+        }))
+        .build()
+        .context(DownloadSnafu { url })?;
+
+    let mut response = client
+        .get(url)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .context(DownloadSnafu { url })?;
+
+    ensure!(
+        response.content_length().unwrap_or_default() as usize <= MAX_COVER_SIZE,
+        TooLargeSnafu
+    );
+
+    // The announced length may be missing or wrong, stop reading past the limit
+    let mut bytes = Vec::new();
+    while let Some(chunk) = response.chunk().await.context(DownloadSnafu { url })? {
+        ensure!(bytes.len() + chunk.len() <= MAX_COVER_SIZE, TooLargeSnafu);
+        bytes.extend_from_slice(&chunk);
+    }
+
+    save(state, book, decode(bytes).await?).await
+}
+
+/// Removes the cover of the book
+pub async fn delete(state: &AppState, book: book::Model) -> Result<book::Model, CoverError> {
+    let Some(name) = book.cover.clone() else {
+        return Ok(book);
+    };
+
+    let mut book: book::ActiveModel = book.into();
+    book.cover = Set(None);
+    let book = book.update(&state.db).await.context(DBSnafu)?;
+
+    remove_files(state, &name).await?;
+
+    Ok(book)
+}
+
+/// Content and MIME type of the cover of the book
+pub async fn read(
+    state: &AppState,
+    book: &book::Model,
+    size: CoverSize,
+) -> Result<(Vec<u8>, &'static str), CoverError> {
+    let name = book
+        .cover
+        .as_deref()
+        .context(NotFoundSnafu { id: book.id })?;
+
+    let bytes = state
+        .storage
+        .get(&key(name, size))
+        .await
+        .context(StorageSnafu)?;
+    let mime_type = match size {
+        CoverSize::Original => name
+            .rsplit_once('.')
+            .and_then(|(_, extension)| ImageFormat::from_extension(extension))
+            .map(|format| format.to_mime_type())
+            .unwrap_or("application/octet-stream"),
+        CoverSize::Thumbnail => ImageFormat::Jpeg.to_mime_type(),
+    };
+
+    Ok((bytes, mime_type))
+}
+
+/// Removes the original and the thumbnail once no book uses them anymore
+pub async fn remove_files(state: &AppState, name: &str) -> Result<(), CoverError> {
+    let still_used = book::Entity::find()
+        .filter(book::Column::Cover.eq(name))
+        .count(&state.db)
+        .await
+        .context(DBSnafu)?
+        > 0;
+    if still_used {
+        return Ok(());
+    }
+
+    for size in [CoverSize::Original, CoverSize::Thumbnail] {
+        state
+            .storage
+            .delete(&key(name, size))
+            .await
+            .context(StorageSnafu)?;
+    }
+
+    Ok(())
+}
+
+// private
+
+fn key(name: &str, size: CoverSize) -> String {
+    match size {
+        CoverSize::Original => format!("{}/{}", ORIGINALS, name),
+        // Thumbnails are always JPEG, whatever the original format
+        CoverSize::Thumbnail => {
+            let stem = name.rsplit_once('.').map_or(name, |(stem, _)| stem);
+            format!("{}/{}.jpg", THUMBNAILS, stem)
+        }
+    }
+}
+
+/// Decoding and resizing are CPU bound, so done outside of the async runtime
+async fn thumbnail(bytes: Vec<u8>, format: ImageFormat) -> Result<Vec<u8>, CoverError> {
+    tokio::task::spawn_blocking(move || {
+        let thumbnail = image::load_from_memory_with_format(&bytes, format)
+            .context(ImageSnafu)?
+            .resize_to_fill(THUMBNAIL_WIDTH, THUMBNAIL_HEIGHT, FilterType::Lanczos3)
+            .to_rgb8();
+
+        let mut encoded = Cursor::new(vec![]);
+        thumbnail
+            .write_to(&mut encoded, ImageFormat::Jpeg)
+            .context(ImageSnafu)?;
+
+        Ok(encoded.into_inner())
+    })
+    .await
+    .context(TaskSnafu)?
+}
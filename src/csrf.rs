@@ -0,0 +1,206 @@
+use axum::{
+    Json,
+    body::{Body, Bytes, to_bytes},
+    extract::Request,
+    http::{HeaderValue, Method, StatusCode, header},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use axum_extra::extract::{
+    CookieJar,
+    cookie::{Cookie, SameSite},
+};
+
+use crate::{
+    auth,
+    routes::{self, api::ErrorBody},
+    state::error::AppStateError,
+};
+
+/// Name of the cookie holding the CSRF token of the login form
+pub const CSRF_COOKIE: &str = "bookforge_csrf";
+
+/// Name of the form field carrying the CSRF token
+pub const CSRF_FIELD: &str = "csrf_token";
+
+/// Name of the header carrying the CSRF token, for scripts and API clients
+pub const CSRF_HEADER: &str = "x-csrf-token";
+
+/// Maximum size of a form body read to find the token, the default limit of axum
+const MAX_FORM_SIZE: usize = 2 * 1024 * 1024;
+
+/// CSRF token rendered in the forms of the current request, inserted by [`protect`]
+#[derive(Clone, Debug)]
+pub struct CsrfToken(pub String);
+
+/// CSRF token stored with the session of the logged in user,
+/// inserted by [`auth::authenticate`]
+#[derive(Clone, Debug)]
+pub struct SessionCsrfToken(pub String);
+
+/// Middleware rejecting state-changing requests without a valid CSRF token.
+///
+/// A logged in user must send back the token of their session, before logging in
+/// the login form is checked against a `SameSite=Strict` cookie. The token comes
+/// in the `csrf_token` field of urlencoded and multipart forms, or in the
+/// `X-CSRF-Token` header. The JSON API is protected the same way, its responses
+/// carry the token of the session in the `X-CSRF-Token` header.
+pub async fn protect(jar: CookieJar, request: Request, next: Next) -> Response {
+    let session_token = request
+        .extensions()
+        .get::<SessionCsrfToken>()
+        .map(|token| token.0.clone());
+    let cookie_token = jar
+        .get(CSRF_COOKIE)
+        .map(|cookie| cookie.value().to_string());
+    let expected = session_token.clone().or_else(|| cookie_token.clone());
+
+    let is_safe = matches!(
+        *request.method(),
+        Method::GET | Method::HEAD | Method::OPTIONS
+    );
+    let is_api = request.uri().path().starts_with("/api/");
+
+    let mut request = if is_safe {
+        request
+    } else {
+        let limit = body_limit(request.uri().path());
+        match verify(request, expected.as_deref(), limit).await {
+            Some(request) => request,
+            None if is_api => return api_error(),
+            None => return AppStateError::Csrf.into_response(),
+        }
+    };
+
+    let token = expected.unwrap_or_else(auth::random_token);
+    request.extensions_mut().insert(CsrfToken(token.clone()));
+
+    let mut response = next.run(request).await;
+
+    if is_api
+        && session_token.is_some()
+        && let Ok(value) = HeaderValue::from_str(&token)
+    {
+        response.headers_mut().insert(CSRF_HEADER, value);
+    }
+
+    if session_token.is_none() && cookie_token.is_none() {
+        (jar.add(csrf_cookie(token)), response).into_response()
+    } else {
+        response
+    }
+}
+
+/// Builds the cookie holding the CSRF token of the login form
+pub fn csrf_cookie(token: String) -> Cookie<'static> {
+    Cookie::build((CSRF_COOKIE, token))
+        .path("/")
+        .http_only(true)
+        .same_site(SameSite::Strict)
+        .build()
+}
+
+/// Size of the body read to find the token, bigger on the routes receiving files
+/// to match their `DefaultBodyLimit`
+fn body_limit(path: &str) -> usize {
+    let is_book_form = path == "/books"
+        || path
+            .strip_prefix("/books/")
+            .is_some_and(|id| id.parse::<i32>().is_ok());
+
+    match path {
+        "/books/import" | "/books/import/preview" | "/backup/restore" => {
+            routes::import::MAX_UPLOAD_SIZE
+        }
+        _ if is_book_form => routes::book::MAX_FORM_SIZE,
+        _ => MAX_FORM_SIZE,
+    }
+}
+
+/// Returns the request back if it carries the expected token
+async fn verify(request: Request, expected: Option<&str>, limit: usize) -> Option<Request> {
+    let expected = expected?;
+
+    if let Some(token) = request.headers().get(CSRF_HEADER) {
+        return constant_time_eq(token.as_bytes(), expected.as_bytes()).then_some(request);
+    }
+
+    let content_type = request
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|content_type| content_type.to_str().ok())?
+        .to_string();
+    let is_multipart = content_type.starts_with("multipart/form-data");
+    if !is_multipart && !content_type.starts_with("application/x-www-form-urlencoded") {
+        return None;
+    }
+
+    // The body has to be read to find the field, then given back to the handler
+    let (parts, body) = request.into_parts();
+    let bytes = to_bytes(body, limit).await.ok()?;
+    let token = if is_multipart {
+        find_multipart_token(&content_type, bytes.clone()).await?
+    } else {
+        find_token(std::str::from_utf8(&bytes).ok()?)?
+    };
+
+    constant_time_eq(token.as_bytes(), expected.as_bytes())
+        .then(|| Request::from_parts(parts, Body::from(bytes)))
+}
+
+/// Finds the CSRF token in an urlencoded string
+fn find_token(encoded: &str) -> Option<String> {
+    serde_urlencoded::from_str::<Vec<(String, String)>>(encoded)
+        .ok()?
+        .into_iter()
+        .find(|(name, _)| name == CSRF_FIELD)
+        .map(|(_, value)| value)
+}
+
+/// Finds the CSRF token in a multipart body, the forms send it first
+async fn find_multipart_token(content_type: &str, bytes: Bytes) -> Option<String> {
+    let boundary = multer::parse_boundary(content_type).ok()?;
+    let mut multipart = multer::Multipart::new(Body::from(bytes).into_data_stream(), boundary);
+
+    while let Some(field) = multipart.next_field().await.ok()? {
+        if field.name() == Some(CSRF_FIELD) {
+            return field.text().await.ok();
+        }
+    }
+
+    None
+}
+
+fn api_error() -> Response {
+    let body = ErrorBody {
+        status: StatusCode::FORBIDDEN.as_u16(),
+        error: AppStateError::Csrf.to_string(),
+    };
+
+    (StatusCode::FORBIDDEN, Json(body)).into_response()
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn body_limit_by_route() {
+        assert_eq!(body_limit("/login"), MAX_FORM_SIZE);
+        assert_eq!(body_limit("/books/3/delete"), MAX_FORM_SIZE);
+        assert_eq!(body_limit("/books"), routes::book::MAX_FORM_SIZE);
+        assert_eq!(body_limit("/books/3"), routes::book::MAX_FORM_SIZE);
+        assert_eq!(
+            body_limit("/books/import/preview"),
+            routes::import::MAX_UPLOAD_SIZE
+        );
+        assert_eq!(
+            body_limit("/backup/restore"),
+            routes::import::MAX_UPLOAD_SIZE
+        );
+    }
+}
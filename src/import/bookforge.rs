@@ -0,0 +1,39 @@
+use super::{ImportError, ImportedBook, Table};
+
+/// Reads a CSV file with the column layout of our own export.
+///
+/// Columns are found by their header (case insensitive) so they can be
+/// reordered, and the ID and Overdue columns of the export are ignored.
+/// Only the title column is mandatory: rows without owner get the owner
+/// chosen for the import.
+pub fn parse(content: &str) -> Result<Vec<ImportedBook>, ImportError> {
+    let table = Table::read(content, b',')?;
+
+    let title = table.required_column(&["title"])?;
+    let owner = table.column(&["owner", "owner id"]);
+    let authors = table.column(&["author(s)", "authors", "author"]);
+    let isbn = table.column(&["isbn"]);
+    let description = table.column(&["description"]);
+    let comment = table.column(&["comment"]);
+    let current_holder = table.column(&["current holder", "current holder id", "holder"]);
+    let due_date = table.column(&["due date"]);
+    let tags = table.column(&["tags"]);
+    let cover_url = table.column(&["cover url", "cover"]);
+
+    Ok(table
+        .records
+        .iter()
+        .map(|record| ImportedBook {
+            title: Table::field(record, Some(title)).unwrap_or_default(),
+            authors: Table::field(record, authors).unwrap_or_default(),
+            isbn: Table::field(record, isbn),
+            description: Table::field(record, description),
+            comment: Table::field(record, comment),
+            owner: Table::field(record, owner),
+            current_holder: Table::field(record, current_holder),
+            due_date: Table::field(record, due_date),
+            tags: Table::field(record, tags),
+            cover_url: Table::field(record, cover_url),
+        })
+        .collect())
+}
@@ -0,0 +1,157 @@
+use sea_orm::{Database, DbBackend, FromQueryResult, Statement};
+use snafu::prelude::*;
+
+use super::{CalibreSnafu, ImportError, ImportedBook, IoSnafu, non_empty};
+use crate::auth;
+
+/// Book as stored in the tables of a Calibre library
+#[derive(Debug, FromQueryResult)]
+struct CalibreBook {
+    title: String,
+    authors: Option<String>,
+    isbn: Option<String>,
+    description: Option<String>,
+}
+
+const BOOKS_QUERY: &str = r#"
+    SELECT
+        books.title AS title,
+        (
+            SELECT group_concat(authors.name, ', ')
+            FROM books_authors_link
+            JOIN authors ON authors.id = books_authors_link.author
+            WHERE books_authors_link.book = books.id
+        ) AS authors,
+        (
+            SELECT identifiers.val
+            FROM identifiers
+            WHERE identifiers.book = books.id AND identifiers.type = 'isbn'
+        ) AS isbn,
+        (
+            SELECT comments.text FROM comments WHERE comments.book = books.id
+        ) AS description
+    FROM books
+    ORDER BY books.id
+"#;
+
+/// Reads the `metadata.db` file at the root of a Calibre library.
+///
+/// SQLite can only open files, so the upload is written to a temporary
+/// file which is removed once read.
+pub async fn parse(content: &[u8]) -> Result<Vec<ImportedBook>, ImportError> {
+    let path = std::env::temp_dir().join(format!("bookforge-calibre-{}.db", auth::random_token()));
+    std::fs::write(&path, content).context(IoSnafu)?;
+
+    let books = read(&path).await;
+
+    if let Err(error) = std::fs::remove_file(&path) {
+        log::warn!("Failed to remove {}: {:?}", path.display(), error);
+    }
+
+    Ok(books?
+        .into_iter()
+        .map(|book| ImportedBook {
+            title: book.title,
+            authors: book.authors.unwrap_or_default(),
+            isbn: non_empty(book.isbn.as_deref()),
+            // Calibre stores the description as HTML
+            description: non_empty(book.description.as_deref().map(strip_html).as_deref()),
+            ..Default::default()
+        })
+        .collect())
+}
+
+async fn read(path: &std::path::Path) -> Result<Vec<CalibreBook>, ImportError> {
+    let db = Database::connect(format!("sqlite:{}?mode=ro", path.display()))
+        .await
+        .context(CalibreSnafu)?;
+
+    let books =
+        CalibreBook::find_by_statement(Statement::from_string(DbBackend::Sqlite, BOOKS_QUERY))
+            .all(&db)
+            .await
+            .context(CalibreSnafu);
+
+    db.close().await.context(CalibreSnafu)?;
+
+    books
+}
+
+/// Removes the tags of an HTML description, keeping paragraphs apart
+fn strip_html(html: &str) -> String {
+    let html = html.replace("</p>", "\n").replace("<br>", "\n");
+
+    let mut text = String::with_capacity(html.len());
+    let mut in_tag = false;
+    for c in html.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' => in_tag = false,
+            c if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+
+    text.trim().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use sea_orm::ConnectionTrait;
+
+    use super::*;
+
+    /// The tables of a Calibre library read by [`BOOKS_QUERY`], with two books
+    const LIBRARY: &str = r#"
+        CREATE TABLE books (id INTEGER PRIMARY KEY, title TEXT NOT NULL);
+        CREATE TABLE authors (id INTEGER PRIMARY KEY, name TEXT NOT NULL);
+        CREATE TABLE books_authors_link (id INTEGER PRIMARY KEY, book INTEGER, author INTEGER);
+        CREATE TABLE identifiers (id INTEGER PRIMARY KEY, book INTEGER, type TEXT, val TEXT);
+        CREATE TABLE comments (id INTEGER PRIMARY KEY, book INTEGER, text TEXT);
+        INSERT INTO books VALUES (1, 'The Dispossessed'), (2, 'Mutual Aid');
+        INSERT INTO authors VALUES (1, 'Ursula K. Le Guin'), (2, 'Pyotr Kropotkin');
+        INSERT INTO books_authors_link VALUES (1, 1, 1), (2, 2, 2);
+        INSERT INTO identifiers VALUES (1, 1, 'isbn', '9780060512750'), (2, 2, 'goodreads', '13079982');
+        INSERT INTO comments VALUES (1, 1, '<div><p>An <b>ambiguous</b> utopia.</p><p>Anarres<br>and Urras</p></div>');
+    "#;
+
+    #[test]
+    fn html_description() {
+        assert_eq!(
+            strip_html("<div><p>An <b>ambiguous</b> utopia.</p><p>Anarres<br>and Urras</p></div>"),
+            "An ambiguous utopia.\nAnarres\nand Urras"
+        );
+        assert_eq!(strip_html("No markup"), "No markup");
+    }
+
+    #[tokio::test]
+    async fn calibre_library() {
+        let path = std::env::temp_dir().join(format!("bookforge-test-{}.db", auth::random_token()));
+        let db = Database::connect(format!("sqlite:{}?mode=rwc", path.display()))
+            .await
+            .unwrap();
+        db.execute_unprepared(LIBRARY).await.unwrap();
+        db.close().await.unwrap();
+        let content = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let books = parse(&content).await.unwrap();
+
+        assert_eq!(books.len(), 2);
+        assert_eq!(books[0].title, "The Dispossessed");
+        assert_eq!(books[0].authors, "Ursula K. Le Guin");
+        assert_eq!(books[0].isbn.as_deref(), Some("9780060512750"));
+        assert_eq!(
+            books[0].description.as_deref(),
+            Some("An ambiguous utopia.\nAnarres\nand Urras")
+        );
+        // Only the ISBN identifiers are read
+        assert_eq!(books[1].isbn, None);
+        assert_eq!(books[1].description, None);
+    }
+
+    #[tokio::test]
+    async fn not_a_calibre_library() {
+        assert!(parse(b"not a database").await.is_err());
+    }
+}
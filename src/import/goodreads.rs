@@ -0,0 +1,84 @@
+use super::{ImportError, ImportedBook, Table};
+
+/// Reads the CSV export of Goodreads ("My Books" > "Import and export").
+///
+/// The additional authors are appended to the main one, the ISBN-13 is
+/// preferred over the ISBN-10 and the review of the member becomes the comment.
+pub fn parse(content: &str) -> Result<Vec<ImportedBook>, ImportError> {
+    let table = Table::read(content, b',')?;
+
+    let title = table.required_column(&["title"])?;
+    let author = table.column(&["author"]);
+    let additional_authors = table.column(&["additional authors"]);
+    let isbn13 = table.column(&["isbn13"]);
+    let isbn = table.column(&["isbn"]);
+    let review = table.column(&["my review"]);
+
+    Ok(table
+        .records
+        .iter()
+        .map(|record| {
+            let authors = [author, additional_authors]
+                .into_iter()
+                .filter_map(|index| Table::field(record, index))
+                .collect::<Vec<_>>()
+                .join(", ");
+
+            ImportedBook {
+                title: Table::field(record, Some(title)).unwrap_or_default(),
+                authors,
+                isbn: clean_isbn(Table::field(record, isbn13))
+                    .or_else(|| clean_isbn(Table::field(record, isbn))),
+                comment: Table::field(record, review),
+                ..Default::default()
+            }
+        })
+        .collect())
+}
+
+/// Goodreads writes ISBNs as spreadsheet formulas: `="9780439023481"`
+fn clean_isbn(isbn: Option<String>) -> Option<String> {
+    isbn.map(|isbn| isbn.trim_start_matches('=').trim_matches('"').to_string())
+        .filter(|isbn| !isbn.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Shortened export, the ISBN columns are spreadsheet formulas
+    const EXPORT: &str = r#"Book Id,Title,Author,Author l-f,Additional Authors,ISBN,ISBN13,My Rating,My Review
+2767052,The Hunger Games,Suzanne Collins,"Collins, Suzanne",,"=""0439023483""","=""9780439023481""",4,Read it twice
+13079982,Mutual Aid,Pyotr Kropotkin,"Kropotkin, Pyotr","Ashley Montagu, Nicholas Walter","=""0875580246""","=""""",0,
+18619684,The Dispossessed,Ursula K. Le Guin,"Le Guin, Ursula K.",,"=""""","=""""",5,
+"#;
+
+    #[test]
+    fn goodreads_export() {
+        let books = parse(EXPORT).unwrap();
+
+        assert_eq!(books.len(), 3);
+        assert_eq!(books[0].title, "The Hunger Games");
+        assert_eq!(books[0].authors, "Suzanne Collins");
+        assert_eq!(books[0].isbn.as_deref(), Some("9780439023481"));
+        assert_eq!(books[0].comment.as_deref(), Some("Read it twice"));
+
+        // No ISBN-13, the ISBN-10 is used
+        assert_eq!(
+            books[1].authors,
+            "Pyotr Kropotkin, Ashley Montagu, Nicholas Walter"
+        );
+        assert_eq!(books[1].isbn.as_deref(), Some("0875580246"));
+        assert_eq!(books[1].comment, None);
+
+        assert_eq!(books[2].isbn, None);
+    }
+
+    #[test]
+    fn missing_title_column() {
+        assert!(matches!(
+            parse("Book Id,Author\n1,Ursula K. Le Guin\n"),
+            Err(ImportError::MissingColumn { .. })
+        ));
+    }
+}
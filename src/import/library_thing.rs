@@ -0,0 +1,185 @@
+use serde_json::Value;
+use snafu::prelude::*;
+
+use super::{ImportError, ImportedBook, JsonSnafu, Table, non_empty};
+
+/// Reads the TSV or the JSON export of LibraryThing ("More" > "Import/Export").
+///
+/// The format is guessed from the content, a JSON export being an object
+/// of books indexed by their LibraryThing id.
+pub fn parse(content: &str) -> Result<Vec<ImportedBook>, ImportError> {
+    if content.trim_start().starts_with('{') {
+        parse_json(content)
+    } else {
+        parse_tsv(content)
+    }
+}
+
+fn parse_tsv(content: &str) -> Result<Vec<ImportedBook>, ImportError> {
+    let table = Table::read(content, b'\t')?;
+
+    let title = table.required_column(&["title"])?;
+    let primary_author = table.column(&["primary author", "author (first, last)"]);
+    let secondary_author = table.column(&["secondary author"]);
+    let isbn = table.column(&["isbn"]);
+    let isbns = table.column(&["isbns"]);
+    let description = table.column(&["description", "summary"]);
+    let comment = table.column(&["comment", "comments", "review"]);
+    let tags = table.column(&["tags"]);
+
+    Ok(table
+        .records
+        .iter()
+        .map(|record| {
+            let authors = [primary_author, secondary_author]
+                .into_iter()
+                .filter_map(|index| Table::field(record, index))
+                .collect::<Vec<_>>()
+                .join(", ");
+
+            ImportedBook {
+                title: Table::field(record, Some(title)).unwrap_or_default(),
+                authors,
+                isbn: clean_isbn(Table::field(record, isbn))
+                    .or_else(|| clean_isbn(Table::field(record, isbns))),
+                description: Table::field(record, description),
+                comment: Table::field(record, comment),
+                tags: Table::field(record, tags),
+                ..Default::default()
+            }
+        })
+        .collect())
+}
+
+fn parse_json(content: &str) -> Result<Vec<ImportedBook>, ImportError> {
+    let library: serde_json::Map<String, Value> =
+        serde_json::from_str(content).context(JsonSnafu)?;
+
+    Ok(library
+        .values()
+        .map(|book| {
+            let authors = match book.get("authors").and_then(Value::as_array) {
+                Some(authors) => authors
+                    .iter()
+                    .filter_map(|author| author.get("fl").and_then(Value::as_str))
+                    .filter(|author| !author.trim().is_empty())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                None => string(book, "primaryauthor").unwrap_or_default(),
+            };
+
+            // "isbn" is a list (or an object) of every ISBN of the book
+            let isbn = match book.get("isbn") {
+                Some(Value::Array(isbns)) => isbns.iter().filter_map(Value::as_str).last(),
+                Some(Value::Object(isbns)) => isbns.values().filter_map(Value::as_str).last(),
+                Some(Value::String(isbn)) => Some(isbn.as_str()),
+                _ => None,
+            }
+            .map(str::to_string)
+            .or_else(|| string(book, "originalisbn"));
+
+            let tags = match book.get("tags") {
+                Some(Value::Array(tags)) => non_empty(Some(
+                    tags.iter()
+                        .filter_map(Value::as_str)
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                        .as_str(),
+                )),
+                _ => string(book, "tags"),
+            };
+
+            ImportedBook {
+                title: string(book, "title").unwrap_or_default(),
+                authors,
+                isbn: clean_isbn(isbn),
+                description: string(book, "summary"),
+                comment: string(book, "comment").or_else(|| string(book, "review")),
+                tags,
+                ..Default::default()
+            }
+        })
+        .collect())
+}
+
+fn string(book: &Value, key: &str) -> Option<String> {
+    non_empty(book.get(key).and_then(Value::as_str))
+}
+
+/// LibraryThing writes ISBN lists between brackets: `[0439023483, 9780439023481]`
+fn clean_isbn(isbn: Option<String>) -> Option<String> {
+    isbn.and_then(|isbn| {
+        isbn.trim_matches(|c| c == '[' || c == ']')
+            .split(',')
+            .map(str::trim)
+            .rfind(|isbn| !isbn.is_empty())
+            .map(str::to_string)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TSV: &str = "Book Id\tTitle\tPrimary Author\tSecondary Author\tISBN\tISBNs\tSummary\tComments\tTags\n\
+        1\tThe Dispossessed\tLe Guin, Ursula K.\t\t[0060512750]\t0060512750, 9780060512750\tAn ambiguous utopia\tGreat\tscience fiction, utopia\n\
+        2\tMutual Aid\tKropotkin, Pyotr\tMontagu, Ashley\t\t[9780875580241]\t\t\t\n";
+
+    const JSON: &str = r#"{
+        "101": {
+            "title": "The Dispossessed",
+            "primaryauthor": "Le Guin, Ursula K.",
+            "authors": [{"lf": "Le Guin, Ursula K.", "fl": "Ursula K. Le Guin"}],
+            "isbn": {"0": "0060512750", "2": "9780060512750"},
+            "summary": "An ambiguous utopia",
+            "review": "Great",
+            "tags": ["science fiction", "utopia"]
+        },
+        "102": {
+            "title": "Mutual Aid",
+            "primaryauthor": "Kropotkin, Pyotr",
+            "originalisbn": "9780875580241",
+            "tags": []
+        }
+    }"#;
+
+    #[test]
+    fn library_thing_tsv() {
+        let books = parse(TSV).unwrap();
+
+        assert_eq!(books.len(), 2);
+        assert_eq!(books[0].title, "The Dispossessed");
+        assert_eq!(books[0].authors, "Le Guin, Ursula K.");
+        assert_eq!(books[0].isbn.as_deref(), Some("0060512750"));
+        assert_eq!(books[0].description.as_deref(), Some("An ambiguous utopia"));
+        assert_eq!(books[0].comment.as_deref(), Some("Great"));
+        assert_eq!(books[0].tags.as_deref(), Some("science fiction, utopia"));
+
+        // Without the ISBN column, the last one of the ISBNs list
+        assert_eq!(books[1].authors, "Kropotkin, Pyotr, Montagu, Ashley");
+        assert_eq!(books[1].isbn.as_deref(), Some("9780875580241"));
+        assert_eq!(books[1].tags, None);
+    }
+
+    #[test]
+    fn library_thing_json() {
+        let books = parse(JSON).unwrap();
+
+        assert_eq!(books.len(), 2);
+        assert_eq!(books[0].title, "The Dispossessed");
+        assert_eq!(books[0].authors, "Ursula K. Le Guin");
+        assert_eq!(books[0].isbn.as_deref(), Some("9780060512750"));
+        assert_eq!(books[0].description.as_deref(), Some("An ambiguous utopia"));
+        assert_eq!(books[0].comment.as_deref(), Some("Great"));
+        assert_eq!(books[0].tags.as_deref(), Some("science fiction, utopia"));
+
+        assert_eq!(books[1].authors, "Kropotkin, Pyotr");
+        assert_eq!(books[1].isbn.as_deref(), Some("9780875580241"));
+        assert_eq!(books[1].tags, None);
+    }
+
+    #[test]
+    fn invalid_json() {
+        assert!(matches!(parse("{\"101\": "), Err(ImportError::Json { .. })));
+    }
+}
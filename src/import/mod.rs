@@ -0,0 +1,604 @@
+use chrono::NaiveDate;
+use csv::{ReaderBuilder, StringRecord, WriterBuilder};
+use snafu::prelude::*;
+
+use crate::models::book::Model as BookModel;
+use crate::models::isbn::{self, IsbnError};
+use crate::models::user::Model as UserModel;
+use crate::routes::book::BookForm;
+
+pub mod bookforge;
+pub mod calibre;
+pub mod goodreads;
+pub mod library_thing;
+
+#[derive(Snafu, Debug)]
+#[snafu(visibility(pub))]
+pub enum ImportError {
+    /// The file is not a valid CSV file
+    #[snafu(display("Invalid CSV file"))]
+    Csv { source: csv::Error },
+    /// A column needed to import the books is missing from the header
+    #[snafu(display("Missing column {column}"))]
+    MissingColumn { column: String },
+    /// A text export is not encoded in UTF-8
+    #[snafu(display("The file is not UTF-8 text"))]
+    Encoding { source: std::str::Utf8Error },
+    /// The LibraryThing JSON export can't be read
+    #[snafu(display("Invalid JSON file"))]
+    Json { source: serde_json::Error },
+    /// The uploaded Calibre library can't be written to a temporary file
+    #[snafu(display("Can't store the uploaded library"))]
+    Io { source: std::io::Error },
+    /// The Calibre library is not a SQLite database with the expected tables
+    #[snafu(display("Invalid Calibre library"))]
+    Calibre { source: sea_orm::DbErr },
+}
+
+/// Applications whose exports can be imported
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ImportFormat {
+    /// The CSV download of BookForge
+    #[default]
+    BookForge,
+    /// The CSV export of Goodreads
+    Goodreads,
+    /// The TSV or JSON export of LibraryThing
+    LibraryThing,
+    /// The `metadata.db` SQLite database of a Calibre library
+    Calibre,
+}
+
+impl ImportFormat {
+    pub fn all() -> [ImportFormat; 4] {
+        [
+            ImportFormat::BookForge,
+            ImportFormat::Goodreads,
+            ImportFormat::LibraryThing,
+            ImportFormat::Calibre,
+        ]
+    }
+
+    /// Finds a format by the name returned by [`ImportFormat::as_str`]
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::all()
+            .into_iter()
+            .find(|format| format.as_str() == name)
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ImportFormat::BookForge => "book_forge",
+            ImportFormat::Goodreads => "goodreads",
+            ImportFormat::LibraryThing => "library_thing",
+            ImportFormat::Calibre => "calibre",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ImportFormat::BookForge => "BookForge (CSV)",
+            ImportFormat::Goodreads => "Goodreads (CSV)",
+            ImportFormat::LibraryThing => "LibraryThing (TSV, JSON)",
+            ImportFormat::Calibre => "Calibre (metadata.db)",
+        }
+    }
+}
+
+/// Why a row of the imported file can't become a book
+#[derive(Snafu, Debug)]
+#[snafu(visibility(pub))]
+pub enum RowError {
+    #[snafu(display("The title is missing"))]
+    MissingTitle,
+    #[snafu(display("The owner is missing"))]
+    MissingOwner,
+    #[snafu(display("No user matches the owner {owner}"))]
+    UnknownOwner { owner: String },
+    #[snafu(display("No user matches the current holder {holder}"))]
+    UnknownHolder { holder: String },
+    #[snafu(display("{source}"))]
+    InvalidIsbn { source: IsbnError },
+    #[snafu(display("The due date {date} is not a YYYY-MM-DD date"))]
+    InvalidDueDate { date: String },
+    #[snafu(display("You are not allowed to add books for {owner}"))]
+    Forbidden { owner: String },
+}
+
+/// Book read from an imported file, before matching the users
+#[derive(Clone, Debug, Default)]
+pub struct ImportedBook {
+    pub title: String,
+    pub authors: String,
+    pub isbn: Option<String>,
+    pub description: Option<String>,
+    pub comment: Option<String>,
+    /// Name or id of the owner
+    pub owner: Option<String>,
+    /// Name or id of the current holder
+    pub current_holder: Option<String>,
+    pub due_date: Option<String>,
+    /// Comma separated tags
+    pub tags: Option<String>,
+    /// Cover to download once the book is created
+    pub cover_url: Option<String>,
+}
+
+/// Row of the dry-run preview: the book as read, and what would be created
+#[derive(Debug)]
+pub struct ImportRow {
+    /// Position of the book in the file, starting at 1
+    pub line: usize,
+    pub book: ImportedBook,
+    pub form: Result<BookForm, Vec<RowError>>,
+    /// Existing book with the same ISBN, or the same title and authors.
+    /// Duplicates are not imported.
+    pub duplicate_of: Option<i32>,
+}
+
+impl ImportRow {
+    pub fn is_valid(&self) -> bool {
+        self.form.is_ok()
+    }
+
+    /// Valid and not already in the library
+    pub fn is_new(&self) -> bool {
+        self.is_valid() && self.duplicate_of.is_none()
+    }
+
+    pub fn errors(&self) -> Vec<String> {
+        match &self.form {
+            Ok(_) => vec![],
+            Err(errors) => errors.iter().map(|error| error.to_string()).collect(),
+        }
+    }
+}
+
+/// Reads an export in the given format.
+///
+/// Books without an owner (every book of the other applications) are given
+/// to `default_owner`, which is a user name or id like in our own CSV.
+pub async fn parse(
+    format: ImportFormat,
+    content: &[u8],
+    default_owner: Option<String>,
+) -> Result<Vec<ImportedBook>, ImportError> {
+    let mut books = match format {
+        ImportFormat::BookForge => bookforge::parse(text(content)?)?,
+        ImportFormat::Goodreads => goodreads::parse(text(content)?)?,
+        ImportFormat::LibraryThing => library_thing::parse(text(content)?)?,
+        ImportFormat::Calibre => calibre::parse(content).await?,
+    };
+
+    for book in books.iter_mut() {
+        if book.owner.is_none() {
+            book.owner = default_owner.clone();
+        }
+    }
+
+    Ok(books)
+}
+
+/// Writes the books with the column layout of our own CSV, so the preview
+/// can send them back to be imported whatever the original format.
+pub fn to_csv(books: &[ImportedBook]) -> Result<String, ImportError> {
+    let mut writer = WriterBuilder::new().from_writer(vec![]);
+    writer
+        .write_record([
+            "Title",
+            "Author(s)",
+            "ISBN",
+            "Description",
+            "Owner",
+            "Current Holder",
+            "Comment",
+            "Due Date",
+            "Tags",
+            "Cover URL",
+        ])
+        .context(CsvSnafu)?;
+
+    for book in books {
+        writer
+            .write_record([
+                book.title.as_str(),
+                book.authors.as_str(),
+                book.isbn.as_deref().unwrap_or_default(),
+                book.description.as_deref().unwrap_or_default(),
+                book.owner.as_deref().unwrap_or_default(),
+                book.current_holder.as_deref().unwrap_or_default(),
+                book.comment.as_deref().unwrap_or_default(),
+                book.due_date.as_deref().unwrap_or_default(),
+                book.tags.as_deref().unwrap_or_default(),
+                book.cover_url.as_deref().unwrap_or_default(),
+            ])
+            .context(CsvSnafu)?;
+    }
+
+    let bytes = writer
+        .into_inner()
+        .map_err(|error| error.into_error())
+        .context(IoSnafu)?;
+
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// Matches the users of every imported book, validates the fields and
+/// looks for duplicates in `existing`, without touching the database.
+pub fn preview(
+    books: Vec<ImportedBook>,
+    users: &[UserModel],
+    existing: &[BookModel],
+) -> Vec<ImportRow> {
+    books
+        .into_iter()
+        .enumerate()
+        .map(|(index, book)| {
+            let form = to_form(&book, users);
+            let duplicate_of = form
+                .as_ref()
+                .ok()
+                .and_then(|form| find_duplicate(form, existing))
+                .map(|duplicate| duplicate.id);
+
+            ImportRow {
+                line: index + 1,
+                form,
+                duplicate_of,
+                book,
+            }
+        })
+        .collect()
+}
+
+/// Table read from a CSV or TSV export, with columns found by header
+pub(crate) struct Table {
+    headers: StringRecord,
+    pub records: Vec<StringRecord>,
+}
+
+impl Table {
+    pub fn read(content: &str, delimiter: u8) -> Result<Self, ImportError> {
+        let mut reader = ReaderBuilder::new()
+            .delimiter(delimiter)
+            .flexible(true)
+            .trim(csv::Trim::All)
+            .from_reader(content.as_bytes());
+
+        let headers = reader.headers().context(CsvSnafu)?.clone();
+        let records = reader
+            .records()
+            .collect::<Result<Vec<_>, _>>()
+            .context(CsvSnafu)?;
+
+        Ok(Self { headers, records })
+    }
+
+    /// Index of the first column named like one of `names` (case insensitive)
+    pub fn column(&self, names: &[&str]) -> Option<usize> {
+        self.headers
+            .iter()
+            .position(|header| names.iter().any(|name| header.eq_ignore_ascii_case(name)))
+    }
+
+    /// Like [`Table::column`] for a column which must be there
+    pub fn required_column(&self, names: &[&str]) -> Result<usize, ImportError> {
+        self.column(names)
+            .context(MissingColumnSnafu { column: names[0] })
+    }
+
+    pub fn field(record: &StringRecord, index: Option<usize>) -> Option<String> {
+        non_empty(index.and_then(|index| record.get(index)))
+    }
+}
+
+/// Empty cells and the "-" of our own export mean no value
+pub fn non_empty(value: Option<&str>) -> Option<String> {
+    value
+        .map(str::trim)
+        .filter(|value| !value.is_empty() && *value != "-")
+        .map(str::to_string)
+}
+
+fn to_form(book: &ImportedBook, users: &[UserModel]) -> Result<BookForm, Vec<RowError>> {
+    let mut errors = vec![];
+
+    if book.title.trim().is_empty() {
+        errors.push(RowError::MissingTitle);
+    }
+
+    let owner_id = match &book.owner {
+        Some(owner) => match find_user(owner, users) {
+            Some(user) => Some(user.id),
+            None => {
+                errors.push(RowError::UnknownOwner {
+                    owner: owner.clone(),
+                });
+                None
+            }
+        },
+        None => {
+            errors.push(RowError::MissingOwner);
+            None
+        }
+    };
+
+    let current_holder_id = match &book.current_holder {
+        Some(holder) => match find_user(holder, users) {
+            Some(user) => Some(user.id),
+            None => {
+                errors.push(RowError::UnknownHolder {
+                    holder: holder.clone(),
+                });
+                None
+            }
+        },
+        None => None,
+    };
+
+    let isbn = match &book.isbn {
+        Some(raw) => match isbn::normalize(raw) {
+            Ok(isbn) => Some(isbn),
+            Err(source) => {
+                errors.push(RowError::InvalidIsbn { source });
+                None
+            }
+        },
+        None => None,
+    };
+
+    let due_date = match &book.due_date {
+        Some(date) => match NaiveDate::parse_from_str(date, "%Y-%m-%d") {
+            Ok(date) => Some(date),
+            Err(_) => {
+                errors.push(RowError::InvalidDueDate { date: date.clone() });
+                None
+            }
+        },
+        None => None,
+    };
+
+    match owner_id {
+        Some(owner_id) if errors.is_empty() => Ok(BookForm {
+            title: book.title.trim().to_string(),
+            authors: book.authors.trim().to_string(),
+            isbn,
+            owner_id,
+            description: book.description.clone(),
+            comment: book.comment.clone(),
+            current_holder_id,
+            loan_notes: None,
+            due_date,
+            tags: book.tags.clone(),
+            translators: None,
+            illustrators: None,
+            editors: None,
+            cover_url: book.cover_url.clone(),
+            remove_cover: None,
+        }),
+        _ => Err(errors),
+    }
+}
+
+/// Finds a book with the same ISBN, or with the same title and authors
+fn find_duplicate<'a>(form: &BookForm, existing: &'a [BookModel]) -> Option<&'a BookModel> {
+    existing.iter().find(|book| match (&form.isbn, &book.isbn) {
+        (Some(isbn), Some(other_isbn)) => isbn == other_isbn,
+        _ => {
+            book.title.trim().eq_ignore_ascii_case(&form.title)
+                && book.authors.trim().eq_ignore_ascii_case(&form.authors)
+        }
+    })
+}
+
+fn text(content: &[u8]) -> Result<&str, ImportError> {
+    // Spreadsheets like to start UTF-8 files with a byte order mark
+    let content = content.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(content);
+
+    std::str::from_utf8(content).context(EncodingSnafu)
+}
+
+/// Finds a user by id, by name (case insensitive),
+/// or by the "Name (id: 3)" format of our own export
+fn find_user<'a>(value: &str, users: &'a [UserModel]) -> Option<&'a UserModel> {
+    let value = value.trim();
+
+    let id = value
+        .strip_suffix(')')
+        .and_then(|value| value.rsplit_once("(id:"))
+        .map(|(_, id)| id.trim())
+        .unwrap_or(value)
+        .parse::<i32>()
+        .ok();
+
+    if let Some(id) = id
+        && let Some(user) = users.iter().find(|user| user.id == id)
+    {
+        return Some(user);
+    }
+
+    users
+        .iter()
+        .find(|user| user.name.trim().eq_ignore_ascii_case(value))
+}
+
+#[cfg(test)]
+mod tests {
+    use sea_orm::ActiveValue::Set;
+    use sea_orm::TryIntoModel;
+
+    use super::*;
+    use crate::models::{book, user};
+
+    fn users() -> Vec<UserModel> {
+        [(1, "Ursula"), (3, "Pyotr"), (7, "3")]
+            .into_iter()
+            .map(|(id, name)| {
+                user::ActiveModel {
+                    id: Set(id),
+                    name: Set(name.to_string()),
+                    password_hash: Set(None),
+                    role: Set(user::Role::Member),
+                }
+                .try_into_model()
+                .unwrap()
+            })
+            .collect()
+    }
+
+    fn existing_book(id: i32, title: &str, authors: &str, isbn: Option<&str>) -> BookModel {
+        book::ActiveModel {
+            id: Set(id),
+            title: Set(title.to_string()),
+            authors: Set(authors.to_string()),
+            isbn: Set(isbn.map(str::to_string)),
+            description: Set(None),
+            comment: Set(None),
+            cover: Set(None),
+            owner_id: Set(1),
+            current_holder_id: Set(None),
+        }
+        .try_into_model()
+        .unwrap()
+    }
+
+    fn imported(title: &str, owner: &str) -> ImportedBook {
+        ImportedBook {
+            title: title.to_string(),
+            authors: "Ursula K. Le Guin".to_string(),
+            owner: Some(owner.to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn user_found_by_export_format() {
+        let users = users();
+        let id = |value: &str| find_user(value, &users).map(|user| user.id);
+
+        assert_eq!(id("Someone renamed (id: 3)"), Some(3));
+        assert_eq!(id(" Pyotr (id:3) "), Some(3));
+        // Ids win over names
+        assert_eq!(id("3"), Some(3));
+        assert_eq!(id("ursula"), Some(1));
+        // An unknown id falls back to the whole value as a name
+        assert_eq!(id("Pyotr (id: 99)"), None);
+        assert_eq!(id("Nobody"), None);
+    }
+
+    #[test]
+    fn dash_means_empty() {
+        assert_eq!(non_empty(Some("-")), None);
+        assert_eq!(non_empty(Some(" - ")), None);
+        assert_eq!(non_empty(Some("  ")), None);
+        assert_eq!(non_empty(None), None);
+        assert_eq!(non_empty(Some(" Anarres ")), Some("Anarres".to_string()));
+    }
+
+    #[tokio::test]
+    async fn byte_order_mark_stripped() {
+        let content = "\u{FEFF}Title,Owner,Current Holder\nThe Dispossessed,Ursula (id: 1),-\n";
+
+        let books = parse(ImportFormat::BookForge, content.as_bytes(), None)
+            .await
+            .unwrap();
+
+        assert_eq!(books.len(), 1);
+        assert_eq!(books[0].title, "The Dispossessed");
+        assert_eq!(books[0].owner.as_deref(), Some("Ursula (id: 1)"));
+        assert_eq!(books[0].current_holder, None);
+    }
+
+    #[tokio::test]
+    async fn default_owner_only_when_missing() {
+        let content = "Title,Owner\nThe Dispossessed,Pyotr\nThe Word for World Is Forest,\n";
+
+        let books = parse(
+            ImportFormat::BookForge,
+            content.as_bytes(),
+            Some("Ursula".to_string()),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(books[0].owner.as_deref(), Some("Pyotr"));
+        assert_eq!(books[1].owner.as_deref(), Some("Ursula"));
+    }
+
+    #[test]
+    fn due_date_parsed() {
+        let mut book = imported("The Dispossessed", "Ursula");
+        book.current_holder = Some("Pyotr".to_string());
+        book.due_date = Some("2026-06-01".to_string());
+
+        let form = to_form(&book, &users()).unwrap();
+
+        assert_eq!(form.owner_id, 1);
+        assert_eq!(form.current_holder_id, Some(3));
+        assert_eq!(form.due_date, NaiveDate::from_ymd_opt(2026, 6, 1));
+    }
+
+    #[test]
+    fn row_errors_collected() {
+        let mut book = imported(" ", "Nobody");
+        book.due_date = Some("01/06/2026".to_string());
+        book.isbn = Some("978044117271".to_string());
+
+        let errors: Vec<String> = to_form(&book, &users())
+            .unwrap_err()
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+
+        assert_eq!(
+            errors,
+            [
+                "The title is missing",
+                "No user matches the owner Nobody",
+                "ISBN 978044117271 must have 10 or 13 digits",
+                "The due date 01/06/2026 is not a YYYY-MM-DD date",
+            ]
+        );
+
+        let mut book = imported("The Dispossessed", "Ursula");
+        book.owner = None;
+        assert!(matches!(
+            to_form(&book, &users()).unwrap_err().as_slice(),
+            [RowError::MissingOwner]
+        ));
+    }
+
+    #[test]
+    fn duplicates_detected() {
+        let existing = [
+            existing_book(10, "The Dispossessed", "Ursula K. Le Guin", None),
+            existing_book(11, "Mutual Aid", "Pyotr Kropotkin", Some("9780441172719")),
+        ];
+        let mut by_isbn = imported("Another title", "Ursula");
+        by_isbn.isbn = Some("0-441-17271-7".to_string());
+        let mut other_isbn = imported("Mutual Aid", "Ursula");
+        other_isbn.authors = "Pyotr Kropotkin".to_string();
+        other_isbn.isbn = Some("978-0-8044-2957-3".to_string());
+
+        let rows = preview(
+            vec![
+                imported("the dispossessed ", "Ursula"),
+                by_isbn,
+                other_isbn,
+                imported("The Left Hand of Darkness", "Ursula"),
+                imported("The Dispossessed", "Nobody"),
+            ],
+            &users(),
+            &existing,
+        );
+
+        let duplicates: Vec<Option<i32>> = rows.iter().map(|row| row.duplicate_of).collect();
+        // Another edition, with its own ISBN, is not a duplicate
+        assert_eq!(duplicates, [Some(10), Some(11), None, None, None]);
+        assert_eq!(
+            rows.iter().map(ImportRow::is_new).collect::<Vec<_>>(),
+            [false, false, true, true, false]
+        );
+        assert_eq!(rows[4].line, 5);
+    }
+}
@@ -2,7 +2,8 @@ use askama::Template;
 use askama_web::WebTemplate;
 use axum::{
     Router,
-    extract::State,
+    extract::DefaultBodyLimit,
+    middleware,
     routing::{get, post},
 };
 use static_serve::embed_assets;
@@ -10,10 +11,17 @@ use static_serve::embed_assets;
 use crate::routes::router::Router as InternalRouter;
 use crate::state::AppState;
 
+mod auth;
+pub mod cli;
+mod covers;
+mod csrf;
+mod import;
+mod metadata;
 mod migrations;
 mod models;
 mod routes;
 pub mod state;
+mod storage;
 
 #[macro_use]
 extern crate rust_i18n;
@@ -27,21 +35,86 @@ pub fn build_app(state: AppState) -> Router {
     Router::new()
         .route("/", get(routes::book::index))
         .route("/books/new", get(routes::book::new))
-        .route("/books", post(routes::book::create))
+        .route(
+            "/books",
+            post(routes::book::create).layer(DefaultBodyLimit::max(routes::book::MAX_FORM_SIZE)),
+        )
         .route("/books/{id}", get(routes::book::show))
-        .route("/books/{id}", post(routes::book::update))
+        .route(
+            "/books/{id}",
+            post(routes::book::update).layer(DefaultBodyLimit::max(routes::book::MAX_FORM_SIZE)),
+        )
+        .route("/books/{id}/cover", get(routes::book::cover))
+        .route("/books/{id}/thumbnail", get(routes::book::thumbnail))
         .route("/books/{id}/delete", post(routes::book::delete))
+        .route("/books/{id}/return", post(routes::book::give_back))
+        .route("/books/{id}/requests", post(routes::borrow_request::create))
+        .route("/books/{id}/waitlist", post(routes::waitlist::join))
+        .route("/books/{id}/waitlist/leave", post(routes::waitlist::leave))
+        .route("/books/{id}/waitlist/clear", post(routes::waitlist::clear))
+        .route("/books/{id}/waitlist/skip", post(routes::waitlist::skip))
+        .route("/waitlist/{id}/up", post(routes::waitlist::move_up))
+        .route("/waitlist/{id}/down", post(routes::waitlist::move_down))
         .route("/books/{id}/edit", get(routes::book::edit))
         .route("/books/search", get(routes::book::search))
         .route("/books/download_csv", get(routes::book::download_csv))
+        .route("/books/import", get(routes::import::new))
+        .route(
+            "/books/import",
+            post(routes::import::create)
+                .layer(DefaultBodyLimit::max(routes::import::MAX_UPLOAD_SIZE)),
+        )
+        .route(
+            "/books/import/preview",
+            post(routes::import::preview)
+                .layer(DefaultBodyLimit::max(routes::import::MAX_UPLOAD_SIZE)),
+        )
+        .route("/requests", get(routes::borrow_request::index))
+        .route(
+            "/requests/{id}/approve",
+            post(routes::borrow_request::approve),
+        )
+        .route(
+            "/requests/{id}/decline",
+            post(routes::borrow_request::decline),
+        )
+        .route(
+            "/requests/{id}/cancel",
+            post(routes::borrow_request::cancel),
+        )
+        .route("/tags", get(routes::tag::index))
+        .route("/tags/{id}", post(routes::tag::update))
+        .route("/tags/{id}/delete", post(routes::tag::delete))
+        .route("/authors", get(routes::author::index))
+        .route("/authors/{id}", get(routes::author::show))
+        .route("/authors/{id}", post(routes::author::update))
+        .route("/backup", get(routes::backup::index))
+        .route("/backup/download", get(routes::backup::download))
+        .route(
+            "/backup/restore",
+            post(routes::backup::restore)
+                .layer(DefaultBodyLimit::max(routes::import::MAX_UPLOAD_SIZE)),
+        )
+        .route("/login", get(routes::session::new))
+        .route("/login", post(routes::session::create))
+        .route("/logout", post(routes::session::delete))
         .route("/users", get(routes::user::index))
         .route("/users/new", get(routes::user::new))
+        .route("/users/{id}", get(routes::user::show))
         .route("/users/{id}/edit", get(routes::user::edit))
         .route("/users/{id}", post(routes::user::update))
         .route("/users", post(routes::user::create))
         .route("/users/{id}/delete", post(routes::user::delete))
+        .nest("/api/v1", routes::api::router())
         .nest("/assets", static_router())
         .fallback(error_handler)
+        // The CSRF protection needs the session loaded by the authentication,
+        // its `body_limit` follows the `DefaultBodyLimit` of the routes above
+        .layer(middleware::from_fn(csrf::protect))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            auth::authenticate,
+        ))
         .with_state(state)
 }
 
@@ -51,10 +124,6 @@ struct NotFoundTemplate {
     pub router: InternalRouter,
 }
 
-pub async fn error_handler(State(state): State<AppState>) -> impl axum::response::IntoResponse {
-    NotFoundTemplate {
-        router: InternalRouter {
-            base_path: state.config.base_path,
-        },
-    }
+pub async fn error_handler(router: InternalRouter) -> impl axum::response::IntoResponse {
+    NotFoundTemplate { router }
 }
@@ -0,0 +1,289 @@
+use async_trait::async_trait;
+use serde::Deserialize;
+use snafu::prelude::*;
+
+use crate::metadata::{
+    BookMetadata, InvalidResponseSnafu, MetadataError, MetadataProvider, MetadataQuery,
+    RequestSnafu,
+};
+
+const NAME: &str = "Google Books";
+
+/// Metadata provider backed by the Google Books volumes API
+pub struct GoogleBooksProvider {
+    client: reqwest::Client,
+    base_url: String,
+    api_key: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct VolumesResponse {
+    #[serde(default)]
+    items: Vec<Volume>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct Volume {
+    volume_info: VolumeInfo,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct VolumeInfo {
+    title: String,
+    #[serde(default)]
+    authors: Vec<String>,
+    description: Option<String>,
+    publisher: Option<String>,
+    published_date: Option<String>,
+    #[serde(default)]
+    industry_identifiers: Vec<IndustryIdentifier>,
+    image_links: Option<ImageLinks>,
+}
+
+#[derive(Deserialize, Debug)]
+struct IndustryIdentifier {
+    #[serde(rename = "type")]
+    kind: String,
+    identifier: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct ImageLinks {
+    thumbnail: Option<String>,
+}
+
+impl From<VolumeInfo> for BookMetadata {
+    fn from(info: VolumeInfo) -> Self {
+        // Prefer the ISBN-13, fallback on the ISBN-10
+        let isbn = ["ISBN_13", "ISBN_10"].iter().find_map(|kind| {
+            info.industry_identifiers
+                .iter()
+                .find(|identifier| identifier.kind == *kind)
+                .map(|identifier| identifier.identifier.clone())
+        });
+
+        // publishedDate is "2004", "2004-05" or "2004-05-01"
+        let year = info
+            .published_date
+            .as_deref()
+            .and_then(|date| date.get(0..4))
+            .and_then(|year| year.parse().ok());
+
+        BookMetadata {
+            provider: NAME.to_string(),
+            title: info.title,
+            authors: info.authors,
+            description: info.description,
+            isbn,
+            cover_url: info.image_links.and_then(|links| links.thumbnail),
+            publisher: info.publisher,
+            year,
+        }
+    }
+}
+
+impl GoogleBooksProvider {
+    /// Creates a provider querying the API at `base_url` (e.g. `https://www.googleapis.com/books/v1`)
+    pub fn new(base_url: String, api_key: String) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            base_url,
+            api_key,
+        }
+    }
+
+    fn query_string(query: &MetadataQuery) -> String {
+        match query {
+            MetadataQuery::Isbn(isbn) => format!("isbn:{}", isbn),
+            MetadataQuery::Title {
+                title,
+                authors: Some(authors),
+            } => format!("intitle:{} inauthor:{}", title, authors),
+            MetadataQuery::Title {
+                title,
+                authors: None,
+            } => format!("intitle:{}", title),
+        }
+    }
+}
+
+#[async_trait]
+impl MetadataProvider for GoogleBooksProvider {
+    fn name(&self) -> &'static str {
+        NAME
+    }
+
+    async fn search(
+        &self,
+        query: &MetadataQuery,
+        max_results: usize,
+    ) -> Result<Vec<BookMetadata>, MetadataError> {
+        let mut params = vec![
+            ("q", Self::query_string(query)),
+            ("maxResults", max_results.to_string()),
+        ];
+        if !self.api_key.is_empty() {
+            params.push(("key", self.api_key.clone()));
+        }
+
+        let response: VolumesResponse = self
+            .client
+            .get(format!("{}/volumes", self.base_url.trim_end_matches('/')))
+            .query(&params)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .context(RequestSnafu { provider: NAME })?
+            .json()
+            .await
+            .context(InvalidResponseSnafu { provider: NAME })?;
+
+        Ok(response
+            .items
+            .into_iter()
+            .map(|volume| volume.volume_info.into())
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use wiremock::matchers::{method, path, query_param, query_param_is_missing};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use super::*;
+
+    fn volume() -> serde_json::Value {
+        json!({
+            "volumeInfo": {
+                "title": "Dune",
+                "authors": ["Frank Herbert"],
+                "description": "A desert planet",
+                "publisher": "Chilton Books",
+                "publishedDate": "1965-08-01",
+                "industryIdentifiers": [
+                    { "type": "ISBN_10", "identifier": "0441172717" },
+                    { "type": "ISBN_13", "identifier": "9780441172719" }
+                ],
+                "imageLinks": { "thumbnail": "http://books.google.com/dune.jpg" }
+            }
+        })
+    }
+
+    #[tokio::test]
+    async fn search_by_isbn() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/volumes"))
+            .and(query_param("q", "isbn:9780441172719"))
+            .and(query_param("maxResults", "5"))
+            .and(query_param("key", "secret"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "items": [volume()] })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let provider = GoogleBooksProvider::new(server.uri(), "secret".to_string());
+        let books = provider
+            .search(&MetadataQuery::Isbn("9780441172719".to_string()), 5)
+            .await
+            .unwrap();
+
+        assert_eq!(books.len(), 1);
+        let book = &books[0];
+        assert_eq!(book.provider, NAME);
+        assert_eq!(book.title, "Dune");
+        assert_eq!(book.authors, vec!["Frank Herbert".to_string()]);
+        assert_eq!(book.isbn.as_deref(), Some("9780441172719"));
+        assert_eq!(book.publisher.as_deref(), Some("Chilton Books"));
+        assert_eq!(book.year, Some(1965));
+        assert_eq!(
+            book.cover_url.as_deref(),
+            Some("http://books.google.com/dune.jpg")
+        );
+    }
+
+    #[tokio::test]
+    async fn search_by_title_and_authors() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/volumes"))
+            .and(query_param("q", "intitle:Dune inauthor:Frank Herbert"))
+            .and(query_param_is_missing("key"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "items": [volume()] })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let provider = GoogleBooksProvider::new(format!("{}/", server.uri()), String::new());
+        let books = provider
+            .search(
+                &MetadataQuery::Title {
+                    title: "Dune".to_string(),
+                    authors: Some("Frank Herbert".to_string()),
+                },
+                5,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(books.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn search_without_results() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/volumes"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "totalItems": 0 })))
+            .mount(&server)
+            .await;
+
+        let provider = GoogleBooksProvider::new(server.uri(), String::new());
+        let books = provider
+            .search(&MetadataQuery::Isbn("9780000000000".to_string()), 5)
+            .await
+            .unwrap();
+
+        assert!(books.is_empty());
+    }
+
+    #[tokio::test]
+    async fn search_error_status() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/volumes"))
+            .respond_with(ResponseTemplate::new(403))
+            .mount(&server)
+            .await;
+
+        let provider = GoogleBooksProvider::new(server.uri(), String::new());
+        let error = provider
+            .search(&MetadataQuery::Isbn("9780441172719".to_string()), 5)
+            .await
+            .unwrap_err();
+
+        assert!(matches!(error, MetadataError::Request { .. }));
+    }
+
+    #[tokio::test]
+    async fn search_invalid_body() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/volumes"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("<html>"))
+            .mount(&server)
+            .await;
+
+        let provider = GoogleBooksProvider::new(server.uri(), String::new());
+        let error = provider
+            .search(&MetadataQuery::Isbn("9780441172719".to_string()), 5)
+            .await
+            .unwrap_err();
+
+        assert!(matches!(error, MetadataError::InvalidResponse { .. }));
+    }
+}
@@ -0,0 +1,137 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use snafu::prelude::*;
+
+use crate::state::{api_config::ApiConfig, metadata_config::MetadataConfig};
+
+pub mod google_books;
+pub mod open_library;
+
+pub use google_books::GoogleBooksProvider;
+pub use open_library::OpenLibraryProvider;
+
+#[derive(Snafu, Debug)]
+#[snafu(visibility(pub))]
+pub enum MetadataError {
+    /// The request to the provider failed or returned an error status
+    #[snafu(display("Request to {provider} failed"))]
+    Request {
+        provider: String,
+        source: reqwest::Error,
+    },
+    /// The provider answered with a body we don't understand
+    #[snafu(display("Invalid response from {provider}"))]
+    InvalidResponse {
+        provider: String,
+        source: reqwest::Error,
+    },
+    /// No provider is enabled in the configuration
+    #[snafu(display("No metadata provider configured"))]
+    NoProvider,
+}
+
+/// What we are looking for on the metadata providers
+#[derive(Clone, Debug)]
+pub enum MetadataQuery {
+    /// Normalised ISBN-13
+    Isbn(String),
+    /// Title with optional authors
+    Title {
+        title: String,
+        authors: Option<String>,
+    },
+}
+
+/// Book metadata normalised across every provider
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct BookMetadata {
+    /// Name of the provider which returned this result
+    pub provider: String,
+    pub title: String,
+    pub authors: Vec<String>,
+    pub description: Option<String>,
+    pub isbn: Option<String>,
+    pub cover_url: Option<String>,
+    pub publisher: Option<String>,
+    pub year: Option<i32>,
+}
+
+/// A source of book metadata (Google Books, Open Library, ...)
+#[async_trait]
+pub trait MetadataProvider: Send + Sync {
+    /// Human readable name of the provider
+    fn name(&self) -> &'static str;
+
+    /// Searches books matching the query, returns at most `max_results` books.
+    async fn search(
+        &self,
+        query: &MetadataQuery,
+        max_results: usize,
+    ) -> Result<Vec<BookMetadata>, MetadataError>;
+}
+
+/// Kind of metadata provider which can be enabled in the configuration
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MetadataProviderKind {
+    GoogleBooks,
+    OpenLibrary,
+}
+
+/// Builds the providers enabled in the configuration, in the configured order.
+pub fn providers(
+    config: &MetadataConfig,
+    api_config: &ApiConfig,
+) -> Vec<Box<dyn MetadataProvider>> {
+    config
+        .providers
+        .iter()
+        .map(|kind| -> Box<dyn MetadataProvider> {
+            match kind {
+                MetadataProviderKind::GoogleBooks => Box::new(GoogleBooksProvider::new(
+                    config.google_books_url.clone(),
+                    api_config.google_books_api_key.clone(),
+                )),
+                MetadataProviderKind::OpenLibrary => {
+                    Box::new(OpenLibraryProvider::new(config.open_library_url.clone()))
+                }
+            }
+        })
+        .collect()
+}
+
+/// Queries every provider in order and concatenates their results.
+///
+/// A failing provider is skipped as long as another one answers.
+/// For an ISBN, the first provider which knows the book wins.
+pub async fn search(
+    providers: &[Box<dyn MetadataProvider>],
+    query: &MetadataQuery,
+    max_results: usize,
+) -> Result<Vec<BookMetadata>, MetadataError> {
+    ensure!(!providers.is_empty(), NoProviderSnafu);
+
+    let mut results = Vec::new();
+    let mut last_error = None;
+
+    for provider in providers {
+        match provider.search(query, max_results).await {
+            Ok(books) => {
+                results.extend(books);
+
+                if matches!(query, MetadataQuery::Isbn(_)) && !results.is_empty() {
+                    break;
+                }
+            }
+            Err(error) => {
+                log::warn!("Metadata provider {} failed: {}", provider.name(), error);
+                last_error = Some(error);
+            }
+        }
+    }
+
+    match last_error {
+        Some(error) if results.is_empty() => Err(error),
+        _ => Ok(results),
+    }
+}
@@ -0,0 +1,243 @@
+use async_trait::async_trait;
+use serde::Deserialize;
+use snafu::prelude::*;
+
+use crate::metadata::{
+    BookMetadata, InvalidResponseSnafu, MetadataError, MetadataProvider, MetadataQuery,
+    RequestSnafu,
+};
+
+const NAME: &str = "Open Library";
+
+/// Metadata provider backed by the Open Library search API
+pub struct OpenLibraryProvider {
+    client: reqwest::Client,
+    base_url: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct SearchResponse {
+    #[serde(default)]
+    docs: Vec<Doc>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Doc {
+    title: String,
+    #[serde(default)]
+    author_name: Vec<String>,
+    #[serde(default)]
+    isbn: Vec<String>,
+    #[serde(default)]
+    publisher: Vec<String>,
+    first_publish_year: Option<i32>,
+    cover_i: Option<i64>,
+    first_sentence: Option<Vec<String>>,
+}
+
+impl From<Doc> for BookMetadata {
+    fn from(doc: Doc) -> Self {
+        // Prefer the ISBN-13, fallback on the first ISBN
+        let isbn = doc
+            .isbn
+            .iter()
+            .find(|isbn| isbn.len() == 13)
+            .or_else(|| doc.isbn.first())
+            .cloned();
+
+        BookMetadata {
+            provider: NAME.to_string(),
+            title: doc.title,
+            authors: doc.author_name,
+            description: doc
+                .first_sentence
+                .and_then(|sentences| sentences.first().cloned()),
+            isbn,
+            cover_url: doc
+                .cover_i
+                .map(|id| format!("https://covers.openlibrary.org/b/id/{}-M.jpg", id)),
+            publisher: doc.publisher.into_iter().next(),
+            year: doc.first_publish_year,
+        }
+    }
+}
+
+impl OpenLibraryProvider {
+    /// Creates a provider querying the API at `base_url` (e.g. `https://openlibrary.org`)
+    pub fn new(base_url: String) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            base_url,
+        }
+    }
+}
+
+#[async_trait]
+impl MetadataProvider for OpenLibraryProvider {
+    fn name(&self) -> &'static str {
+        NAME
+    }
+
+    async fn search(
+        &self,
+        query: &MetadataQuery,
+        max_results: usize,
+    ) -> Result<Vec<BookMetadata>, MetadataError> {
+        let mut params = vec![("limit", max_results.to_string())];
+        match query {
+            MetadataQuery::Isbn(isbn) => params.push(("isbn", isbn.clone())),
+            MetadataQuery::Title { title, authors } => {
+                params.push(("title", title.clone()));
+                if let Some(authors) = authors {
+                    params.push(("author", authors.clone()));
+                }
+            }
+        }
+
+        let response: SearchResponse = self
+            .client
+            .get(format!(
+                "{}/search.json",
+                self.base_url.trim_end_matches('/')
+            ))
+            .query(&params)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .context(RequestSnafu { provider: NAME })?
+            .json()
+            .await
+            .context(InvalidResponseSnafu { provider: NAME })?;
+
+        Ok(response.docs.into_iter().map(BookMetadata::from).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use wiremock::matchers::{method, path, query_param, query_param_is_missing};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use super::*;
+
+    fn doc() -> serde_json::Value {
+        json!({
+            "title": "Dune",
+            "author_name": ["Frank Herbert"],
+            "isbn": ["0441172717", "9780441172719"],
+            "publisher": ["Chilton Books", "Ace"],
+            "first_publish_year": 1965,
+            "cover_i": 12345,
+            "first_sentence": ["In the week before their departure to Arrakis..."]
+        })
+    }
+
+    #[tokio::test]
+    async fn search_by_isbn() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/search.json"))
+            .and(query_param("isbn", "9780441172719"))
+            .and(query_param("limit", "5"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "docs": [doc()] })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let provider = OpenLibraryProvider::new(server.uri());
+        let books = provider
+            .search(&MetadataQuery::Isbn("9780441172719".to_string()), 5)
+            .await
+            .unwrap();
+
+        assert_eq!(books.len(), 1);
+        let book = &books[0];
+        assert_eq!(book.provider, NAME);
+        assert_eq!(book.title, "Dune");
+        assert_eq!(book.authors, vec!["Frank Herbert".to_string()]);
+        assert_eq!(book.isbn.as_deref(), Some("9780441172719"));
+        assert_eq!(book.publisher.as_deref(), Some("Chilton Books"));
+        assert_eq!(book.year, Some(1965));
+        assert_eq!(
+            book.cover_url.as_deref(),
+            Some("https://covers.openlibrary.org/b/id/12345-M.jpg")
+        );
+        assert_eq!(
+            book.description.as_deref(),
+            Some("In the week before their departure to Arrakis...")
+        );
+    }
+
+    #[tokio::test]
+    async fn search_by_title_and_authors() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/search.json"))
+            .and(query_param("title", "Dune"))
+            .and(query_param("author", "Frank Herbert"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "docs": [doc()] })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let provider = OpenLibraryProvider::new(format!("{}/", server.uri()));
+        let books = provider
+            .search(
+                &MetadataQuery::Title {
+                    title: "Dune".to_string(),
+                    authors: Some("Frank Herbert".to_string()),
+                },
+                5,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(books.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn search_by_title_only() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/search.json"))
+            .and(query_param("title", "Dune"))
+            .and(query_param_is_missing("author"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "docs": [] })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let provider = OpenLibraryProvider::new(server.uri());
+        let books = provider
+            .search(
+                &MetadataQuery::Title {
+                    title: "Dune".to_string(),
+                    authors: None,
+                },
+                5,
+            )
+            .await
+            .unwrap();
+
+        assert!(books.is_empty());
+    }
+
+    #[tokio::test]
+    async fn search_error_status() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/search.json"))
+            .respond_with(ResponseTemplate::new(500))
+            .mount(&server)
+            .await;
+
+        let provider = OpenLibraryProvider::new(server.uri());
+        let error = provider
+            .search(&MetadataQuery::Isbn("9780441172719".to_string()), 5)
+            .await
+            .unwrap_err();
+
+        assert!(matches!(error, MetadataError::Request { .. }));
+    }
+}
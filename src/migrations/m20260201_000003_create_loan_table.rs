@@ -0,0 +1,64 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::migrations::m20260126_000001_create_user_table::User;
+use crate::migrations::m20260126_000002_create_book_table::Book;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Loan::Table)
+                    .if_not_exists()
+                    .col(pk_auto(Loan::Id))
+                    .col(ColumnDef::new(Loan::BookId).integer().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-loan-book_id")
+                            .from(Loan::Table, Loan::BookId)
+                            .to(Book::Table, Book::Id),
+                    )
+                    .col(ColumnDef::new(Loan::BorrowerId).integer().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-loan-borrower_id")
+                            .from(Loan::Table, Loan::BorrowerId)
+                            .to(User::Table, User::Id),
+                    )
+                    .col(ColumnDef::new(Loan::LenderId).integer().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-loan-lender_id")
+                            .from(Loan::Table, Loan::LenderId)
+                            .to(User::Table, User::Id),
+                    )
+                    .col(date(Loan::StartDate))
+                    .col(date_null(Loan::ReturnDate))
+                    .col(text_null(Loan::Notes))
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Loan::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+pub enum Loan {
+    Table,
+    Id,
+    BookId,
+    BorrowerId,
+    LenderId,
+    StartDate,
+    ReturnDate,
+    Notes,
+}
@@ -0,0 +1,56 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::migrations::m20260126_000002_create_book_table::Book;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Book::Table)
+                    .add_column(string_null(BookIsbn::Isbn))
+                    .to_owned(),
+            )
+            .await?;
+
+        // Not unique: the same edition can be owned by several users
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-book-isbn")
+                    .table(Book::Table)
+                    .col(BookIsbn::Isbn)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx-book-isbn")
+                    .table(Book::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Book::Table)
+                    .drop_column(BookIsbn::Isbn)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+pub enum BookIsbn {
+    Isbn,
+}
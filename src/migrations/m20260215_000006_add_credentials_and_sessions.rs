@@ -0,0 +1,68 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::migrations::m20260126_000001_create_user_table::User;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Users created before the authentication have no password
+        // and can't log in until one is set
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(User::Table)
+                    .add_column(string_null(UserCredentials::PasswordHash))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(Session::Table)
+                    .if_not_exists()
+                    .col(string(Session::Id).primary_key())
+                    .col(ColumnDef::new(Session::UserId).integer().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-session-user_id")
+                            .from(Session::Table, Session::UserId)
+                            .to(User::Table, User::Id),
+                    )
+                    .col(date_time(Session::ExpiresAt))
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Session::Table).to_owned())
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(User::Table)
+                    .drop_column(UserCredentials::PasswordHash)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+pub enum UserCredentials {
+    PasswordHash,
+}
+
+#[derive(DeriveIden)]
+pub enum Session {
+    Table,
+    Id,
+    UserId,
+    ExpiresAt,
+}
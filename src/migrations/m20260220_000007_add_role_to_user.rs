@@ -0,0 +1,59 @@
+use sea_orm_migration::sea_orm::DbBackend;
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::migrations::m20260126_000001_create_user_table::User;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(User::Table)
+                    .add_column(string(UserRole::Role).default("member"))
+                    .to_owned(),
+            )
+            .await?;
+
+        // MySQL can't update the table selected in the subquery,
+        // `m20260501_000018` promotes the first user there
+        if manager.get_database_backend() == DbBackend::MySql {
+            return Ok(());
+        }
+
+        // Promote the first user so that someone can still manage the users
+        let first_user = Query::select()
+            .expr(Expr::col(User::Id).min())
+            .from(User::Table)
+            .to_owned();
+
+        manager
+            .exec_stmt(
+                Query::update()
+                    .table(User::Table)
+                    .value(UserRole::Role, "admin")
+                    .and_where(Expr::col(User::Id).in_subquery(first_user))
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(User::Table)
+                    .drop_column(UserRole::Role)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+pub enum UserRole {
+    Role,
+}
@@ -0,0 +1,70 @@
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::sea_orm::{ConnectionTrait, DbBackend};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+/// FTS5 index of the books, kept in sync with the book table by triggers.
+///
+/// `remove_diacritics 2` makes "etranger" match "L'Étranger".
+/// Only SQLite has FTS5, the other databases fall back to `LIKE` filters.
+const UP: &str = r#"
+CREATE VIRTUAL TABLE book_search USING fts5(
+    title,
+    authors,
+    description,
+    comment,
+    content = 'book',
+    content_rowid = 'id',
+    tokenize = 'unicode61 remove_diacritics 2'
+);
+
+CREATE TRIGGER book_search_insert AFTER INSERT ON book BEGIN
+    INSERT INTO book_search (rowid, title, authors, description, comment)
+    VALUES (new.id, new.title, new.authors, new.description, new.comment);
+END;
+
+CREATE TRIGGER book_search_delete AFTER DELETE ON book BEGIN
+    INSERT INTO book_search (book_search, rowid, title, authors, description, comment)
+    VALUES ('delete', old.id, old.title, old.authors, old.description, old.comment);
+END;
+
+CREATE TRIGGER book_search_update AFTER UPDATE ON book BEGIN
+    INSERT INTO book_search (book_search, rowid, title, authors, description, comment)
+    VALUES ('delete', old.id, old.title, old.authors, old.description, old.comment);
+    INSERT INTO book_search (rowid, title, authors, description, comment)
+    VALUES (new.id, new.title, new.authors, new.description, new.comment);
+END;
+
+INSERT INTO book_search (book_search) VALUES ('rebuild');
+"#;
+
+const DOWN: &str = r#"
+DROP TRIGGER IF EXISTS book_search_update;
+DROP TRIGGER IF EXISTS book_search_delete;
+DROP TRIGGER IF EXISTS book_search_insert;
+DROP TABLE IF EXISTS book_search;
+"#;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        if manager.get_database_backend() != DbBackend::Sqlite {
+            return Ok(());
+        }
+
+        manager.get_connection().execute_unprepared(UP).await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        if manager.get_database_backend() != DbBackend::Sqlite {
+            return Ok(());
+        }
+
+        manager.get_connection().execute_unprepared(DOWN).await?;
+
+        Ok(())
+    }
+}
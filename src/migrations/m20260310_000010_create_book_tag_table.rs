@@ -0,0 +1,60 @@
+use sea_orm_migration::prelude::*;
+
+use crate::migrations::m20260126_000002_create_book_table::Book;
+use crate::migrations::m20260310_000009_create_tag_table::Tag;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(BookTag::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(BookTag::BookId).integer().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-book_tag-book_id")
+                            .from(BookTag::Table, BookTag::BookId)
+                            .to(Book::Table, Book::Id),
+                    )
+                    .col(ColumnDef::new(BookTag::TagId).integer().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-book_tag-tag_id")
+                            .from(BookTag::Table, BookTag::TagId)
+                            .to(Tag::Table, Tag::Id),
+                    )
+                    .primary_key(Index::create().col(BookTag::BookId).col(BookTag::TagId))
+                    .to_owned(),
+            )
+            .await?;
+
+        // The tag filter looks for the books of a tag
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-book_tag-tag_id")
+                    .table(BookTag::Table)
+                    .col(BookTag::TagId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(BookTag::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+pub enum BookTag {
+    Table,
+    BookId,
+    TagId,
+}
@@ -0,0 +1,92 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::migrations::m20260126_000002_create_book_table::Book;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Author::Table)
+                    .if_not_exists()
+                    .col(pk_auto(Author::Id))
+                    .col(string(Author::Name).unique_key())
+                    .to_owned(),
+            )
+            .await?;
+
+        // A person can have several roles on the same book,
+        // e.g. author and illustrator
+        manager
+            .create_table(
+                Table::create()
+                    .table(BookAuthor::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(BookAuthor::BookId).integer().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-book_author-book_id")
+                            .from(BookAuthor::Table, BookAuthor::BookId)
+                            .to(Book::Table, Book::Id),
+                    )
+                    .col(ColumnDef::new(BookAuthor::AuthorId).integer().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-book_author-author_id")
+                            .from(BookAuthor::Table, BookAuthor::AuthorId)
+                            .to(Author::Table, Author::Id),
+                    )
+                    .col(string(BookAuthor::Role).default("author"))
+                    .col(integer(BookAuthor::Position).default(0))
+                    .primary_key(
+                        Index::create()
+                            .col(BookAuthor::BookId)
+                            .col(BookAuthor::AuthorId)
+                            .col(BookAuthor::Role),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // The author page looks for the books of an author
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-book_author-author_id")
+                    .table(BookAuthor::Table)
+                    .col(BookAuthor::AuthorId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(BookAuthor::Table).to_owned())
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(Author::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+pub enum Author {
+    Table,
+    Id,
+    Name,
+}
+
+#[derive(DeriveIden)]
+pub enum BookAuthor {
+    Table,
+    BookId,
+    AuthorId,
+    Role,
+    Position,
+}
@@ -0,0 +1,155 @@
+use std::collections::HashMap;
+
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::sea_orm::FromQueryResult;
+
+use crate::migrations::m20260126_000002_create_book_table::Book;
+use crate::migrations::m20260320_000011_create_author_tables::{Author, BookAuthor};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+/// Rows inserted per statement, to stay below the bind parameter limits
+const CHUNK_SIZE: usize = 500;
+
+#[derive(FromQueryResult)]
+struct BookAuthors {
+    id: i32,
+    authors: String,
+}
+
+#[derive(FromQueryResult)]
+struct AuthorRow {
+    id: i32,
+    name: String,
+}
+
+/// Creates an author for every name found in the free text `book.authors`,
+/// and credits them on their books.
+///
+/// Names are separated by `,`, `;` or `&`. The same name with another case
+/// is the same author, spelled like its first occurrence.
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        let backend = manager.get_database_backend();
+
+        let books = BookAuthors::find_by_statement(
+            backend.build(
+                &Query::select()
+                    .columns([Book::Id, Book::Authors])
+                    .from(Book::Table)
+                    .to_owned(),
+            ),
+        )
+        .all(db)
+        .await?;
+
+        let names_by_book: Vec<(i32, Vec<String>)> = books
+            .into_iter()
+            .map(|book| (book.id, split_names(&book.authors)))
+            .collect();
+
+        let mut names: Vec<String> = Vec::new();
+        for (_, book_names) in &names_by_book {
+            for name in book_names {
+                if !names
+                    .iter()
+                    .any(|other| other.to_lowercase() == name.to_lowercase())
+                {
+                    names.push(name.clone());
+                }
+            }
+        }
+
+        for chunk in names.chunks(CHUNK_SIZE) {
+            let mut insert = Query::insert();
+            insert.into_table(Author::Table).columns([Author::Name]);
+            for name in chunk {
+                insert.values_panic([name.clone().into()]);
+            }
+            manager.exec_stmt(insert).await?;
+        }
+
+        let author_ids: HashMap<String, i32> = AuthorRow::find_by_statement(
+            backend.build(
+                &Query::select()
+                    .columns([Author::Id, Author::Name])
+                    .from(Author::Table)
+                    .to_owned(),
+            ),
+        )
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|author| (author.name.to_lowercase(), author.id))
+        .collect();
+
+        let credits: Vec<(i32, i32, i32)> = names_by_book
+            .iter()
+            .flat_map(|(book_id, book_names)| {
+                book_names
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(position, name)| {
+                        let author_id = author_ids.get(&name.to_lowercase())?;
+                        Some((*book_id, *author_id, position as i32))
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+
+        for chunk in credits.chunks(CHUNK_SIZE) {
+            let mut insert = Query::insert();
+            insert.into_table(BookAuthor::Table).columns([
+                BookAuthor::BookId,
+                BookAuthor::AuthorId,
+                BookAuthor::Role,
+                BookAuthor::Position,
+            ]);
+            for (book_id, author_id, position) in chunk {
+                insert.values_panic([
+                    (*book_id).into(),
+                    (*author_id).into(),
+                    "author".into(),
+                    (*position).into(),
+                ]);
+            }
+            manager.exec_stmt(insert).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // `book.authors` was kept up to date, only the split is undone
+        manager
+            .exec_stmt(Query::delete().from_table(BookAuthor::Table).to_owned())
+            .await?;
+
+        manager
+            .exec_stmt(Query::delete().from_table(Author::Table).to_owned())
+            .await
+    }
+}
+
+/// Names of a free text list of authors, without duplicates
+fn split_names(authors: &str) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+
+    for name in authors
+        .split([',', ';', '&'])
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+    {
+        if !names
+            .iter()
+            .any(|other| other.to_lowercase() == name.to_lowercase())
+        {
+            names.push(name.to_string());
+        }
+    }
+
+    names
+}
@@ -0,0 +1,74 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::migrations::m20260126_000001_create_user_table::User;
+use crate::migrations::m20260126_000002_create_book_table::Book;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(BorrowRequest::Table)
+                    .if_not_exists()
+                    .col(pk_auto(BorrowRequest::Id))
+                    .col(ColumnDef::new(BorrowRequest::BookId).integer().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-borrow_request-book_id")
+                            .from(BorrowRequest::Table, BorrowRequest::BookId)
+                            .to(Book::Table, Book::Id),
+                    )
+                    .col(
+                        ColumnDef::new(BorrowRequest::RequesterId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-borrow_request-requester_id")
+                            .from(BorrowRequest::Table, BorrowRequest::RequesterId)
+                            .to(User::Table, User::Id),
+                    )
+                    .col(string(BorrowRequest::Status).default("pending"))
+                    .col(text_null(BorrowRequest::Message))
+                    .col(date_time(BorrowRequest::CreatedAt))
+                    .col(date_time_null(BorrowRequest::AnsweredAt))
+                    .to_owned(),
+            )
+            .await?;
+
+        // The inbox looks for the pending requests of a book
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-borrow_request-book_id-status")
+                    .table(BorrowRequest::Table)
+                    .col(BorrowRequest::BookId)
+                    .col(BorrowRequest::Status)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(BorrowRequest::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+pub enum BorrowRequest {
+    Table,
+    Id,
+    BookId,
+    RequesterId,
+    Status,
+    Message,
+    CreatedAt,
+    AnsweredAt,
+}
@@ -0,0 +1,69 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::migrations::m20260126_000001_create_user_table::User;
+use crate::migrations::m20260126_000002_create_book_table::Book;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(WaitlistEntry::Table)
+                    .if_not_exists()
+                    .col(pk_auto(WaitlistEntry::Id))
+                    .col(ColumnDef::new(WaitlistEntry::BookId).integer().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-waitlist_entry-book_id")
+                            .from(WaitlistEntry::Table, WaitlistEntry::BookId)
+                            .to(Book::Table, Book::Id),
+                    )
+                    .col(ColumnDef::new(WaitlistEntry::UserId).integer().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-waitlist_entry-user_id")
+                            .from(WaitlistEntry::Table, WaitlistEntry::UserId)
+                            .to(User::Table, User::Id),
+                    )
+                    .col(integer(WaitlistEntry::Position))
+                    .col(date_time(WaitlistEntry::CreatedAt))
+                    .col(date_time_null(WaitlistEntry::OfferedAt))
+                    .to_owned(),
+            )
+            .await?;
+
+        // Nobody waits twice for the same book
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-waitlist_entry-book_id-user_id")
+                    .table(WaitlistEntry::Table)
+                    .col(WaitlistEntry::BookId)
+                    .col(WaitlistEntry::UserId)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(WaitlistEntry::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+pub enum WaitlistEntry {
+    Table,
+    Id,
+    BookId,
+    UserId,
+    Position,
+    CreatedAt,
+    OfferedAt,
+}
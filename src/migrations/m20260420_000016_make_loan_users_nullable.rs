@@ -0,0 +1,136 @@
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::sea_orm::DbBackend;
+
+use crate::migrations::m20260126_000001_create_user_table::User;
+use crate::migrations::m20260126_000002_create_book_table::Book;
+use crate::migrations::m20260201_000003_create_loan_table::Loan;
+use crate::migrations::m20260205_000004_add_due_date_to_loan::LoanDueDate;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+/// The borrower and the lender of a loan are nulled out when their user
+/// is deleted, so the other side keeps its history.
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        set_users_nullable(manager, true).await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Loans of deleted users can't be kept without them
+        manager
+            .exec_stmt(
+                Query::delete()
+                    .from_table(Loan::Table)
+                    .cond_where(
+                        Condition::any()
+                            .add(Expr::col(Loan::BorrowerId).is_null())
+                            .add(Expr::col(Loan::LenderId).is_null()),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        set_users_nullable(manager, false).await
+    }
+}
+
+async fn set_users_nullable(manager: &SchemaManager<'_>, nullable: bool) -> Result<(), DbErr> {
+    if manager.get_database_backend() != DbBackend::Sqlite {
+        return manager
+            .alter_table(
+                Table::alter()
+                    .table(Loan::Table)
+                    .modify_column(user_column(Loan::BorrowerId, nullable))
+                    .modify_column(user_column(Loan::LenderId, nullable))
+                    .to_owned(),
+            )
+            .await;
+    }
+
+    // SQLite can't alter a column, the table is rebuilt with the same rows
+    let rebuilt = Alias::new("loan_rebuilt");
+    let columns: Vec<DynIden> = vec![
+        Loan::Id.into_iden(),
+        Loan::BookId.into_iden(),
+        Loan::BorrowerId.into_iden(),
+        Loan::LenderId.into_iden(),
+        Loan::StartDate.into_iden(),
+        Loan::ReturnDate.into_iden(),
+        Loan::Notes.into_iden(),
+        LoanDueDate::DueDate.into_iden(),
+    ];
+
+    manager
+        .create_table(
+            Table::create()
+                .table(rebuilt.clone())
+                .col(
+                    ColumnDef::new(Loan::Id)
+                        .integer()
+                        .not_null()
+                        .auto_increment()
+                        .primary_key(),
+                )
+                .col(ColumnDef::new(Loan::BookId).integer().not_null())
+                .foreign_key(
+                    ForeignKey::create()
+                        .name("fk-loan-book_id")
+                        .from(rebuilt.clone(), Loan::BookId)
+                        .to(Book::Table, Book::Id),
+                )
+                .col(user_column(Loan::BorrowerId, nullable))
+                .foreign_key(
+                    ForeignKey::create()
+                        .name("fk-loan-borrower_id")
+                        .from(rebuilt.clone(), Loan::BorrowerId)
+                        .to(User::Table, User::Id),
+                )
+                .col(user_column(Loan::LenderId, nullable))
+                .foreign_key(
+                    ForeignKey::create()
+                        .name("fk-loan-lender_id")
+                        .from(rebuilt.clone(), Loan::LenderId)
+                        .to(User::Table, User::Id),
+                )
+                .col(ColumnDef::new(Loan::StartDate).date().not_null())
+                .col(ColumnDef::new(Loan::ReturnDate).date().null())
+                .col(ColumnDef::new(Loan::Notes).text().null())
+                .col(ColumnDef::new(LoanDueDate::DueDate).date().null())
+                .to_owned(),
+        )
+        .await?;
+
+    let mut insert = Query::insert();
+    insert
+        .into_table(rebuilt.clone())
+        .columns(columns.clone())
+        .select_from(
+            Query::select()
+                .columns(columns)
+                .from(Loan::Table)
+                .to_owned(),
+        )
+        .map_err(|error| DbErr::Migration(error.to_string()))?;
+    manager.exec_stmt(insert).await?;
+
+    manager
+        .drop_table(Table::drop().table(Loan::Table).to_owned())
+        .await?;
+
+    manager
+        .rename_table(Table::rename().table(rebuilt, Loan::Table).to_owned())
+        .await
+}
+
+fn user_column(column: Loan, nullable: bool) -> ColumnDef {
+    let mut column = ColumnDef::new(column);
+    column.integer();
+    if nullable {
+        column.null();
+    } else {
+        column.not_null();
+    }
+    column
+}
@@ -0,0 +1,42 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::migrations::m20260215_000006_add_credentials_and_sessions::Session;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+/// Every session gets its own CSRF token.
+/// The open sessions have none, everybody has to log in again.
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .exec_stmt(Query::delete().from_table(Session::Table).to_owned())
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Session::Table)
+                    .add_column(string(SessionCsrf::CsrfToken).default(""))
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Session::Table)
+                    .drop_column(SessionCsrf::CsrfToken)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+pub enum SessionCsrf {
+    CsrfToken,
+}
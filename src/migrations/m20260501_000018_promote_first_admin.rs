@@ -0,0 +1,49 @@
+use sea_orm_migration::prelude::*;
+
+use crate::migrations::m20260126_000001_create_user_table::User;
+use crate::migrations::m20260220_000007_add_role_to_user::UserRole;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+/// Promotes the first user when nobody is admin, which `m20260220_000007`
+/// skips on MySQL.
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let admins = Query::select()
+            .column(User::Id)
+            .from(User::Table)
+            .and_where(Expr::col(UserRole::Role).eq("admin"))
+            .to_owned();
+
+        // MySQL can't update a table selected in a subquery, unless the
+        // subquery is wrapped in a derived table
+        let first_user = Query::select()
+            .column(Alias::new("id"))
+            .from_subquery(
+                Query::select()
+                    .expr_as(Expr::col(User::Id).min(), Alias::new("id"))
+                    .from(User::Table)
+                    .and_where(Expr::exists(admins).not())
+                    .to_owned(),
+                Alias::new("first_user"),
+            )
+            .to_owned();
+
+        manager
+            .exec_stmt(
+                Query::update()
+                    .table(User::Table)
+                    .value(UserRole::Role, "admin")
+                    .and_where(Expr::col(User::Id).in_subquery(first_user))
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, _manager: &SchemaManager) -> Result<(), DbErr> {
+        // The promoted user can't be told apart from the other admins
+        Ok(())
+    }
+}
@@ -0,0 +1,38 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::migrations::m20260415_000015_create_waitlist_table::WaitlistEntry;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+/// The entry offered the book stays until the owner answers the request
+/// of its user, the book is reserved for that request.
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(WaitlistEntry::Table)
+                    .add_column(integer_null(WaitlistEntryRequest::BorrowRequestId))
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(WaitlistEntry::Table)
+                    .drop_column(WaitlistEntryRequest::BorrowRequestId)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+pub enum WaitlistEntryRequest {
+    BorrowRequestId,
+}
@@ -0,0 +1,444 @@
+use std::collections::{HashMap, HashSet};
+
+use sea_orm::ActiveValue::Set;
+use sea_orm::ConnectionTrait;
+use sea_orm::QueryOrder;
+use sea_orm::TransactionTrait;
+use sea_orm::entity::prelude::*;
+use sea_orm::sea_query::Expr;
+use snafu::ResultExt;
+use snafu::prelude::*;
+
+use crate::models::filter;
+use crate::models::{book, book_author};
+use crate::state::AppState;
+
+#[sea_orm::model]
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "author")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    #[sea_orm(unique)]
+    pub name: String,
+}
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {}
+
+/// What a person did on a book
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+    Hash,
+    PartialOrd,
+    Ord,
+    EnumIter,
+    DeriveActiveEnum,
+    serde::Deserialize,
+    serde::Serialize,
+    utoipa::ToSchema,
+)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::None)")]
+#[serde(rename_all = "snake_case")]
+pub enum AuthorRole {
+    #[sea_orm(string_value = "author")]
+    Author,
+    #[sea_orm(string_value = "translator")]
+    Translator,
+    #[sea_orm(string_value = "illustrator")]
+    Illustrator,
+    #[sea_orm(string_value = "editor")]
+    Editor,
+}
+
+impl AuthorRole {
+    pub fn all() -> [AuthorRole; 4] {
+        [
+            AuthorRole::Author,
+            AuthorRole::Translator,
+            AuthorRole::Illustrator,
+            AuthorRole::Editor,
+        ]
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuthorRole::Author => "author",
+            AuthorRole::Translator => "translator",
+            AuthorRole::Illustrator => "illustrator",
+            AuthorRole::Editor => "editor",
+        }
+    }
+}
+
+#[derive(Debug, Snafu)]
+#[snafu(visibility(pub))]
+pub enum AuthorError {
+    /// Db Error from SeaOrm
+    #[snafu(display("Database error"))]
+    DB { source: sea_orm::DbErr },
+    /// When Author with Id is not found
+    #[snafu(display("Author with id {id} not found"))]
+    NotFound { id: i32 },
+    /// An author needs a name
+    #[snafu(display("The name of an author can't be empty"))]
+    EmptyName,
+}
+
+/// Splits a list of names typed in a form, e.g. "Emma Goldman, Voltairine de Cleyre".
+///
+/// Names are separated by `,`, `;` or `&`, trimmed, and the same name is only
+/// kept once, ignoring the case.
+pub fn parse_names(input: &str) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+
+    for name in input
+        .split([',', ';', '&'])
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+    {
+        if !names
+            .iter()
+            .any(|other| other.to_lowercase() == name.to_lowercase())
+        {
+            names.push(name.to_string());
+        }
+    }
+
+    names
+}
+
+/// People credited on a book, by role then by rank
+#[derive(Debug, Clone, Default)]
+pub struct BookCredits {
+    pub credits: Vec<(AuthorRole, Model)>,
+}
+
+impl BookCredits {
+    /// People having the role on the book
+    pub fn with_role(&self, role: AuthorRole) -> Vec<&Model> {
+        self.credits
+            .iter()
+            .filter(|(credit_role, _)| *credit_role == role)
+            .map(|(_, author)| author)
+            .collect()
+    }
+
+    /// Names of the people having the role, as typed in the book form
+    pub fn names(&self, role: AuthorRole) -> String {
+        self.with_role(role)
+            .iter()
+            .map(|author| author.name.as_str())
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+#[derive(Debug)]
+/// Operator for the authors and their credits on the books
+pub struct AuthorOperator {
+    pub state: AppState,
+}
+
+impl AuthorOperator {
+    /// Creates a new `AuthorOperator` with the given application state.
+    pub fn new(state: AppState) -> Self {
+        Self { state }
+    }
+
+    /// Every author, by name
+    pub async fn all(&self) -> Result<Vec<Model>, AuthorError> {
+        Entity::find()
+            .order_by_asc(Column::Name)
+            .all(&self.state.db)
+            .await
+            .context(DBSnafu)
+    }
+
+    /// Every author with the number of books they are credited on, by name
+    pub async fn all_with_count(&self) -> Result<Vec<(Model, usize)>, AuthorError> {
+        let mut books_by_author_id: HashMap<i32, HashSet<i32>> = HashMap::new();
+        for credit in book_author::Entity::find()
+            .all(&self.state.db)
+            .await
+            .context(DBSnafu)?
+        {
+            books_by_author_id
+                .entry(credit.author_id)
+                .or_default()
+                .insert(credit.book_id);
+        }
+
+        Ok(self
+            .all()
+            .await?
+            .into_iter()
+            .map(|author| {
+                let count = books_by_author_id
+                    .get(&author.id)
+                    .map(HashSet::len)
+                    .unwrap_or_default();
+                (author, count)
+            })
+            .collect())
+    }
+
+    /// Finds an author by its ID.
+    ///
+    /// # Errors
+    /// Returns `AuthorError::NotFound` if no author exists with the given ID.
+    pub async fn find_by_id(&self, id: i32) -> Result<Model, AuthorError> {
+        Entity::find_by_id(id)
+            .one(&self.state.db)
+            .await
+            .context(DBSnafu)?
+            .context(NotFoundSnafu { id })
+    }
+
+    /// People credited on a book
+    pub async fn find_credits_by_book(&self, book_id: i32) -> Result<BookCredits, AuthorError> {
+        let credits = book_author::Entity::find()
+            .filter(book_author::Column::BookId.eq(book_id))
+            .order_by_asc(book_author::Column::Position)
+            .all(&self.state.db)
+            .await
+            .context(DBSnafu)?;
+
+        let authors_by_id: HashMap<i32, Model> = Entity::find()
+            .filter(Column::Id.is_in(credits.iter().map(|credit| credit.author_id)))
+            .all(&self.state.db)
+            .await
+            .context(DBSnafu)?
+            .into_iter()
+            .map(|author| (author.id, author))
+            .collect();
+
+        let mut credits: Vec<(AuthorRole, Model)> = credits
+            .into_iter()
+            .filter_map(|credit| {
+                let author = authors_by_id.get(&credit.author_id).cloned()?;
+                Some((credit.role, author))
+            })
+            .collect();
+        // Stable, so the rank is kept inside a role
+        credits.sort_by_key(|(role, _)| *role);
+
+        Ok(BookCredits { credits })
+    }
+
+    /// Books an author is credited on, with their role, by title
+    pub async fn find_books(
+        &self,
+        author_id: i32,
+    ) -> Result<Vec<(book::Model, AuthorRole)>, AuthorError> {
+        let credits = book_author::Entity::find()
+            .filter(book_author::Column::AuthorId.eq(author_id))
+            .all(&self.state.db)
+            .await
+            .context(DBSnafu)?;
+
+        let books_by_id: HashMap<i32, book::Model> = book::Entity::find()
+            .filter(book::Column::Id.is_in(credits.iter().map(|credit| credit.book_id)))
+            .all(&self.state.db)
+            .await
+            .context(DBSnafu)?
+            .into_iter()
+            .map(|book| (book.id, book))
+            .collect();
+
+        let mut books: Vec<(book::Model, AuthorRole)> = credits
+            .into_iter()
+            .filter_map(|credit| Some((books_by_id.get(&credit.book_id).cloned()?, credit.role)))
+            .collect();
+        books.sort_by_key(|(book, role)| (book.title.to_lowercase(), *role));
+
+        Ok(books)
+    }
+
+    /// Replaces the people having the role on a book, creating the authors
+    /// which don't exist yet. The order of `names` is kept.
+    ///
+    /// Takes the connection so it can be part of a transaction.
+    pub async fn set_book_credits<C: ConnectionTrait>(
+        db: &C,
+        book_id: i32,
+        role: AuthorRole,
+        names: &[String],
+    ) -> Result<(), AuthorError> {
+        book_author::Entity::delete_many()
+            .filter(book_author::Column::BookId.eq(book_id))
+            .filter(book_author::Column::Role.eq(role))
+            .exec(db)
+            .await
+            .context(DBSnafu)?;
+
+        for (position, name) in names.iter().enumerate() {
+            let author = Self::find_or_create(db, name).await?;
+
+            book_author::ActiveModel {
+                book_id: Set(book_id),
+                author_id: Set(author.id),
+                role: Set(role),
+                position: Set(position as i32),
+            }
+            .insert(db)
+            .await
+            .context(DBSnafu)?;
+        }
+
+        Ok(())
+    }
+
+    /// Renames an author.
+    ///
+    /// When another author already has this name, ignoring the case, both are
+    /// the same person: the books are moved to the other author and this one
+    /// is deleted. Returns the author the books now belong to.
+    pub async fn rename(&self, id: i32, name: &str) -> Result<Model, AuthorError> {
+        let name = name.trim();
+        ensure!(!name.is_empty(), EmptyNameSnafu);
+
+        let author = self.find_by_id(id).await?;
+        let txn = self.state.db.begin().await.context(DBSnafu)?;
+
+        let same_name = Entity::find()
+            .filter(Column::Id.ne(author.id))
+            .all(&txn)
+            .await
+            .context(DBSnafu)?
+            .into_iter()
+            .find(|other| other.name.to_lowercase() == name.to_lowercase());
+
+        let credits = book_author::Entity::find()
+            .filter(book_author::Column::AuthorId.eq(author.id))
+            .all(&txn)
+            .await
+            .context(DBSnafu)?;
+        let book_ids: Vec<i32> = credits.iter().map(|credit| credit.book_id).collect();
+
+        let author = match same_name {
+            Some(other) => {
+                let other_credits = book_author::Entity::find()
+                    .filter(book_author::Column::AuthorId.eq(other.id))
+                    .all(&txn)
+                    .await
+                    .context(DBSnafu)?;
+
+                for credit in credits {
+                    let duplicate = other_credits.iter().any(|other_credit| {
+                        other_credit.book_id == credit.book_id && other_credit.role == credit.role
+                    });
+
+                    book_author::Entity::delete_many()
+                        .filter(book_author::Column::BookId.eq(credit.book_id))
+                        .filter(book_author::Column::AuthorId.eq(credit.author_id))
+                        .filter(book_author::Column::Role.eq(credit.role))
+                        .exec(&txn)
+                        .await
+                        .context(DBSnafu)?;
+
+                    if !duplicate {
+                        book_author::ActiveModel {
+                            book_id: Set(credit.book_id),
+                            author_id: Set(other.id),
+                            role: Set(credit.role),
+                            position: Set(credit.position),
+                        }
+                        .insert(&txn)
+                        .await
+                        .context(DBSnafu)?;
+                    }
+                }
+
+                author.delete(&txn).await.context(DBSnafu)?;
+                other
+            }
+            None => {
+                let mut author: ActiveModel = author.into();
+                author.name = Set(name.to_string());
+                author.update(&txn).await.context(DBSnafu)?
+            }
+        };
+
+        Self::refresh_books(&txn, &book_ids).await?;
+        txn.commit().await.context(DBSnafu)?;
+
+        Ok(author)
+    }
+
+    /// Removes every credit of a book, before the book is deleted
+    pub async fn delete_all_by_book<C: ConnectionTrait>(
+        db: &C,
+        book_id: i32,
+    ) -> Result<(), AuthorError> {
+        book_author::Entity::delete_many()
+            .filter(book_author::Column::BookId.eq(book_id))
+            .exec(db)
+            .await
+            .context(DBSnafu)?;
+
+        Ok(())
+    }
+
+    // private
+
+    async fn find_or_create<C: ConnectionTrait>(db: &C, name: &str) -> Result<Model, AuthorError> {
+        let existing = Entity::find()
+            .filter(filter::eq_ignore_case(Column::Name, name))
+            .one(db)
+            .await
+            .context(DBSnafu)?;
+
+        match existing {
+            Some(author) => Ok(author),
+            None => ActiveModel {
+                name: Set(name.to_string()),
+                ..Default::default()
+            }
+            .insert(db)
+            .await
+            .context(DBSnafu),
+        }
+    }
+
+    /// Writes the names of the authors back in `book.authors`, which is
+    /// what the list, the search and the exports show
+    async fn refresh_books<C: ConnectionTrait>(
+        db: &C,
+        book_ids: &[i32],
+    ) -> Result<(), AuthorError> {
+        for book_id in book_ids {
+            let credits = book_author::Entity::find()
+                .filter(book_author::Column::BookId.eq(*book_id))
+                .filter(book_author::Column::Role.eq(AuthorRole::Author))
+                .order_by_asc(book_author::Column::Position)
+                .all(db)
+                .await
+                .context(DBSnafu)?;
+
+            let mut names = Vec::with_capacity(credits.len());
+            for credit in credits {
+                if let Some(author) = Entity::find_by_id(credit.author_id)
+                    .one(db)
+                    .await
+                    .context(DBSnafu)?
+                {
+                    names.push(author.name);
+                }
+            }
+
+            book::Entity::update_many()
+                .col_expr(book::Column::Authors, Expr::value(names.join(", ")))
+                .filter(book::Column::Id.eq(*book_id))
+                .exec(db)
+                .await
+                .context(DBSnafu)?;
+        }
+
+        Ok(())
+    }
+}
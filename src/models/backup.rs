@@ -0,0 +1,723 @@
+use std::collections::HashMap;
+
+use chrono::{NaiveDate, NaiveDateTime};
+use sea_orm::ActiveValue::Set;
+use sea_orm::QueryOrder;
+use sea_orm::TransactionTrait;
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+use snafu::prelude::*;
+
+use crate::models::author::{self, AuthorError, AuthorOperator, AuthorRole};
+use crate::models::borrow_request::RequestStatus;
+use crate::models::tag::{TagError, TagOperator};
+use crate::models::user::Role;
+use crate::models::{
+    book, book_author, book_tag, borrow_request, loan, session, tag, user, waitlist,
+};
+use crate::state::AppState;
+
+/// Version of the archive format, to bump whenever a table or a column
+/// is added to the backup so old archives can be told apart
+pub const BACKUP_VERSION: u32 = 5;
+
+/// Oldest archive format which can still be restored, the books of a version 1
+/// archive have no tags, the authors of versions 1 and 2 are only a string,
+/// the books of versions 1 to 3 have no cover and versions 1 to 4 have
+/// neither borrow requests nor waitlists
+pub const MIN_BACKUP_VERSION: u32 = 1;
+
+#[derive(Debug, Snafu)]
+#[snafu(visibility(pub))]
+pub enum BackupError {
+    /// Db Error from SeaOrm
+    #[snafu(display("Database error"))]
+    DB { source: sea_orm::DbErr },
+    /// The archive is not valid JSON or doesn't have the expected fields
+    #[snafu(display("Invalid backup archive: {source}"))]
+    Json { source: serde_json::Error },
+    /// The archive was made by a version of BookForge we can't read
+    #[snafu(display(
+        "Unsupported backup version {version}, expected {MIN_BACKUP_VERSION} to {BACKUP_VERSION}"
+    ))]
+    UnsupportedVersion { version: u32 },
+    /// A row references a user which is not in the archive
+    #[snafu(display("Unknown user {id} referenced in the backup"))]
+    UnknownUser { id: i32 },
+    /// Error while restoring the tags of a book
+    #[snafu(display("Tag error"))]
+    Tag { source: TagError },
+    /// Error while restoring the authors of a book
+    #[snafu(display("Author error"))]
+    Author { source: AuthorError },
+    /// A row references a book which is not in the archive
+    #[snafu(display("Unknown book {id} referenced in the backup"))]
+    UnknownBook { id: i32 },
+    /// A waitlist entry references a borrow request which is not in the archive
+    #[snafu(display("Unknown borrow request {id} referenced in the backup"))]
+    UnknownBorrowRequest { id: i32 },
+}
+
+/// Snapshot of the whole library.
+///
+/// Login sessions are not saved, everybody has to log in again after a restore.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Backup {
+    pub version: u32,
+    pub created_at: NaiveDateTime,
+    pub users: Vec<UserRecord>,
+    pub books: Vec<BookRecord>,
+    pub loans: Vec<LoanRecord>,
+    #[serde(default)]
+    pub borrow_requests: Vec<BorrowRequestRecord>,
+    #[serde(default)]
+    pub waitlist_entries: Vec<WaitlistEntryRecord>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UserRecord {
+    pub id: i32,
+    pub name: String,
+    pub password_hash: Option<String>,
+    pub role: Role,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BookRecord {
+    pub id: i32,
+    pub title: String,
+    pub authors: String,
+    pub isbn: Option<String>,
+    pub description: Option<String>,
+    pub comment: Option<String>,
+    pub owner_id: i32,
+    pub current_holder_id: Option<i32>,
+    /// Names of the tags of the book
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// People credited on the book, by rank
+    #[serde(default)]
+    pub credits: Vec<CreditRecord>,
+    /// File name of the cover, the files themselves are not in the archive
+    #[serde(default)]
+    pub cover: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreditRecord {
+    pub name: String,
+    pub role: AuthorRole,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LoanRecord {
+    pub id: i32,
+    pub book_id: i32,
+    /// None when the user was deleted
+    pub borrower_id: Option<i32>,
+    pub lender_id: Option<i32>,
+    pub start_date: NaiveDate,
+    pub return_date: Option<NaiveDate>,
+    pub notes: Option<String>,
+    pub due_date: Option<NaiveDate>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BorrowRequestRecord {
+    pub id: i32,
+    pub book_id: i32,
+    pub requester_id: i32,
+    pub status: RequestStatus,
+    pub message: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub answered_at: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WaitlistEntryRecord {
+    pub id: i32,
+    pub book_id: i32,
+    pub user_id: i32,
+    pub position: i32,
+    pub created_at: NaiveDateTime,
+    pub offered_at: Option<NaiveDateTime>,
+    /// Request the offered book is reserved for
+    pub borrow_request_id: Option<i32>,
+}
+
+/// Counts of restored rows
+#[derive(Debug, Default)]
+pub struct RestoreSummary {
+    pub users: usize,
+    pub books: usize,
+    pub loans: usize,
+}
+
+#[derive(Debug)]
+/// Operator for the backup and the restore of the whole library
+pub struct BackupOperator {
+    pub state: AppState,
+}
+
+impl BackupOperator {
+    /// Creates a new `BackupOperator` with the given application state.
+    pub fn new(state: AppState) -> Self {
+        Self { state }
+    }
+
+    /// Reads every table in a single transaction, so the snapshot is consistent
+    pub async fn export(&self) -> Result<Backup, BackupError> {
+        let txn = self.state.db.begin().await.context(DBSnafu)?;
+
+        let users = user::Entity::find()
+            .order_by_asc(user::Column::Id)
+            .all(&txn)
+            .await
+            .context(DBSnafu)?;
+        let books = book::Entity::find()
+            .order_by_asc(book::Column::Id)
+            .all(&txn)
+            .await
+            .context(DBSnafu)?;
+        let loans = loan::Entity::find()
+            .order_by_asc(loan::Column::Id)
+            .all(&txn)
+            .await
+            .context(DBSnafu)?;
+        let borrow_requests = borrow_request::Entity::find()
+            .order_by_asc(borrow_request::Column::Id)
+            .all(&txn)
+            .await
+            .context(DBSnafu)?;
+        let waitlist_entries = waitlist::Entity::find()
+            .order_by_asc(waitlist::Column::Id)
+            .all(&txn)
+            .await
+            .context(DBSnafu)?;
+        let tag_names: HashMap<i32, String> = tag::Entity::find()
+            .all(&txn)
+            .await
+            .context(DBSnafu)?
+            .into_iter()
+            .map(|tag| (tag.id, tag.name))
+            .collect();
+        let mut tags_by_book_id: HashMap<i32, Vec<String>> = HashMap::new();
+        for link in book_tag::Entity::find().all(&txn).await.context(DBSnafu)? {
+            if let Some(name) = tag_names.get(&link.tag_id) {
+                tags_by_book_id
+                    .entry(link.book_id)
+                    .or_default()
+                    .push(name.clone());
+            }
+        }
+        let author_names: HashMap<i32, String> = author::Entity::find()
+            .all(&txn)
+            .await
+            .context(DBSnafu)?
+            .into_iter()
+            .map(|author| (author.id, author.name))
+            .collect();
+        let mut credits_by_book_id: HashMap<i32, Vec<CreditRecord>> = HashMap::new();
+        for credit in book_author::Entity::find()
+            .order_by_asc(book_author::Column::Position)
+            .all(&txn)
+            .await
+            .context(DBSnafu)?
+        {
+            if let Some(name) = author_names.get(&credit.author_id) {
+                credits_by_book_id
+                    .entry(credit.book_id)
+                    .or_default()
+                    .push(CreditRecord {
+                        name: name.clone(),
+                        role: credit.role,
+                    });
+            }
+        }
+
+        txn.commit().await.context(DBSnafu)?;
+
+        Ok(Backup {
+            version: BACKUP_VERSION,
+            created_at: chrono::Local::now().naive_local(),
+            users: users
+                .into_iter()
+                .map(|user| UserRecord {
+                    id: user.id,
+                    name: user.name,
+                    password_hash: user.password_hash,
+                    role: user.role,
+                })
+                .collect(),
+            books: books
+                .into_iter()
+                .map(|book| BookRecord {
+                    id: book.id,
+                    title: book.title,
+                    authors: book.authors,
+                    isbn: book.isbn,
+                    description: book.description,
+                    comment: book.comment,
+                    owner_id: book.owner_id,
+                    current_holder_id: book.current_holder_id,
+                    tags: tags_by_book_id.remove(&book.id).unwrap_or_default(),
+                    credits: credits_by_book_id.remove(&book.id).unwrap_or_default(),
+                    cover: book.cover,
+                })
+                .collect(),
+            loans: loans
+                .into_iter()
+                .map(|loan| LoanRecord {
+                    id: loan.id,
+                    book_id: loan.book_id,
+                    borrower_id: loan.borrower_id,
+                    lender_id: loan.lender_id,
+                    start_date: loan.start_date,
+                    return_date: loan.return_date,
+                    notes: loan.notes,
+                    due_date: loan.due_date,
+                })
+                .collect(),
+            borrow_requests: borrow_requests
+                .into_iter()
+                .map(|request| BorrowRequestRecord {
+                    id: request.id,
+                    book_id: request.book_id,
+                    requester_id: request.requester_id,
+                    status: request.status,
+                    message: request.message,
+                    created_at: request.created_at,
+                    answered_at: request.answered_at,
+                })
+                .collect(),
+            waitlist_entries: waitlist_entries
+                .into_iter()
+                .map(|entry| WaitlistEntryRecord {
+                    id: entry.id,
+                    book_id: entry.book_id,
+                    user_id: entry.user_id,
+                    position: entry.position,
+                    created_at: entry.created_at,
+                    offered_at: entry.offered_at,
+                    borrow_request_id: entry.borrow_request_id,
+                })
+                .collect(),
+        })
+    }
+
+    /// Parses a JSON archive and checks its version
+    pub fn parse(json: &[u8]) -> Result<Backup, BackupError> {
+        // Look at the version first, a newer archive may not match our structs
+        #[derive(Deserialize)]
+        struct Version {
+            version: u32,
+        }
+        let Version { version } = serde_json::from_slice(json).context(JsonSnafu)?;
+        ensure!(
+            (MIN_BACKUP_VERSION..=BACKUP_VERSION).contains(&version),
+            UnsupportedVersionSnafu { version }
+        );
+
+        serde_json::from_slice(json).context(JsonSnafu)
+    }
+
+    /// Replaces the whole library with the content of the backup.
+    ///
+    /// Rows get new ids and every reference is remapped, so an archive can be
+    /// restored in any instance. Everything happens in a single transaction:
+    /// on error the library is left untouched.
+    pub async fn restore(&self, backup: Backup) -> Result<RestoreSummary, BackupError> {
+        ensure!(
+            (MIN_BACKUP_VERSION..=BACKUP_VERSION).contains(&backup.version),
+            UnsupportedVersionSnafu {
+                version: backup.version
+            }
+        );
+
+        let txn = self.state.db.begin().await.context(DBSnafu)?;
+
+        // Children first because of the foreign keys
+        book_author::Entity::delete_many()
+            .exec(&txn)
+            .await
+            .context(DBSnafu)?;
+        author::Entity::delete_many()
+            .exec(&txn)
+            .await
+            .context(DBSnafu)?;
+        book_tag::Entity::delete_many()
+            .exec(&txn)
+            .await
+            .context(DBSnafu)?;
+        tag::Entity::delete_many()
+            .exec(&txn)
+            .await
+            .context(DBSnafu)?;
+        waitlist::Entity::delete_many()
+            .exec(&txn)
+            .await
+            .context(DBSnafu)?;
+        borrow_request::Entity::delete_many()
+            .exec(&txn)
+            .await
+            .context(DBSnafu)?;
+        loan::Entity::delete_many()
+            .exec(&txn)
+            .await
+            .context(DBSnafu)?;
+        session::Entity::delete_many()
+            .exec(&txn)
+            .await
+            .context(DBSnafu)?;
+        book::Entity::delete_many()
+            .exec(&txn)
+            .await
+            .context(DBSnafu)?;
+        user::Entity::delete_many()
+            .exec(&txn)
+            .await
+            .context(DBSnafu)?;
+
+        let mut user_ids: HashMap<i32, i32> = HashMap::new();
+        for record in &backup.users {
+            let user = user::ActiveModel {
+                name: Set(record.name.clone()),
+                password_hash: Set(record.password_hash.clone()),
+                role: Set(record.role),
+                ..Default::default()
+            }
+            .insert(&txn)
+            .await
+            .context(DBSnafu)?;
+
+            user_ids.insert(record.id, user.id);
+        }
+        let user_id = |id: i32| user_ids.get(&id).copied().context(UnknownUserSnafu { id });
+
+        let mut book_ids: HashMap<i32, i32> = HashMap::new();
+        for record in &backup.books {
+            let book = book::ActiveModel {
+                title: Set(record.title.clone()),
+                authors: Set(record.authors.clone()),
+                isbn: Set(record.isbn.clone()),
+                description: Set(record.description.clone()),
+                comment: Set(record.comment.clone()),
+                cover: Set(record.cover.clone()),
+                owner_id: Set(user_id(record.owner_id)?),
+                current_holder_id: Set(record.current_holder_id.map(user_id).transpose()?),
+                ..Default::default()
+            }
+            .insert(&txn)
+            .await
+            .context(DBSnafu)?;
+
+            TagOperator::set_book_tags(&txn, book.id, &record.tags)
+                .await
+                .context(TagSnafu)?;
+
+            for role in AuthorRole::all() {
+                let names: Vec<String> = if record.credits.is_empty() {
+                    // Older archives only have the authors string
+                    match role {
+                        AuthorRole::Author => author::parse_names(&record.authors),
+                        _ => vec![],
+                    }
+                } else {
+                    record
+                        .credits
+                        .iter()
+                        .filter(|credit| credit.role == role)
+                        .map(|credit| credit.name.clone())
+                        .collect()
+                };
+
+                AuthorOperator::set_book_credits(&txn, book.id, role, &names)
+                    .await
+                    .context(AuthorSnafu)?;
+            }
+
+            book_ids.insert(record.id, book.id);
+        }
+
+        let book_id = |id: i32| book_ids.get(&id).copied().context(UnknownBookSnafu { id });
+
+        for record in &backup.loans {
+            loan::ActiveModel {
+                book_id: Set(book_id(record.book_id)?),
+                borrower_id: Set(record.borrower_id.map(user_id).transpose()?),
+                lender_id: Set(record.lender_id.map(user_id).transpose()?),
+                start_date: Set(record.start_date),
+                return_date: Set(record.return_date),
+                notes: Set(record.notes.clone()),
+                due_date: Set(record.due_date),
+                ..Default::default()
+            }
+            .insert(&txn)
+            .await
+            .context(DBSnafu)?;
+        }
+
+        let mut request_ids: HashMap<i32, i32> = HashMap::new();
+        for record in &backup.borrow_requests {
+            let request = borrow_request::ActiveModel {
+                book_id: Set(book_id(record.book_id)?),
+                requester_id: Set(user_id(record.requester_id)?),
+                status: Set(record.status),
+                message: Set(record.message.clone()),
+                created_at: Set(record.created_at),
+                answered_at: Set(record.answered_at),
+                ..Default::default()
+            }
+            .insert(&txn)
+            .await
+            .context(DBSnafu)?;
+
+            request_ids.insert(record.id, request.id);
+        }
+        let request_id = |id: i32| {
+            request_ids
+                .get(&id)
+                .copied()
+                .context(UnknownBorrowRequestSnafu { id })
+        };
+
+        for record in &backup.waitlist_entries {
+            waitlist::ActiveModel {
+                book_id: Set(book_id(record.book_id)?),
+                user_id: Set(user_id(record.user_id)?),
+                position: Set(record.position),
+                created_at: Set(record.created_at),
+                offered_at: Set(record.offered_at),
+                borrow_request_id: Set(record.borrow_request_id.map(request_id).transpose()?),
+                ..Default::default()
+            }
+            .insert(&txn)
+            .await
+            .context(DBSnafu)?;
+        }
+
+        txn.commit().await.context(DBSnafu)?;
+
+        Ok(RestoreSummary {
+            users: backup.users.len(),
+            books: backup.books.len(),
+            loans: backup.loans.len(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sea_orm::{ConnectOptions, Database};
+    use sea_orm_migration::MigratorTrait;
+
+    use super::*;
+    use crate::migrations::Migrator;
+    use crate::state::config::AppConfig;
+    use crate::storage;
+
+    async fn state() -> AppState {
+        // A single connection, every connection to SQLite in memory is a new database
+        let options = ConnectOptions::new("sqlite::memory:")
+            .max_connections(1)
+            .to_owned();
+        let db = Database::connect(options).await.unwrap();
+        Migrator::up(&db, None).await.unwrap();
+
+        let config = AppConfig::default();
+        AppState {
+            storage: storage::from_config(&config),
+            config,
+            db,
+        }
+    }
+
+    async fn insert_user(state: &AppState, name: &str) -> user::Model {
+        user::ActiveModel {
+            name: Set(name.to_string()),
+            password_hash: Set(None),
+            role: Set(Role::Member),
+            ..Default::default()
+        }
+        .insert(&state.db)
+        .await
+        .unwrap()
+    }
+
+    async fn insert_book(state: &AppState, title: &str, owner_id: i32) -> book::Model {
+        book::ActiveModel {
+            title: Set(title.to_string()),
+            authors: Set("Ursula K. Le Guin".to_string()),
+            owner_id: Set(owner_id),
+            ..Default::default()
+        }
+        .insert(&state.db)
+        .await
+        .unwrap()
+    }
+
+    /// Two users, a book lent by one to the other, a request of the owner
+    /// on a book of the borrower and a waitlist entry reserved for it
+    async fn library(state: &AppState) -> Backup {
+        let ursula = insert_user(state, "ursula").await;
+        let pyotr = insert_user(state, "pyotr").await;
+
+        let dispossessed = insert_book(state, "The Dispossessed", ursula.id).await;
+        let mut lent: book::ActiveModel = dispossessed.clone().into();
+        lent.current_holder_id = Set(Some(pyotr.id));
+        lent.update(&state.db).await.unwrap();
+        loan::ActiveModel {
+            book_id: Set(dispossessed.id),
+            borrower_id: Set(Some(pyotr.id)),
+            lender_id: Set(Some(ursula.id)),
+            start_date: Set(NaiveDate::from_ymd_opt(2026, 5, 1).unwrap()),
+            ..Default::default()
+        }
+        .insert(&state.db)
+        .await
+        .unwrap();
+
+        let mutual_aid = insert_book(state, "Mutual Aid", pyotr.id).await;
+        let now = chrono::Utc::now().naive_utc();
+        let request = borrow_request::ActiveModel {
+            book_id: Set(mutual_aid.id),
+            requester_id: Set(ursula.id),
+            status: Set(RequestStatus::Pending),
+            message: Set(None),
+            created_at: Set(now),
+            answered_at: Set(None),
+            ..Default::default()
+        }
+        .insert(&state.db)
+        .await
+        .unwrap();
+        waitlist::ActiveModel {
+            book_id: Set(mutual_aid.id),
+            user_id: Set(ursula.id),
+            position: Set(0),
+            created_at: Set(now),
+            offered_at: Set(Some(now)),
+            borrow_request_id: Set(Some(request.id)),
+            ..Default::default()
+        }
+        .insert(&state.db)
+        .await
+        .unwrap();
+
+        BackupOperator::new(state.clone()).export().await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn restore_remaps_ids() {
+        let source = state().await;
+        let backup = library(&source).await;
+        let json = serde_json::to_vec(&backup).unwrap();
+
+        // Rows already there push the restored ones to other ids
+        let target = state().await;
+        for name in ["someone", "someone else", "a third one"] {
+            let user = insert_user(&target, name).await;
+            insert_book(&target, name, user.id).await;
+        }
+
+        let summary = BackupOperator::new(target.clone())
+            .restore(BackupOperator::parse(&json).unwrap())
+            .await
+            .unwrap();
+        assert_eq!((summary.users, summary.books, summary.loans), (2, 2, 1));
+
+        let users: HashMap<String, i32> = user::Entity::find()
+            .all(&target.db)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|user| (user.name, user.id))
+            .collect();
+        assert_eq!(users.len(), 2);
+        let (ursula, pyotr) = (users["ursula"], users["pyotr"]);
+        assert!(!backup.users.iter().any(|user| user.id == ursula));
+
+        let books: HashMap<String, book::Model> = book::Entity::find()
+            .all(&target.db)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|book| (book.title.clone(), book))
+            .collect();
+        let dispossessed = &books["The Dispossessed"];
+        let mutual_aid = &books["Mutual Aid"];
+        assert_eq!(dispossessed.owner_id, ursula);
+        assert_eq!(dispossessed.current_holder_id, Some(pyotr));
+        assert_eq!(mutual_aid.owner_id, pyotr);
+
+        let loans = loan::Entity::find().all(&target.db).await.unwrap();
+        assert_eq!(loans.len(), 1);
+        assert_eq!(loans[0].book_id, dispossessed.id);
+        assert_eq!(loans[0].borrower_id, Some(pyotr));
+        assert_eq!(loans[0].lender_id, Some(ursula));
+
+        let requests = borrow_request::Entity::find()
+            .all(&target.db)
+            .await
+            .unwrap();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].book_id, mutual_aid.id);
+        assert_eq!(requests[0].requester_id, ursula);
+
+        let entries = waitlist::Entity::find().all(&target.db).await.unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].book_id, mutual_aid.id);
+        assert_eq!(entries[0].user_id, ursula);
+        assert_eq!(entries[0].borrow_request_id, Some(requests[0].id));
+    }
+
+    #[tokio::test]
+    async fn unknown_reference_rejected() {
+        let source = state().await;
+        let mut backup = library(&source).await;
+        backup.waitlist_entries[0].borrow_request_id = Some(999);
+
+        let target = state().await;
+        insert_user(&target, "someone").await;
+        let error = BackupOperator::new(target.clone())
+            .restore(backup)
+            .await
+            .unwrap_err();
+
+        assert!(matches!(
+            error,
+            BackupError::UnknownBorrowRequest { id: 999 }
+        ));
+        // Nothing restored, the library is left untouched
+        let users = user::Entity::find().all(&target.db).await.unwrap();
+        assert_eq!(users.len(), 1);
+        assert_eq!(users[0].name, "someone");
+    }
+
+    #[test]
+    fn wrong_version_rejected() {
+        for version in [MIN_BACKUP_VERSION - 1, BACKUP_VERSION + 1] {
+            let json = format!(r#"{{"version": {}, "users": "not a list"}}"#, version);
+
+            assert!(matches!(
+                BackupOperator::parse(json.as_bytes()),
+                Err(BackupError::UnsupportedVersion { version: found }) if found == version
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn wrong_version_not_restored() {
+        let source = state().await;
+        let mut backup = library(&source).await;
+        backup.version = BACKUP_VERSION + 1;
+
+        let error = BackupOperator::new(source.clone())
+            .restore(backup)
+            .await
+            .unwrap_err();
+
+        assert!(matches!(error, BackupError::UnsupportedVersion { .. }));
+        assert_eq!(user::Entity::find().all(&source.db).await.unwrap().len(), 2);
+    }
+}
@@ -1,11 +1,39 @@
+use std::collections::HashMap;
+
 use sea_orm::ActiveValue::Set;
 use sea_orm::Condition;
+use sea_orm::ConnectionTrait;
+use sea_orm::DbBackend;
 use sea_orm::DeleteResult;
+use sea_orm::FromQueryResult;
+use sea_orm::JoinType;
+use sea_orm::Order;
 use sea_orm::QueryOrder;
+use sea_orm::QuerySelect;
+use sea_orm::Select;
+use sea_orm::Statement;
+use sea_orm::TransactionTrait;
+use sea_orm::Value;
 use sea_orm::entity::prelude::*;
+use sea_orm::sea_query::{Alias, Expr, Func, Query, SelectStatement};
+use serde::{Deserialize, Serialize};
 use snafu::ResultExt;
 use snafu::prelude::*;
-
+use utoipa::ToSchema;
+
+use crate::covers::{self, CoverError};
+use crate::models::author::{self, AuthorError, AuthorOperator, AuthorRole};
+use crate::models::book_author;
+use crate::models::book_tag;
+use crate::models::borrow_request::{BorrowRequestError, BorrowRequestOperator};
+use crate::models::filter::{self, SortDirection};
+use crate::models::isbn::{self, IsbnError};
+use crate::models::loan;
+use crate::models::loan::LoanError;
+use crate::models::loan::LoanOperator;
+use crate::models::tag::{self, TagError, TagMode, TagOperator};
+use crate::models::user;
+use crate::models::waitlist::{WaitlistError, WaitlistOperator};
 use crate::routes::book::BookForm;
 use crate::routes::book::IndexQuery;
 use crate::state::AppState;
@@ -19,8 +47,11 @@ pub struct Model {
     pub id: i32,
     pub title: String,
     pub authors: String,
+    pub isbn: Option<String>,
     pub description: Option<String>,
     pub comment: Option<String>,
+    /// File name of the cover in the covers directory, see [`crate::covers`]
+    pub cover: Option<String>,
     pub owner_id: i32,
     #[sea_orm(belongs_to, relation_enum = "Owner", from = "owner_id", to = "id")]
     pub owner: HasOne<super::user::Entity>,
@@ -37,6 +68,16 @@ pub struct Model {
 #[async_trait::async_trait]
 impl ActiveModelBehavior for ActiveModel {}
 
+impl Model {
+    /// A book can be borrowed when nobody but its owner holds it
+    pub fn is_available(&self) -> bool {
+        match self.current_holder_id {
+            Some(holder_id) => holder_id == self.owner_id,
+            None => true,
+        }
+    }
+}
+
 #[derive(Debug, Snafu)]
 #[snafu(visibility(pub))]
 pub enum BookError {
@@ -46,6 +87,111 @@ pub enum BookError {
     /// When Book with Id is not found
     #[snafu(display("Book with id {id} not found"))]
     NotFound { id: i32 },
+    /// Error while recording the loan history
+    #[snafu(display("Loan error"))]
+    Loan { source: LoanError },
+    /// When the ISBN given in the form is not valid
+    #[snafu(display("Invalid ISBN"))]
+    Isbn { source: IsbnError },
+    /// Error while saving the tags of the book
+    #[snafu(display("Tag error"))]
+    Tag { source: TagError },
+    /// Error while saving the authors of the book
+    #[snafu(display("Author error"))]
+    Author { source: AuthorError },
+    /// Error while removing the cover files of the book
+    #[snafu(display("Cover error"))]
+    Cover { source: CoverError },
+    /// Error while removing the borrow requests of the book
+    #[snafu(display("Borrow request error"))]
+    BorrowRequest { source: BorrowRequestError },
+    /// Error while offering the returned book to the waitlist
+    #[snafu(display("Waitlist error"))]
+    Waitlist { source: WaitlistError },
+}
+
+/// Availability filter of the book list, `*_by_me` are relative to the
+/// logged in user
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Availability {
+    /// Held by nobody or by its owner
+    Available,
+    /// Held by someone else than its owner
+    LentOut,
+    /// Owned by me and held by someone else
+    LentByMe,
+    /// Held by me and owned by someone else
+    BorrowedByMe,
+}
+
+impl Availability {
+    pub fn all() -> [Availability; 4] {
+        [
+            Availability::Available,
+            Availability::LentOut,
+            Availability::LentByMe,
+            Availability::BorrowedByMe,
+        ]
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Availability::Available => "available",
+            Availability::LentOut => "lent_out",
+            Availability::LentByMe => "lent_by_me",
+            Availability::BorrowedByMe => "borrowed_by_me",
+        }
+    }
+}
+
+impl std::fmt::Display for Availability {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for Availability {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Availability::all()
+            .into_iter()
+            .find(|availability| availability.as_str() == s)
+            .ok_or_else(|| format!("unknown availability {s:?}"))
+    }
+}
+
+/// Column the book list can be sorted by
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BookSort {
+    Title,
+    Authors,
+    Owner,
+    Holder,
+    /// Order in which the books were added
+    Added,
+}
+
+impl BookSort {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BookSort::Title => "title",
+            BookSort::Authors => "authors",
+            BookSort::Owner => "owner",
+            BookSort::Holder => "holder",
+            BookSort::Added => "added",
+        }
+    }
+
+    /// Direction used when the column is first clicked
+    pub fn default_direction(&self) -> SortDirection {
+        match self {
+            BookSort::Added => SortDirection::Desc,
+            _ => SortDirection::Asc,
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -59,6 +205,17 @@ pub struct BooksPaginate {
     pub books: Vec<Model>,
     pub current_page: u64,
     pub total_page: u64,
+    pub per_page: u64,
+    /// Extract of the text matching the `q` search of each book, as HTML
+    /// with the matched words in `<mark>`
+    pub snippets: HashMap<i32, String>,
+}
+
+/// Extract of a book matching a full-text search
+#[derive(Debug, FromQueryResult)]
+struct BookSnippet {
+    id: i32,
+    snippet: String,
 }
 
 impl BookOperator {
@@ -78,12 +235,19 @@ impl BookOperator {
             .context(DBSnafu)
     }
 
-    pub async fn all_filtered(&self, query: Option<IndexQuery>) -> Result<Vec<Model>, BookError> {
-        let conditions = Self::filter_conditions(query);
+    /// Books matching the query, `viewer_id` is the logged in user for the
+    /// `*_by_me` availability filters
+    pub async fn all_filtered(
+        &self,
+        query: Option<IndexQuery>,
+        viewer_id: Option<i32>,
+    ) -> Result<Vec<Model>, BookError> {
+        let search = self.search_query(query.as_ref());
+        let select = self.sorted(query.as_ref(), search.as_deref());
+        let conditions = self.filter_conditions(query, viewer_id);
 
-        Entity::find()
+        select
             .filter(conditions)
-            .order_by_desc(Column::Id)
             .all(&self.state.db)
             .await
             .context(DBSnafu)
@@ -93,16 +257,21 @@ impl BookOperator {
         &self,
         page: u64,
         query: Option<IndexQuery>,
+        viewer_id: Option<i32>,
     ) -> Result<BooksPaginate, BookError> {
         let page = if page > 0 { page } else { 1 }; // keep 1-indexed
         let page_0indexed = page - 1; // convert for SeaORM (0-based index)
 
-        let conditions = Self::filter_conditions(query);
+        let per_page = self
+            .state
+            .config
+            .pagination
+            .page_size(query.as_ref().and_then(|query| query.per_page));
+        let search = self.search_query(query.as_ref());
+        let select = self.sorted(query.as_ref(), search.as_deref());
+        let conditions = self.filter_conditions(query, viewer_id);
 
-        let book_pages = Entity::find()
-            .filter(conditions)
-            .order_by_desc(Column::Id)
-            .paginate(&self.state.db, 100);
+        let book_pages = select.filter(conditions).paginate(&self.state.db, per_page);
 
         let books = book_pages
             .fetch_page(page_0indexed)
@@ -110,10 +279,17 @@ impl BookOperator {
             .context(DBSnafu)?;
         let total_page = book_pages.num_pages().await.context(DBSnafu)?;
 
+        let snippets = match &search {
+            Some(search) => self.snippets(search, &books).await?,
+            None => HashMap::new(),
+        };
+
         Ok(BooksPaginate {
             books,
             current_page: page,
             total_page,
+            per_page,
+            snippets,
         })
     }
 
@@ -134,6 +310,15 @@ impl BookOperator {
         }
     }
 
+    /// Finds vec of book with the given ISBN (normalised as an ISBN-13)
+    pub async fn find_all_by_isbn(&self, isbn: &str) -> Result<Vec<Model>, BookError> {
+        Entity::find()
+            .filter(Column::Isbn.eq(isbn))
+            .all(&self.state.db)
+            .await
+            .context(DBSnafu)
+    }
+
     /// Finds vec of book by its Owner
     pub async fn find_all_by_owner(&self, owner_id: i32) -> Result<Vec<Model>, BookError> {
         Entity::find()
@@ -159,7 +344,8 @@ impl BookOperator {
     pub async fn create(&self, form: BookForm) -> Result<Model, BookError> {
         let book = ActiveModel {
             title: Set(form.title.clone()),
-            authors: Set(form.authors.clone()),
+            authors: Set(Self::normalize_authors(&form.authors)),
+            isbn: Set(Self::normalize_isbn(form.isbn.as_deref())?),
             owner_id: Set(form.owner_id),
             current_holder_id: Set(form.current_holder_id),
             description: Set(form.description.clone()),
@@ -167,7 +353,72 @@ impl BookOperator {
             ..Default::default()
         };
 
-        book.insert(&self.state.db).await.context(DBSnafu)
+        let book = book.insert(&self.state.db).await.context(DBSnafu)?;
+
+        if let Some(tags) = &form.tags {
+            TagOperator::set_book_tags(&self.state.db, book.id, &tag::parse_names(tags))
+                .await
+                .context(TagSnafu)?;
+        }
+
+        Self::set_credits(&self.state.db, book.id, &form).await?;
+
+        self.record_loan(&book, None, form.loan_notes, form.due_date)
+            .await?;
+
+        Ok(book)
+    }
+
+    /// Creates every book of an import in a single transaction:
+    /// either all of them are created, or none.
+    ///
+    /// Loans are opened for books held by someone else than their owner.
+    pub async fn import(&self, forms: Vec<BookForm>) -> Result<Vec<Model>, BookError> {
+        let txn = self.state.db.begin().await.context(DBSnafu)?;
+        let mut books = Vec::with_capacity(forms.len());
+
+        for form in forms {
+            let book = ActiveModel {
+                title: Set(form.title.clone()),
+                authors: Set(Self::normalize_authors(&form.authors)),
+                isbn: Set(Self::normalize_isbn(form.isbn.as_deref())?),
+                owner_id: Set(form.owner_id),
+                current_holder_id: Set(form.current_holder_id),
+                description: Set(form.description.clone()),
+                comment: Set(form.comment.clone()),
+                ..Default::default()
+            };
+            let book = book.insert(&txn).await.context(DBSnafu)?;
+
+            if let Some(tags) = &form.tags {
+                TagOperator::set_book_tags(&txn, book.id, &tag::parse_names(tags))
+                    .await
+                    .context(TagSnafu)?;
+            }
+
+            Self::set_credits(&txn, book.id, &form).await?;
+
+            if let Some(borrower_id) = book.current_holder_id
+                && borrower_id != book.owner_id
+            {
+                LoanOperator::new_loan(
+                    book.id,
+                    borrower_id,
+                    book.owner_id,
+                    form.loan_notes,
+                    form.due_date,
+                )
+                .insert(&txn)
+                .await
+                .context(DBSnafu)?;
+            }
+
+            books.push(book);
+        }
+
+        txn.commit().await.context(DBSnafu)?;
+
+        Ok(books)
     }
 
     /// Update a book (find with ID) from the given form data
@@ -178,43 +429,378 @@ impl BookOperator {
         let book_by_id = Self::find_by_id(self, id).await.context(BookSnafu);
 
         if let Ok(book) = book_by_id {
+            let previous_holder_id = book.current_holder_id;
             let mut book: ActiveModel = book.into();
 
             book.title = Set(form.title.clone());
-            book.authors = Set(form.authors.clone());
+            book.authors = Set(Self::normalize_authors(&form.authors));
+            book.isbn = Set(Self::normalize_isbn(form.isbn.as_deref())?);
             book.owner_id = Set(form.owner_id);
             book.current_holder_id = Set(form.current_holder_id);
             book.description = Set(form.description.clone());
             book.comment = Set(form.comment.clone());
 
-            book.update(&self.state.db).await.context(DBSnafu)
+            let book = book.update(&self.state.db).await.context(DBSnafu)?;
+
+            if let Some(tags) = &form.tags {
+                TagOperator::set_book_tags(&self.state.db, book.id, &tag::parse_names(tags))
+                    .await
+                    .context(TagSnafu)?;
+            }
+
+            Self::set_credits(&self.state.db, book.id, &form).await?;
+
+            self.record_loan(&book, previous_holder_id, form.loan_notes, form.due_date)
+                .await?;
+            self.offer_to_waitlist(&book).await?;
+
+            Ok(book)
         } else {
             Err(BookError::NotFound { id })
         }
     }
 
-    /// Delete a book (find with ID)
+    /// The current holder gives the book back to its owner, closing the ongoing loan
+    ///
+    /// # Error
+    /// Returns BookError::NotFound if id is not found in database
+    pub async fn give_back(&self, id: i32) -> Result<Model, BookError> {
+        let book = self.find_by_id(id).await?;
+        let previous_holder_id = book.current_holder_id;
+
+        let mut book: ActiveModel = book.into();
+        book.current_holder_id = Set(None);
+        let book = book.update(&self.state.db).await.context(DBSnafu)?;
+
+        self.record_loan(&book, previous_holder_id, None, None)
+            .await?;
+        self.offer_to_waitlist(&book).await?;
+
+        Ok(book)
+    }
+
+    /// Delete a book (find with ID) in a single transaction
+    ///
+    /// # Error
+    /// Returns BookError::NotFound if id is not found in database
     pub async fn delete(&self, id: i32) -> Result<DeleteResult, BookError> {
-        let book: Option<Model> = Entity::find_by_id(id)
-            .one(&self.state.db)
+        let book: Model = self.find_by_id(id).await?;
+        let cover = book.cover.clone();
+
+        let txn = self.state.db.begin().await.context(DBSnafu)?;
+        let result = Self::delete_cascade(&txn, book).await?;
+        txn.commit().await.context(DBSnafu)?;
+
+        // Files can't be rolled back, they are removed once the book is gone
+        if let Some(cover) = cover {
+            covers::remove_files(&self.state, &cover)
+                .await
+                .context(CoverSnafu)?;
+        }
+
+        Ok(result)
+    }
+
+    /// Deletes a book with its loans, tags, credits, requests and waitlist,
+    /// to be called inside a transaction. The cover files are left to the caller.
+    pub async fn delete_cascade<C: ConnectionTrait>(
+        db: &C,
+        book: Model,
+    ) -> Result<DeleteResult, BookError> {
+        LoanOperator::delete_all_by_book(db, book.id)
             .await
-            .context(DBSnafu)?;
-        let book: Model = book.unwrap();
+            .context(LoanSnafu)?;
+        TagOperator::delete_all_by_book(db, book.id)
+            .await
+            .context(TagSnafu)?;
+        AuthorOperator::delete_all_by_book(db, book.id)
+            .await
+            .context(AuthorSnafu)?;
+        BorrowRequestOperator::delete_all_by_book(db, book.id)
+            .await
+            .context(BorrowRequestSnafu)?;
+        WaitlistOperator::delete_all_by_book(db, book.id)
+            .await
+            .context(WaitlistSnafu)?;
 
-        book.delete(&self.state.db).await.context(DBSnafu)
+        book.delete(db).await.context(DBSnafu)
     }
 
     // private
 
-    fn filter_conditions(query: Option<IndexQuery>) -> Condition {
+    /// Authors as stored in `book.authors`, the same list as their credits
+    fn normalize_authors(authors: &str) -> String {
+        author::parse_names(authors).join(", ")
+    }
+
+    /// Saves the people credited on the book from the form.
+    ///
+    /// Authors are always replaced, the other roles only when given.
+    async fn set_credits<C: ConnectionTrait>(
+        db: &C,
+        book_id: i32,
+        form: &BookForm,
+    ) -> Result<(), BookError> {
+        let roles = [
+            (AuthorRole::Author, Some(&form.authors)),
+            (AuthorRole::Translator, form.translators.as_ref()),
+            (AuthorRole::Illustrator, form.illustrators.as_ref()),
+            (AuthorRole::Editor, form.editors.as_ref()),
+        ];
+
+        for (role, names) in roles {
+            if let Some(names) = names {
+                AuthorOperator::set_book_credits(db, book_id, role, &author::parse_names(names))
+                    .await
+                    .context(AuthorSnafu)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// FTS5 query of the `q` parameter, only on SQLite.
+    ///
+    /// Every word must match, as a prefix so that results show up while typing.
+    /// Words are quoted so that FTS5 operators typed by the user are plain text.
+    fn search_query(&self, query: Option<&IndexQuery>) -> Option<String> {
+        if self.state.db.get_database_backend() != DbBackend::Sqlite {
+            return None;
+        }
+
+        let words: Vec<String> = query?
+            .q
+            .as_deref()?
+            .split_whitespace()
+            .map(|word| format!("\"{}\"*", word.replace('"', "\"\"")))
+            .collect();
+
+        (!words.is_empty()).then(|| words.join(" "))
+    }
+
+    /// Books in the order asked by the query.
+    ///
+    /// Without `sort`, the most relevant books come first when searching with
+    /// FTS5, then the last added ones.
+    fn sorted(&self, query: Option<&IndexQuery>, search: Option<&str>) -> Select<Entity> {
+        let select = Entity::find();
+        let sort = query.and_then(|query| query.sort);
+
+        let select = match sort {
+            Some(sort) => {
+                let order: Order = query
+                    .and_then(|query| query.direction)
+                    .unwrap_or(sort.default_direction())
+                    .into();
+
+                match sort {
+                    BookSort::Title => {
+                        select.order_by(Func::lower(Expr::col((Entity, Column::Title))), order)
+                    }
+                    BookSort::Authors => {
+                        select.order_by(Func::lower(Expr::col((Entity, Column::Authors))), order)
+                    }
+                    BookSort::Owner => select
+                        .join_as(JoinType::LeftJoin, Relation::Owner.def(), Alias::new("owner"))
+                        .order_by(
+                            Func::lower(Expr::col((Alias::new("owner"), user::Column::Name))),
+                            order,
+                        ),
+                    BookSort::Holder => select
+                        .join_as(
+                            JoinType::LeftJoin,
+                            Relation::CurrentHolder.def(),
+                            Alias::new("holder"),
+                        )
+                        .order_by(
+                            Func::lower(Expr::col((Alias::new("holder"), user::Column::Name))),
+                            order,
+                        ),
+                    BookSort::Added => select.order_by(Column::Id, order),
+                }
+            }
+            None => match search {
+                Some(search) => select.order_by(
+                    Expr::cust_with_values(
+                        r#"(SELECT bm25(book_search) FROM book_search WHERE book_search MATCH ? AND book_search.rowid = "book"."id")"#,
+                        [search],
+                    ),
+                    Order::Asc,
+                ),
+                None => select,
+            },
+        };
+
+        // Stable order between pages for equal values
+        select.order_by_desc(Column::Id)
+    }
+
+    /// Ids of the books having one of the tags matching the condition
+    fn books_tagged(tag_condition: Condition) -> SelectStatement {
+        let tag_ids = Query::select()
+            .column(tag::Column::Id)
+            .from(tag::Entity)
+            .cond_where(tag_condition)
+            .to_owned();
+
+        Query::select()
+            .column(book_tag::Column::BookId)
+            .from(book_tag::Entity)
+            .and_where(book_tag::Column::TagId.in_subquery(tag_ids))
+            .to_owned()
+    }
+
+    /// Extracts of the text matching the search for the given books
+    async fn snippets(
+        &self,
+        search: &str,
+        books: &[Model],
+    ) -> Result<HashMap<i32, String>, BookError> {
+        if books.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        // The matched words are surrounded by control characters, replaced by
+        // <mark> once the rest of the text is escaped
+        let placeholders = vec!["?"; books.len()].join(", ");
+        let sql = format!(
+            "SELECT rowid AS id, snippet(book_search, -1, char(2), char(3), '…', 16) AS snippet \
+             FROM book_search WHERE book_search MATCH ? AND rowid IN ({})",
+            placeholders
+        );
+        let mut values: Vec<Value> = vec![search.into()];
+        values.extend(books.iter().map(|book| Value::from(book.id)));
+
+        let snippets = BookSnippet::find_by_statement(Statement::from_sql_and_values(
+            DbBackend::Sqlite,
+            sql,
+            values,
+        ))
+        .all(&self.state.db)
+        .await
+        .context(DBSnafu)?;
+
+        Ok(snippets
+            .into_iter()
+            .map(|snippet| {
+                let html = escape_html(&snippet.snippet)
+                    .replace('\u{2}', "<mark>")
+                    .replace('\u{3}', "</mark>");
+                (snippet.id, html)
+            })
+            .collect())
+    }
+
+    /// Normalises the optional ISBN of a form, an empty ISBN is stored as None
+    fn normalize_isbn(isbn: Option<&str>) -> Result<Option<String>, BookError> {
+        match isbn.map(str::trim) {
+            Some(isbn) if !isbn.is_empty() => Ok(Some(isbn::normalize(isbn).context(IsbnSnafu)?)),
+            _ => Ok(None),
+        }
+    }
+
+    /// Keeps the loan history in sync with the current holder of a book.
+    ///
+    /// When the holder changes, the ongoing loan (if any) is closed and a new one
+    /// is opened for the new holder. A book held by its owner is not a loan.
+    /// When the holder stays the same, only the due date of the ongoing loan is updated.
+    async fn record_loan(
+        &self,
+        book: &Model,
+        previous_holder_id: Option<i32>,
+        notes: Option<String>,
+        due_date: Option<Date>,
+    ) -> Result<(), BookError> {
+        let loan_operator = LoanOperator::new(self.state.clone());
+
+        if book.current_holder_id == previous_holder_id {
+            return loan_operator
+                .update_due_date(book.id, due_date)
+                .await
+                .context(LoanSnafu);
+        }
+
+        if previous_holder_id.is_some() {
+            LoanOperator::give_back(&self.state.db, book.id)
+                .await
+                .context(LoanSnafu)?;
+        }
+
+        if let Some(borrower_id) = book.current_holder_id
+            && borrower_id != book.owner_id
+        {
+            loan_operator
+                .borrow(book.id, borrower_id, book.owner_id, notes, due_date)
+                .await
+                .context(LoanSnafu)?;
+        }
+
+        Ok(())
+    }
+
+    /// Once the book is back, the first user of its waitlist gets it
+    async fn offer_to_waitlist(&self, book: &Model) -> Result<(), BookError> {
+        if book.is_available() {
+            WaitlistOperator::new(self.state.clone())
+                .offer_next(book.id)
+                .await
+                .context(WaitlistSnafu)?;
+        }
+
+        Ok(())
+    }
+
+    fn filter_conditions(&self, query: Option<IndexQuery>, viewer_id: Option<i32>) -> Condition {
+        let search = self.search_query(query.as_ref());
         let mut conditions = Condition::all();
         if let Some(book_query) = query {
+            if let Some(search) = search {
+                conditions = conditions.add(Expr::cust_with_values(
+                    r#""book"."id" IN (SELECT rowid FROM book_search WHERE book_search MATCH ?)"#,
+                    [search],
+                ));
+            } else if let Some(q) = &book_query.q {
+                // Without FTS5, every word must be in one of the text columns
+                for word in q.split_whitespace() {
+                    conditions = conditions.add(
+                        Condition::any()
+                            .add(filter::contains_ignore_case(Column::Title, word))
+                            .add(filter::contains_ignore_case(Column::Authors, word))
+                            .add(filter::contains_ignore_case(Column::Description, word))
+                            .add(filter::contains_ignore_case(Column::Comment, word)),
+                    );
+                }
+            }
+
             if let Some(title) = book_query.title {
-                conditions = conditions.add(Column::Title.contains(&title));
+                conditions = conditions.add(filter::contains_ignore_case(Column::Title, &title));
             }
 
             if let Some(authors) = book_query.authors {
-                conditions = conditions.add(Column::Authors.contains(&authors));
+                conditions =
+                    conditions.add(filter::contains_ignore_case(Column::Authors, &authors));
+            }
+
+            if let Some(isbn) = book_query.isbn {
+                // A complete ISBN is matched exactly once normalised,
+                // otherwise look for the digits typed so far
+                conditions = match isbn::normalize(&isbn) {
+                    Ok(isbn) => conditions.add(Column::Isbn.eq(isbn)),
+                    Err(_) => conditions.add(filter::contains_ignore_case(
+                        Column::Isbn,
+                        &isbn.replace(['-', ' '], ""),
+                    )),
+                };
+            }
+
+            if let Some(author_id) = book_query.author_id {
+                let credited_books = Query::select()
+                    .column(book_author::Column::BookId)
+                    .from(book_author::Entity)
+                    .and_where(book_author::Column::AuthorId.eq(author_id))
+                    .to_owned();
+
+                conditions = conditions.add(Column::Id.in_subquery(credited_books));
             }
 
             if let Some(owner_id) = book_query.owner_id {
@@ -224,7 +810,72 @@ impl BookOperator {
             if let Some(current_holder_id) = book_query.current_holder_id {
                 conditions = conditions.add(Column::CurrentHolderId.eq(current_holder_id));
             }
+
+            if book_query.overdue.unwrap_or(false) {
+                // Books with an ongoing loan whose due date is already passed
+                let overdue_books = Query::select()
+                    .column(loan::Column::BookId)
+                    .from(loan::Entity)
+                    .and_where(loan::Column::ReturnDate.is_null())
+                    .and_where(loan::Column::DueDate.lt(chrono::Local::now().date_naive()))
+                    .to_owned();
+
+                conditions = conditions.add(Column::Id.in_subquery(overdue_books));
+            }
+
+            let tags = book_query
+                .tags
+                .as_deref()
+                .map(tag::parse_names)
+                .unwrap_or_default();
+            if !tags.is_empty() {
+                conditions = match book_query.tag_mode.unwrap_or_default() {
+                    TagMode::All => tags.iter().fold(conditions, |conditions, name| {
+                        conditions.add(Column::Id.in_subquery(Self::books_tagged(
+                            filter::eq_ignore_case(tag::Column::Name, name),
+                        )))
+                    }),
+                    TagMode::Any => {
+                        let names = tags.iter().fold(Condition::any(), |names, name| {
+                            names.add(filter::eq_ignore_case(tag::Column::Name, name))
+                        });
+                        conditions.add(Column::Id.in_subquery(Self::books_tagged(names)))
+                    }
+                };
+            }
+
+            if let Some(availability) = book_query.availability {
+                let lent_out = Condition::all()
+                    .add(Column::CurrentHolderId.is_not_null())
+                    .add(sea_orm::sea_query::ExprTrait::ne(
+                        Expr::col((Entity, Column::CurrentHolderId)),
+                        Expr::col((Entity, Column::OwnerId)),
+                    ));
+
+                conditions = match (availability, viewer_id) {
+                    (Availability::Available, _) => conditions.add(lent_out.not()),
+                    (Availability::LentOut, _) => conditions.add(lent_out),
+                    (Availability::LentByMe, Some(viewer_id)) => {
+                        conditions.add(lent_out).add(Column::OwnerId.eq(viewer_id))
+                    }
+                    (Availability::BorrowedByMe, Some(viewer_id)) => conditions
+                        .add(lent_out)
+                        .add(Column::CurrentHolderId.eq(viewer_id)),
+                    // Nobody is logged in, so nothing is lent or borrowed by them
+                    (Availability::LentByMe | Availability::BorrowedByMe, None) => {
+                        conditions.add(Column::Id.is_null())
+                    }
+                };
+            }
         }
         conditions
     }
 }
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
@@ -0,0 +1,25 @@
+use sea_orm::entity::prelude::*;
+
+use crate::models::author::AuthorRole;
+
+/// Credit of an author on a book, with their role and their rank
+/// among the people having the same role
+#[sea_orm::model]
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "book_author")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub book_id: i32,
+    #[sea_orm(belongs_to, relation_enum = "Book", from = "book_id", to = "id")]
+    pub book: HasOne<super::book::Entity>,
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub author_id: i32,
+    #[sea_orm(belongs_to, relation_enum = "Author", from = "author_id", to = "id")]
+    pub author: HasOne<super::author::Entity>,
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub role: AuthorRole,
+    pub position: i32,
+}
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {}
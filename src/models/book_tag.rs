@@ -0,0 +1,19 @@
+use sea_orm::entity::prelude::*;
+
+/// Join table between the books and their tags
+#[sea_orm::model]
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "book_tag")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub book_id: i32,
+    #[sea_orm(belongs_to, relation_enum = "Book", from = "book_id", to = "id")]
+    pub book: HasOne<super::book::Entity>,
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub tag_id: i32,
+    #[sea_orm(belongs_to, relation_enum = "Tag", from = "tag_id", to = "id")]
+    pub tag: HasOne<super::tag::Entity>,
+}
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,419 @@
+use sea_orm::ActiveValue::Set;
+use sea_orm::Condition;
+use sea_orm::ConnectionTrait;
+use sea_orm::DeleteResult;
+use sea_orm::QueryOrder;
+use sea_orm::TransactionTrait;
+use sea_orm::entity::prelude::*;
+use sea_orm::sea_query::Expr;
+use snafu::ResultExt;
+use snafu::prelude::*;
+
+use crate::models::book;
+use crate::models::loan::LoanOperator;
+use crate::models::waitlist::{WaitlistError, WaitlistOperator};
+use crate::state::AppState;
+
+#[sea_orm::model]
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "borrow_request")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub book_id: i32,
+    #[sea_orm(belongs_to, relation_enum = "Book", from = "book_id", to = "id")]
+    pub book: HasOne<super::book::Entity>,
+    pub requester_id: i32,
+    #[sea_orm(
+        belongs_to,
+        relation_enum = "Requester",
+        from = "requester_id",
+        to = "id"
+    )]
+    pub requester: HasOne<super::user::Entity>,
+    pub status: RequestStatus,
+    /// Written by the requester for the owner, kept as the notes of the loan
+    pub message: Option<String>,
+    pub created_at: DateTime,
+    pub answered_at: Option<DateTime>,
+}
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {}
+
+impl Model {
+    pub fn is_pending(&self) -> bool {
+        self.status == RequestStatus::Pending
+    }
+}
+
+/// Where a borrow request stands
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+    EnumIter,
+    DeriveActiveEnum,
+    serde::Deserialize,
+    serde::Serialize,
+    utoipa::ToSchema,
+)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::None)")]
+#[serde(rename_all = "snake_case")]
+pub enum RequestStatus {
+    /// Waiting for the answer of the owner
+    #[sea_orm(string_value = "pending")]
+    Pending,
+    /// The owner lent the book to the requester
+    #[sea_orm(string_value = "approved")]
+    Approved,
+    /// The owner refused, or lent the book to someone else
+    #[sea_orm(string_value = "declined")]
+    Declined,
+    /// The requester withdrew the request
+    #[sea_orm(string_value = "cancelled")]
+    Cancelled,
+}
+
+impl RequestStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RequestStatus::Pending => "pending",
+            RequestStatus::Approved => "approved",
+            RequestStatus::Declined => "declined",
+            RequestStatus::Cancelled => "cancelled",
+        }
+    }
+}
+
+#[derive(Debug, Snafu)]
+#[snafu(visibility(pub))]
+pub enum BorrowRequestError {
+    /// Db Error from SeaOrm
+    #[snafu(display("Database error"))]
+    DB { source: sea_orm::DbErr },
+    /// When BorrowRequest with Id is not found
+    #[snafu(display("Borrow request with id {id} not found"))]
+    NotFound { id: i32 },
+    /// The book of the request does not exist anymore
+    #[snafu(display("Book with id {id} not found"))]
+    BookNotFound { id: i32 },
+    #[snafu(display("You can't borrow your own book"))]
+    OwnBook,
+    /// Someone else than the owner holds the book
+    #[snafu(display("The book is already lent out"))]
+    Unavailable,
+    #[snafu(display("You already asked to borrow this book"))]
+    AlreadyRequested,
+    /// The book is back and it is the turn of the next user of the waitlist
+    #[snafu(display("The book is kept for the next person on the waitlist"))]
+    Reserved,
+    /// Only pending requests can be answered or cancelled
+    #[snafu(display("Borrow request {id} is already answered"))]
+    NotPending { id: i32 },
+    /// Error while updating the waitlist of the book
+    #[snafu(display("Waitlist error"))]
+    Waitlist { source: WaitlistError },
+}
+
+#[derive(Debug)]
+/// Operator for the requests to borrow a book, answered by its owner
+pub struct BorrowRequestOperator {
+    pub state: AppState,
+}
+
+impl BorrowRequestOperator {
+    /// Creates a new `BorrowRequestOperator` with the given application state.
+    pub fn new(state: AppState) -> Self {
+        Self { state }
+    }
+
+    /// Finds a borrow request by its ID.
+    ///
+    /// # Errors
+    /// Returns `BorrowRequestError::NotFound` if no request exists with the given ID.
+    pub async fn find_by_id(&self, id: i32) -> Result<Model, BorrowRequestError> {
+        Entity::find_by_id(id)
+            .one(&self.state.db)
+            .await
+            .context(DBSnafu)?
+            .context(NotFoundSnafu { id })
+    }
+
+    /// The request of the user for the book still waiting for an answer, if any
+    pub async fn find_pending(
+        &self,
+        book_id: i32,
+        requester_id: i32,
+    ) -> Result<Option<Model>, BorrowRequestError> {
+        Entity::find()
+            .filter(Column::BookId.eq(book_id))
+            .filter(Column::RequesterId.eq(requester_id))
+            .filter(Column::Status.eq(RequestStatus::Pending))
+            .one(&self.state.db)
+            .await
+            .context(DBSnafu)
+    }
+
+    /// Pending requests on the books of the owner, oldest first
+    pub async fn find_incoming(&self, owner_id: i32) -> Result<Vec<Model>, BorrowRequestError> {
+        let book_ids: Vec<i32> = book::Entity::find()
+            .filter(book::Column::OwnerId.eq(owner_id))
+            .all(&self.state.db)
+            .await
+            .context(DBSnafu)?
+            .into_iter()
+            .map(|book| book.id)
+            .collect();
+
+        Entity::find()
+            .filter(Column::BookId.is_in(book_ids))
+            .filter(Column::Status.eq(RequestStatus::Pending))
+            .order_by_asc(Column::CreatedAt)
+            .order_by_asc(Column::Id)
+            .all(&self.state.db)
+            .await
+            .context(DBSnafu)
+    }
+
+    /// Every request made by the user, most recent first
+    pub async fn find_outgoing(&self, requester_id: i32) -> Result<Vec<Model>, BorrowRequestError> {
+        Entity::find()
+            .filter(Column::RequesterId.eq(requester_id))
+            .order_by_desc(Column::CreatedAt)
+            .order_by_desc(Column::Id)
+            .all(&self.state.db)
+            .await
+            .context(DBSnafu)
+    }
+
+    /// Asks the owner to lend the book to the requester.
+    ///
+    /// When the book was offered to the requester from the waitlist, it stays
+    /// reserved for the request until the owner answers it.
+    ///
+    /// # Errors
+    /// Fails when the requester owns the book, when someone else already holds it
+    /// or has their turn on the waitlist, or when the requester is still waiting
+    /// for an answer on this book.
+    pub async fn create(
+        &self,
+        book: &book::Model,
+        requester_id: i32,
+        message: Option<String>,
+    ) -> Result<Model, BorrowRequestError> {
+        ensure!(book.owner_id != requester_id, OwnBookSnafu);
+        ensure!(book.is_available(), UnavailableSnafu);
+        // The turn of someone who didn't ask in time goes to the next one
+        WaitlistOperator::new(self.state.clone())
+            .expire_offer(book)
+            .await
+            .context(WaitlistSnafu)?;
+        ensure!(
+            self.find_pending(book.id, requester_id).await?.is_none(),
+            AlreadyRequestedSnafu
+        );
+
+        let txn = self.state.db.begin().await.context(DBSnafu)?;
+
+        let offered = WaitlistOperator::find_offered_in(&txn, book)
+            .await
+            .context(WaitlistSnafu)?;
+        ensure!(
+            offered
+                .as_ref()
+                .is_none_or(|entry| entry.user_id == requester_id),
+            ReservedSnafu
+        );
+
+        let request = ActiveModel {
+            book_id: Set(book.id),
+            requester_id: Set(requester_id),
+            status: Set(RequestStatus::Pending),
+            message: Set(message),
+            created_at: Set(chrono::Utc::now().naive_utc()),
+            answered_at: Set(None),
+            ..Default::default()
+        }
+        .insert(&txn)
+        .await
+        .context(DBSnafu)?;
+
+        if let Some(entry) = offered {
+            WaitlistOperator::reserve(&txn, entry, request.id)
+                .await
+                .context(WaitlistSnafu)?;
+        }
+
+        txn.commit().await.context(DBSnafu)?;
+
+        Ok(request)
+    }
+
+    /// Lends the book to the requester: they become its current holder and a loan
+    /// starts, to be given back before the optional `due_date`.
+    ///
+    /// The other pending requests on the book are declined, and the requester
+    /// leaves its waitlist.
+    ///
+    /// # Errors
+    /// Fails when the book is lent out, or kept for someone else of the waitlist.
+    pub async fn approve(
+        &self,
+        id: i32,
+        due_date: Option<Date>,
+    ) -> Result<Model, BorrowRequestError> {
+        let txn = self.state.db.begin().await.context(DBSnafu)?;
+
+        // Answered in the transaction, so that a concurrent answer fails
+        let now = chrono::Utc::now().naive_utc();
+        let request = Self::answer(&txn, id, RequestStatus::Approved, now).await?;
+
+        let book = book::Entity::find_by_id(request.book_id)
+            .one(&txn)
+            .await
+            .context(DBSnafu)?
+            .context(BookNotFoundSnafu {
+                id: request.book_id,
+            })?;
+
+        let offered = WaitlistOperator::find_offered_in(&txn, &book)
+            .await
+            .context(WaitlistSnafu)?;
+        ensure!(
+            offered.is_none_or(|entry| entry.user_id == request.requester_id),
+            ReservedSnafu
+        );
+        WaitlistOperator::remove(&txn, book.id, request.requester_id)
+            .await
+            .context(WaitlistSnafu)?;
+
+        // Only lent while still available, another loan may have started since the read
+        let lent = book::Entity::update_many()
+            .col_expr(
+                book::Column::CurrentHolderId,
+                Expr::value(request.requester_id),
+            )
+            .filter(book::Column::Id.eq(book.id))
+            .filter(
+                Condition::any()
+                    .add(book::Column::CurrentHolderId.is_null())
+                    .add(Expr::col(book::Column::CurrentHolderId).equals(book::Column::OwnerId)),
+            )
+            .exec(&txn)
+            .await
+            .context(DBSnafu)?;
+        ensure!(lent.rows_affected == 1, UnavailableSnafu);
+
+        LoanOperator::new_loan(
+            book.id,
+            request.requester_id,
+            book.owner_id,
+            request.message.clone(),
+            due_date,
+        )
+        .insert(&txn)
+        .await
+        .context(DBSnafu)?;
+
+        Entity::update_many()
+            .col_expr(Column::Status, Expr::value(RequestStatus::Declined))
+            .col_expr(Column::AnsweredAt, Expr::value(now))
+            .filter(Column::BookId.eq(request.book_id))
+            .filter(Column::Id.ne(request.id))
+            .filter(Column::Status.eq(RequestStatus::Pending))
+            .exec(&txn)
+            .await
+            .context(DBSnafu)?;
+
+        txn.commit().await.context(DBSnafu)?;
+
+        Ok(request)
+    }
+
+    /// The owner refuses to lend the book
+    pub async fn decline(&self, id: i32) -> Result<Model, BorrowRequestError> {
+        self.close(id, RequestStatus::Declined).await
+    }
+
+    /// The requester withdraws the request
+    pub async fn cancel(&self, id: i32) -> Result<Model, BorrowRequestError> {
+        self.close(id, RequestStatus::Cancelled).await
+    }
+
+    /// Delete every request on a book
+    pub async fn delete_all_by_book<C: ConnectionTrait>(
+        db: &C,
+        book_id: i32,
+    ) -> Result<DeleteResult, BorrowRequestError> {
+        Entity::delete_many()
+            .filter(Column::BookId.eq(book_id))
+            .exec(db)
+            .await
+            .context(DBSnafu)
+    }
+
+    /// Delete every request made by the user
+    pub async fn delete_all_by_user<C: ConnectionTrait>(
+        db: &C,
+        user_id: i32,
+    ) -> Result<DeleteResult, BorrowRequestError> {
+        Entity::delete_many()
+            .filter(Column::RequesterId.eq(user_id))
+            .exec(db)
+            .await
+            .context(DBSnafu)
+    }
+
+    // private
+
+    /// Answers a pending request without lending the book. The turn reserved
+    /// for the request ends, and the book is offered to the next user of the waitlist.
+    async fn close(&self, id: i32, status: RequestStatus) -> Result<Model, BorrowRequestError> {
+        let txn = self.state.db.begin().await.context(DBSnafu)?;
+
+        let request = Self::answer(&txn, id, status, chrono::Utc::now().naive_utc()).await?;
+
+        WaitlistOperator::remove_by_request(&txn, request.id)
+            .await
+            .context(WaitlistSnafu)?;
+
+        txn.commit().await.context(DBSnafu)?;
+
+        WaitlistOperator::new(self.state.clone())
+            .offer_next(request.book_id)
+            .await
+            .context(WaitlistSnafu)?;
+
+        Ok(request)
+    }
+
+    /// Gives its answer to the request if it is still pending, with a
+    /// conditional update so that only one of two concurrent answers succeeds
+    async fn answer<C: ConnectionTrait>(
+        db: &C,
+        id: i32,
+        status: RequestStatus,
+        now: DateTime,
+    ) -> Result<Model, BorrowRequestError> {
+        let answered = Entity::update_many()
+            .col_expr(Column::Status, Expr::value(status))
+            .col_expr(Column::AnsweredAt, Expr::value(now))
+            .filter(Column::Id.eq(id))
+            .filter(Column::Status.eq(RequestStatus::Pending))
+            .exec(db)
+            .await
+            .context(DBSnafu)?;
+
+        let request = Entity::find_by_id(id)
+            .one(db)
+            .await
+            .context(DBSnafu)?
+            .context(NotFoundSnafu { id })?;
+        ensure!(answered.rows_affected == 1, NotPendingSnafu { id });
+
+        Ok(request)
+    }
+}
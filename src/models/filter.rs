@@ -0,0 +1,61 @@
+use sea_orm::Condition;
+use sea_orm::Order;
+use sea_orm::sea_query::{Expr, ExprTrait, Func, IntoColumnRef, LikeExpr};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Direction of the `sort` query parameter of the lists
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SortDirection {
+    Asc,
+    Desc,
+}
+
+impl SortDirection {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SortDirection::Asc => "asc",
+            SortDirection::Desc => "desc",
+        }
+    }
+
+    pub fn reverse(&self) -> Self {
+        match self {
+            SortDirection::Asc => SortDirection::Desc,
+            SortDirection::Desc => SortDirection::Asc,
+        }
+    }
+}
+
+impl From<SortDirection> for Order {
+    fn from(direction: SortDirection) -> Self {
+        match direction {
+            SortDirection::Asc => Order::Asc,
+            SortDirection::Desc => Order::Desc,
+        }
+    }
+}
+
+/// Case insensitive `LIKE '%value%'` which behaves the same on every database.
+///
+/// `ColumnTrait::contains` is case insensitive on SQLite and MySQL but not on
+/// PostgreSQL, so both sides are lowercased. The `%` and `_` typed by the
+/// user are matched literally.
+pub fn contains_ignore_case(column: impl IntoColumnRef, value: &str) -> Condition {
+    let escaped = value
+        .to_lowercase()
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+
+    Condition::all().add(
+        Expr::expr(Func::lower(Expr::col(column)))
+            .like(LikeExpr::new(format!("%{}%", escaped)).escape('\\')),
+    )
+}
+
+/// Case insensitive `=`, e.g. to find a name typed by the user
+pub fn eq_ignore_case(column: impl IntoColumnRef, value: &str) -> Condition {
+    Condition::all().add(Expr::expr(Func::lower(Expr::col(column))).eq(value.to_lowercase()))
+}
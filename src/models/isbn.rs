@@ -0,0 +1,157 @@
+use snafu::prelude::*;
+
+#[derive(Debug, Snafu)]
+#[snafu(visibility(pub))]
+pub enum IsbnError {
+    /// The ISBN doesn't have 10 or 13 characters once hyphens and spaces are removed
+    #[snafu(display("ISBN {isbn} must have 10 or 13 digits"))]
+    InvalidLength { isbn: String },
+    /// The ISBN contains something else than digits (and a final X for ISBN-10)
+    #[snafu(display("ISBN {isbn} contains invalid characters"))]
+    InvalidCharacters { isbn: String },
+    /// The check digit doesn't match the other digits
+    #[snafu(display("ISBN {isbn} has a wrong check digit"))]
+    InvalidChecksum { isbn: String },
+}
+
+/// Validates an ISBN-10 or ISBN-13 and returns it normalised as an ISBN-13
+/// without hyphens nor spaces.
+///
+/// # Errors
+/// Returns an `IsbnError` if the length, the characters or the check digit are wrong.
+pub fn normalize(raw: &str) -> Result<String, IsbnError> {
+    let isbn: String = raw
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '-')
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+
+    // Checked first, the lengths below count bytes
+    ensure!(isbn.is_ascii(), InvalidCharactersSnafu { isbn: raw });
+
+    match isbn.len() {
+        10 => {
+            let (body, check) = isbn.split_at(9);
+            ensure!(
+                body.chars().all(|c| c.is_ascii_digit())
+                    && check.chars().all(|c| c.is_ascii_digit() || c == 'X'),
+                InvalidCharactersSnafu { isbn: raw }
+            );
+            ensure!(
+                isbn10_check_digit(body) == check.chars().next().unwrap(),
+                InvalidChecksumSnafu { isbn: raw }
+            );
+
+            let body = format!("978{}", body);
+            let check = isbn13_check_digit(&body);
+
+            Ok(format!("{}{}", body, check))
+        }
+        13 => {
+            ensure!(
+                isbn.chars().all(|c| c.is_ascii_digit()),
+                InvalidCharactersSnafu { isbn: raw }
+            );
+            let (body, check) = isbn.split_at(12);
+            ensure!(
+                isbn13_check_digit(body) == check.chars().next().unwrap(),
+                InvalidChecksumSnafu { isbn: raw }
+            );
+
+            Ok(isbn)
+        }
+        _ => InvalidLengthSnafu { isbn: raw }.fail(),
+    }
+}
+
+/// Computes the check digit of the 9 first digits of an ISBN-10
+fn isbn10_check_digit(body: &str) -> char {
+    let sum: u32 = body
+        .chars()
+        .filter_map(|c| c.to_digit(10))
+        .zip((2..=10).rev())
+        .map(|(digit, weight)| digit * weight)
+        .sum();
+
+    match (11 - sum % 11) % 11 {
+        10 => 'X',
+        digit => char::from_digit(digit, 10).unwrap(),
+    }
+}
+
+/// Computes the check digit of the 12 first digits of an ISBN-13
+fn isbn13_check_digit(body: &str) -> char {
+    let sum: u32 = body
+        .chars()
+        .filter_map(|c| c.to_digit(10))
+        .zip([1, 3].into_iter().cycle())
+        .map(|(digit, weight)| digit * weight)
+        .sum();
+
+    char::from_digit((10 - sum % 10) % 10, 10).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn valid_isbn13() {
+        assert_eq!(normalize("978-0-441-17271-9").unwrap(), "9780441172719");
+    }
+
+    #[test]
+    fn valid_isbn10_converted_to_isbn13() {
+        assert_eq!(normalize("0 441 17271 7").unwrap(), "9780441172719");
+    }
+
+    #[test]
+    fn isbn10_with_x_check_digit() {
+        assert_eq!(normalize("0-8044-2957-x").unwrap(), "9780804429573");
+    }
+
+    #[test]
+    fn bad_checksum() {
+        assert!(matches!(
+            normalize("9780441172718"),
+            Err(IsbnError::InvalidChecksum { .. })
+        ));
+        assert!(matches!(
+            normalize("0441172718"),
+            Err(IsbnError::InvalidChecksum { .. })
+        ));
+    }
+
+    #[test]
+    fn invalid_length() {
+        assert!(matches!(
+            normalize("978044117271"),
+            Err(IsbnError::InvalidLength { .. })
+        ));
+    }
+
+    #[test]
+    fn invalid_characters() {
+        assert!(matches!(
+            normalize("97804411727A9"),
+            Err(IsbnError::InvalidCharacters { .. })
+        ));
+        assert!(matches!(
+            normalize("X441172717"),
+            Err(IsbnError::InvalidCharacters { .. })
+        ));
+    }
+
+    #[test]
+    fn non_ascii_input() {
+        // 10 bytes, the 9th character spans bytes 8 and 9
+        assert!(matches!(
+            normalize("12345678é"),
+            Err(IsbnError::InvalidCharacters { .. })
+        ));
+        assert!(matches!(
+            normalize("978044117271é"),
+            Err(IsbnError::InvalidCharacters { .. })
+        ));
+    }
+}
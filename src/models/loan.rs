@@ -0,0 +1,226 @@
+use sea_orm::ActiveValue::Set;
+use sea_orm::Condition;
+use sea_orm::ConnectionTrait;
+use sea_orm::DeleteResult;
+use sea_orm::QueryOrder;
+use sea_orm::entity::prelude::*;
+use sea_orm::sea_query::Expr;
+use snafu::ResultExt;
+use snafu::prelude::*;
+
+use crate::state::AppState;
+
+#[sea_orm::model]
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "loan")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub book_id: i32,
+    #[sea_orm(belongs_to, relation_enum = "Book", from = "book_id", to = "id")]
+    pub book: HasOne<super::book::Entity>,
+    /// None once the borrower is deleted
+    pub borrower_id: Option<i32>,
+    #[sea_orm(
+        belongs_to,
+        relation_enum = "Borrower",
+        from = "borrower_id",
+        to = "id"
+    )]
+    pub borrower: HasOne<super::user::Entity>,
+    /// None once the lender is deleted
+    pub lender_id: Option<i32>,
+    #[sea_orm(belongs_to, relation_enum = "Lender", from = "lender_id", to = "id")]
+    pub lender: HasOne<super::user::Entity>,
+    pub start_date: Date,
+    pub return_date: Option<Date>,
+    pub notes: Option<String>,
+    pub due_date: Option<Date>,
+}
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {}
+
+impl Model {
+    /// A loan is ongoing as long as the book has not been returned
+    pub fn is_ongoing(&self) -> bool {
+        self.return_date.is_none()
+    }
+
+    /// A loan is overdue when it is still ongoing after its due date
+    pub fn is_overdue(&self) -> bool {
+        match self.due_date {
+            Some(due_date) => self.is_ongoing() && due_date < chrono::Local::now().date_naive(),
+            None => false,
+        }
+    }
+}
+
+#[derive(Debug, Snafu)]
+#[snafu(visibility(pub))]
+pub enum LoanError {
+    /// Db Error from SeaOrm
+    #[snafu(display("Database error"))]
+    DB { source: sea_orm::DbErr },
+}
+
+#[derive(Debug)]
+/// Operator for the loan history of books
+pub struct LoanOperator {
+    pub state: AppState,
+}
+
+impl LoanOperator {
+    /// Creates a new `LoanOperator` with the given application state.
+    pub fn new(state: AppState) -> Self {
+        Self { state }
+    }
+
+    /// Lists every loan of a book, most recent first.
+    pub async fn find_all_by_book(&self, book_id: i32) -> Result<Vec<Model>, LoanError> {
+        Entity::find()
+            .filter(Column::BookId.eq(book_id))
+            .order_by_desc(Column::StartDate)
+            .order_by_desc(Column::Id)
+            .all(&self.state.db)
+            .await
+            .context(DBSnafu)
+    }
+
+    /// Lists every loan where the user is the borrower or the lender, most recent first.
+    pub async fn find_all_by_user(&self, user_id: i32) -> Result<Vec<Model>, LoanError> {
+        Entity::find()
+            .filter(
+                Condition::any()
+                    .add(Column::BorrowerId.eq(user_id))
+                    .add(Column::LenderId.eq(user_id)),
+            )
+            .order_by_desc(Column::StartDate)
+            .order_by_desc(Column::Id)
+            .all(&self.state.db)
+            .await
+            .context(DBSnafu)
+    }
+
+    /// Lists every loan which is not returned yet.
+    pub async fn all_ongoing(&self) -> Result<Vec<Model>, LoanError> {
+        Entity::find()
+            .filter(Column::ReturnDate.is_null())
+            .all(&self.state.db)
+            .await
+            .context(DBSnafu)
+    }
+
+    /// Finds the ongoing loan of a book, if any.
+    pub async fn find_ongoing_by_book(&self, book_id: i32) -> Result<Option<Model>, LoanError> {
+        Entity::find()
+            .filter(Column::BookId.eq(book_id))
+            .filter(Column::ReturnDate.is_null())
+            .order_by_desc(Column::Id)
+            .one(&self.state.db)
+            .await
+            .context(DBSnafu)
+    }
+
+    /// Records the start of a loan of `book_id` from `lender_id` to `borrower_id`,
+    /// to be given back before the optional `due_date`.
+    pub async fn borrow(
+        &self,
+        book_id: i32,
+        borrower_id: i32,
+        lender_id: i32,
+        notes: Option<String>,
+        due_date: Option<Date>,
+    ) -> Result<Model, LoanError> {
+        Self::new_loan(book_id, borrower_id, lender_id, notes, due_date)
+            .insert(&self.state.db)
+            .await
+            .context(DBSnafu)
+    }
+
+    /// Builds a loan starting today, to be inserted by the caller
+    /// (for example inside a transaction).
+    pub fn new_loan(
+        book_id: i32,
+        borrower_id: i32,
+        lender_id: i32,
+        notes: Option<String>,
+        due_date: Option<Date>,
+    ) -> ActiveModel {
+        ActiveModel {
+            book_id: Set(book_id),
+            borrower_id: Set(Some(borrower_id)),
+            lender_id: Set(Some(lender_id)),
+            start_date: Set(chrono::Local::now().date_naive()),
+            return_date: Set(None),
+            notes: Set(notes),
+            due_date: Set(due_date),
+            ..Default::default()
+        }
+    }
+
+    /// Changes the due date of the ongoing loan of a book, if any.
+    pub async fn update_due_date(
+        &self,
+        book_id: i32,
+        due_date: Option<Date>,
+    ) -> Result<(), LoanError> {
+        if let Some(loan) = self.find_ongoing_by_book(book_id).await?
+            && loan.due_date != due_date
+        {
+            let mut loan: ActiveModel = loan.into();
+            loan.due_date = Set(due_date);
+            loan.update(&self.state.db).await.context(DBSnafu)?;
+        }
+
+        Ok(())
+    }
+
+    /// Marks every ongoing loan of a book as returned today.
+    pub async fn give_back<C: ConnectionTrait>(db: &C, book_id: i32) -> Result<(), LoanError> {
+        Entity::update_many()
+            .col_expr(
+                Column::ReturnDate,
+                Expr::value(chrono::Local::now().date_naive()),
+            )
+            .filter(Column::BookId.eq(book_id))
+            .filter(Column::ReturnDate.is_null())
+            .exec(db)
+            .await
+            .context(DBSnafu)?;
+
+        Ok(())
+    }
+
+    /// Delete the whole loan history of a book
+    pub async fn delete_all_by_book<C: ConnectionTrait>(
+        db: &C,
+        book_id: i32,
+    ) -> Result<DeleteResult, LoanError> {
+        Entity::delete_many()
+            .filter(Column::BookId.eq(book_id))
+            .exec(db)
+            .await
+            .context(DBSnafu)
+    }
+
+    /// Forgets the user on every loan where they are the borrower or the lender,
+    /// the history stays for the other side of the loan
+    pub async fn detach_user<C: ConnectionTrait>(db: &C, user_id: i32) -> Result<(), LoanError> {
+        Entity::update_many()
+            .col_expr(Column::BorrowerId, Expr::value(Option::<i32>::None))
+            .filter(Column::BorrowerId.eq(user_id))
+            .exec(db)
+            .await
+            .context(DBSnafu)?;
+
+        Entity::update_many()
+            .col_expr(Column::LenderId, Expr::value(Option::<i32>::None))
+            .filter(Column::LenderId.eq(user_id))
+            .exec(db)
+            .await
+            .context(DBSnafu)?;
+
+        Ok(())
+    }
+}
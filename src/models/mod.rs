@@ -1,2 +1,13 @@
+pub mod author;
+pub mod backup;
 pub mod book;
+pub mod book_author;
+pub mod book_tag;
+pub mod borrow_request;
+pub mod filter;
+pub mod isbn;
+pub mod loan;
+pub mod session;
+pub mod tag;
 pub mod user;
+pub mod waitlist;
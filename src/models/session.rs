@@ -0,0 +1,121 @@
+use sea_orm::ActiveValue::Set;
+use sea_orm::ConnectionTrait;
+use sea_orm::DeleteResult;
+use sea_orm::entity::prelude::*;
+use snafu::ResultExt;
+use snafu::prelude::*;
+
+use crate::auth;
+use crate::models::user;
+use crate::state::AppState;
+
+#[sea_orm::model]
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "session")]
+pub struct Model {
+    /// Random token stored in the session cookie
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub id: String,
+    pub user_id: i32,
+    #[sea_orm(belongs_to, relation_enum = "User", from = "user_id", to = "id")]
+    pub user: HasOne<super::user::Entity>,
+    pub expires_at: DateTime,
+    /// Random token every form of the session sends back, against CSRF
+    pub csrf_token: String,
+}
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {}
+
+#[derive(Debug, Snafu)]
+#[snafu(visibility(pub))]
+pub enum SessionError {
+    /// Db Error from SeaOrm
+    #[snafu(display("Database error"))]
+    DB { source: sea_orm::DbErr },
+}
+
+#[derive(Debug)]
+/// Operator for the login sessions persisted in database
+pub struct SessionOperator {
+    pub state: AppState,
+}
+
+impl SessionOperator {
+    /// Creates a new `SessionOperator` with the given application state.
+    pub fn new(state: AppState) -> Self {
+        Self { state }
+    }
+
+    /// Opens a new session for the user, valid for the configured number of days.
+    ///
+    /// The expired sessions are purged at the same time, so they don't pile up.
+    pub async fn create(&self, user_id: i32) -> Result<Model, SessionError> {
+        self.delete_expired().await?;
+
+        let expires_at = chrono::Local::now().naive_local()
+            + chrono::Duration::days(self.state.config.auth.session_days);
+
+        let session = ActiveModel {
+            id: Set(auth::random_token()),
+            user_id: Set(user_id),
+            expires_at: Set(expires_at),
+            csrf_token: Set(auth::random_token()),
+        };
+
+        session.insert(&self.state.db).await.context(DBSnafu)
+    }
+
+    /// Finds the session and the user logged in with the session token,
+    /// if the session is not expired.
+    pub async fn find_with_user(
+        &self,
+        token: &str,
+    ) -> Result<Option<(Model, user::Model)>, SessionError> {
+        let session = Entity::find_by_id(token)
+            .filter(Column::ExpiresAt.gt(chrono::Local::now().naive_local()))
+            .one(&self.state.db)
+            .await
+            .context(DBSnafu)?;
+
+        let Some(session) = session else {
+            return Ok(None);
+        };
+
+        let user = user::Entity::find_by_id(session.user_id)
+            .one(&self.state.db)
+            .await
+            .context(DBSnafu)?;
+
+        Ok(user.map(|user| (session, user)))
+    }
+
+    /// Closes the session with the given token
+    pub async fn delete(&self, token: &str) -> Result<DeleteResult, SessionError> {
+        Entity::delete_by_id(token)
+            .exec(&self.state.db)
+            .await
+            .context(DBSnafu)
+    }
+
+    /// Removes the sessions which can't be used anymore
+    pub async fn delete_expired(&self) -> Result<DeleteResult, SessionError> {
+        Entity::delete_many()
+            .filter(Column::ExpiresAt.lte(chrono::Local::now().naive_local()))
+            .exec(&self.state.db)
+            .await
+            .context(DBSnafu)
+    }
+
+    /// Closes every session of a user
+    pub async fn delete_all_by_user<C: ConnectionTrait>(
+        db: &C,
+        user_id: i32,
+    ) -> Result<DeleteResult, SessionError> {
+        Entity::delete_many()
+            .filter(Column::UserId.eq(user_id))
+            .exec(db)
+            .await
+            .context(DBSnafu)
+    }
+}
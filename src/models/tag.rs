@@ -0,0 +1,288 @@
+use std::collections::HashMap;
+
+use sea_orm::ActiveValue::Set;
+use sea_orm::ConnectionTrait;
+use sea_orm::DeleteResult;
+use sea_orm::QueryOrder;
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+use snafu::ResultExt;
+use snafu::prelude::*;
+use utoipa::ToSchema;
+
+use crate::models::book_tag;
+use crate::state::AppState;
+
+#[sea_orm::model]
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "tag")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    #[sea_orm(unique)]
+    pub name: String,
+}
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {}
+
+#[derive(Debug, Snafu)]
+#[snafu(visibility(pub))]
+pub enum TagError {
+    /// Db Error from SeaOrm
+    #[snafu(display("Database error"))]
+    DB { source: sea_orm::DbErr },
+    /// When Tag with Id is not found
+    #[snafu(display("Tag with id {id} not found"))]
+    NotFound { id: i32 },
+    /// A tag needs a name
+    #[snafu(display("The name of a tag can't be empty"))]
+    EmptyName,
+    /// Another tag already has this name
+    #[snafu(display("The tag {name} already exists"))]
+    AlreadyExists { name: String },
+}
+
+/// How the tags of the book list filter are combined
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TagMode {
+    /// Books with every tag
+    #[default]
+    All,
+    /// Books with at least one of the tags
+    Any,
+}
+
+impl TagMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TagMode::All => "all",
+            TagMode::Any => "any",
+        }
+    }
+}
+
+/// Splits the comma separated tags typed in a form.
+///
+/// Names are trimmed, empty ones are dropped and the same name is only
+/// kept once, ignoring the case.
+pub fn parse_names(input: &str) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+
+    for name in input
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+    {
+        if !names
+            .iter()
+            .any(|other| other.to_lowercase() == name.to_lowercase())
+        {
+            names.push(name.to_string());
+        }
+    }
+
+    names
+}
+
+#[derive(Debug)]
+/// Operator for the tags and the tags of the books
+pub struct TagOperator {
+    pub state: AppState,
+}
+
+impl TagOperator {
+    /// Creates a new `TagOperator` with the given application state.
+    pub fn new(state: AppState) -> Self {
+        Self { state }
+    }
+
+    /// Every tag, by name
+    pub async fn all(&self) -> Result<Vec<Model>, TagError> {
+        Entity::find()
+            .order_by_asc(Column::Name)
+            .all(&self.state.db)
+            .await
+            .context(DBSnafu)
+    }
+
+    /// Every tag with the number of books it is on, by name
+    pub async fn all_with_count(&self) -> Result<Vec<(Model, usize)>, TagError> {
+        let mut count_by_tag_id: HashMap<i32, usize> = HashMap::new();
+        for book_tag in book_tag::Entity::find()
+            .all(&self.state.db)
+            .await
+            .context(DBSnafu)?
+        {
+            *count_by_tag_id.entry(book_tag.tag_id).or_default() += 1;
+        }
+
+        Ok(self
+            .all()
+            .await?
+            .into_iter()
+            .map(|tag| {
+                let count = count_by_tag_id.get(&tag.id).copied().unwrap_or_default();
+                (tag, count)
+            })
+            .collect())
+    }
+
+    /// Finds a tag by its ID.
+    ///
+    /// # Errors
+    /// Returns `TagError::NotFound` if no tag exists with the given ID.
+    pub async fn find_by_id(&self, id: i32) -> Result<Model, TagError> {
+        Entity::find_by_id(id)
+            .one(&self.state.db)
+            .await
+            .context(DBSnafu)?
+            .context(NotFoundSnafu { id })
+    }
+
+    /// Tags of a book, by name
+    pub async fn find_all_by_book(&self, book_id: i32) -> Result<Vec<Model>, TagError> {
+        Ok(self
+            .find_all_by_books(&[book_id])
+            .await?
+            .remove(&book_id)
+            .unwrap_or_default())
+    }
+
+    /// Tags of each of the given books, by name
+    pub async fn find_all_by_books(
+        &self,
+        book_ids: &[i32],
+    ) -> Result<HashMap<i32, Vec<Model>>, TagError> {
+        let links = book_tag::Entity::find()
+            .filter(book_tag::Column::BookId.is_in(book_ids.iter().copied()))
+            .all(&self.state.db)
+            .await
+            .context(DBSnafu)?;
+
+        let tags_by_id: HashMap<i32, Model> = Entity::find()
+            .filter(Column::Id.is_in(links.iter().map(|link| link.tag_id)))
+            .all(&self.state.db)
+            .await
+            .context(DBSnafu)?
+            .into_iter()
+            .map(|tag| (tag.id, tag))
+            .collect();
+
+        let mut tags_by_book_id: HashMap<i32, Vec<Model>> = HashMap::new();
+        for link in links {
+            if let Some(tag) = tags_by_id.get(&link.tag_id) {
+                tags_by_book_id
+                    .entry(link.book_id)
+                    .or_default()
+                    .push(tag.clone());
+            }
+        }
+        for tags in tags_by_book_id.values_mut() {
+            tags.sort_by_key(|tag| tag.name.to_lowercase());
+        }
+
+        Ok(tags_by_book_id)
+    }
+
+    /// Replaces the tags of a book, creating the tags which don't exist yet.
+    ///
+    /// Takes the connection so it can be part of a transaction.
+    pub async fn set_book_tags<C: ConnectionTrait>(
+        db: &C,
+        book_id: i32,
+        names: &[String],
+    ) -> Result<(), TagError> {
+        book_tag::Entity::delete_many()
+            .filter(book_tag::Column::BookId.eq(book_id))
+            .exec(db)
+            .await
+            .context(DBSnafu)?;
+
+        if names.is_empty() {
+            return Ok(());
+        }
+
+        let mut tag_by_name: HashMap<String, Model> = Entity::find()
+            .all(db)
+            .await
+            .context(DBSnafu)?
+            .into_iter()
+            .map(|tag| (tag.name.to_lowercase(), tag))
+            .collect();
+
+        for name in names {
+            let tag = match tag_by_name.get(&name.to_lowercase()) {
+                Some(tag) => tag.clone(),
+                None => {
+                    let tag = ActiveModel {
+                        name: Set(name.clone()),
+                        ..Default::default()
+                    }
+                    .insert(db)
+                    .await
+                    .context(DBSnafu)?;
+                    tag_by_name.insert(name.to_lowercase(), tag.clone());
+                    tag
+                }
+            };
+
+            book_tag::ActiveModel {
+                book_id: Set(book_id),
+                tag_id: Set(tag.id),
+            }
+            .insert(db)
+            .await
+            .context(DBSnafu)?;
+        }
+
+        Ok(())
+    }
+
+    /// Renames a tag, it is renamed on every book
+    ///
+    /// # Errors
+    /// Returns `TagError::AlreadyExists` if another tag has this name,
+    /// ignoring the case.
+    pub async fn rename(&self, id: i32, name: &str) -> Result<Model, TagError> {
+        let name = name.trim();
+        ensure!(!name.is_empty(), EmptyNameSnafu);
+
+        let tag = self.find_by_id(id).await?;
+        let taken =
+            self.all().await?.into_iter().any(|other| {
+                other.id != tag.id && other.name.to_lowercase() == name.to_lowercase()
+            });
+        ensure!(!taken, AlreadyExistsSnafu { name });
+
+        let mut tag: ActiveModel = tag.into();
+        tag.name = Set(name.to_string());
+        tag.update(&self.state.db).await.context(DBSnafu)
+    }
+
+    /// Removes a tag from every book, then deletes it
+    pub async fn delete(&self, id: i32) -> Result<DeleteResult, TagError> {
+        let tag = self.find_by_id(id).await?;
+
+        book_tag::Entity::delete_many()
+            .filter(book_tag::Column::TagId.eq(tag.id))
+            .exec(&self.state.db)
+            .await
+            .context(DBSnafu)?;
+
+        tag.delete(&self.state.db).await.context(DBSnafu)
+    }
+
+    /// Removes every tag of a book, before the book is deleted
+    pub async fn delete_all_by_book<C: ConnectionTrait>(
+        db: &C,
+        book_id: i32,
+    ) -> Result<DeleteResult, TagError> {
+        book_tag::Entity::delete_many()
+            .filter(book_tag::Column::BookId.eq(book_id))
+            .exec(db)
+            .await
+            .context(DBSnafu)
+    }
+}
@@ -1,12 +1,22 @@
+use crate::covers::{self, CoverError};
 use crate::models::book;
-use crate::routes::book::BookForm;
+use crate::models::borrow_request;
+use crate::models::filter;
+use crate::models::loan;
+use crate::models::session;
+use crate::models::waitlist;
 use crate::routes::user::IndexQuery;
 use crate::routes::user::UserForm;
 use crate::state::AppState;
 use crate::state::error::UserSnafu;
+use argon2::Argon2;
+use argon2::password_hash::{
+    PasswordHash, PasswordHasher, PasswordVerifier, SaltString, rand_core::OsRng,
+};
 use sea_orm::ActiveValue::Set;
 use sea_orm::Condition;
 use sea_orm::DeleteResult;
+use sea_orm::TransactionTrait;
 use sea_orm::entity::prelude::*;
 use snafu::ResultExt;
 use snafu::prelude::*;
@@ -17,7 +27,11 @@ use snafu::prelude::*;
 pub struct Model {
     #[sea_orm(primary_key)]
     pub id: i32,
+    #[sea_orm(unique)]
     pub name: String,
+    /// Argon2 hash of the password, users without password can't log in
+    pub password_hash: Option<String>,
+    pub role: Role,
     // #[sea_orm(has_many, relation_enum = "Owner", from = "id", to = "owner_id")]
     // pub books: HasMany<super::book::Entity>,
     // #[sea_orm(
@@ -32,6 +46,45 @@ pub struct Model {
 #[async_trait::async_trait]
 impl ActiveModelBehavior for ActiveModel {}
 
+/// What a user is allowed to do
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+    EnumIter,
+    DeriveActiveEnum,
+    serde::Deserialize,
+    serde::Serialize,
+    utoipa::ToSchema,
+)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::None)")]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    /// Manages the users and every book
+    #[sea_orm(string_value = "admin")]
+    Admin,
+    /// Manages their own books
+    #[sea_orm(string_value = "member")]
+    Member,
+}
+
+impl Role {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::Admin => "admin",
+            Role::Member => "member",
+        }
+    }
+}
+
+impl Model {
+    pub fn is_admin(&self) -> bool {
+        self.role == Role::Admin
+    }
+}
+
 #[derive(Debug, Snafu)]
 #[snafu(visibility(pub))]
 pub enum UserError {
@@ -41,8 +94,31 @@ pub enum UserError {
     DB { source: sea_orm::DbErr },
     #[snafu(display("User with id {id} not found"))]
     NotFound { id: i32 },
+    /// Another user already has this name, which is used to log in
+    #[snafu(display("The user {name} already exists"))]
+    AlreadyExists { name: String },
     #[snafu(display("Book error"))]
     Book { source: super::book::BookError },
+    #[snafu(display("Loan error"))]
+    Loan { source: super::loan::LoanError },
+    #[snafu(display("Borrow request error"))]
+    BorrowRequest {
+        source: super::borrow_request::BorrowRequestError,
+    },
+    #[snafu(display("Waitlist error"))]
+    Waitlist {
+        source: super::waitlist::WaitlistError,
+    },
+    #[snafu(display("Session error"))]
+    Session {
+        source: super::session::SessionError,
+    },
+    #[snafu(display("Cover error"))]
+    Cover { source: CoverError },
+    #[snafu(display("Failed to hash the password"))]
+    PasswordHash {
+        source: argon2::password_hash::Error,
+    },
 }
 
 #[derive(Debug)]
@@ -62,7 +138,7 @@ impl UserOperator {
     pub async fn all_filtered(&self, query: IndexQuery) -> Result<Vec<Model>, UserError> {
         let mut conditions = Condition::all();
         if let Some(name) = query.name {
-            conditions = conditions.add(Column::Name.contains(name))
+            conditions = conditions.add(filter::contains_ignore_case(Column::Name, &name))
         }
 
         Entity::find()
@@ -72,6 +148,42 @@ impl UserOperator {
             .context(DBSnafu)
     }
 
+    /// Finds the user with this name and password, names are unique.
+    ///
+    /// Returns None when the name is unknown, the user has no password or the password is wrong.
+    pub async fn authenticate(
+        &self,
+        name: &str,
+        password: &str,
+    ) -> Result<Option<Model>, UserError> {
+        let user: Option<Model> = Entity::find()
+            .filter(Column::Name.eq(name))
+            .one(&self.state.db)
+            .await
+            .context(DBSnafu)?;
+
+        Ok(user.filter(|user| {
+            user.password_hash.as_deref().is_some_and(|password_hash| {
+                PasswordHash::new(password_hash).is_ok_and(|password_hash| {
+                    Argon2::default()
+                        .verify_password(password.as_bytes(), &password_hash)
+                        .is_ok()
+                })
+            })
+        }))
+    }
+
+    /// Whether at least one user can log in
+    pub async fn any_with_password(&self) -> Result<bool, UserError> {
+        let count = Entity::find()
+            .filter(Column::PasswordHash.is_not_null())
+            .count(&self.state.db)
+            .await
+            .context(DBSnafu)?;
+
+        Ok(count > 0)
+    }
+
     pub async fn find_by_id(&self, id: i32) -> Result<Model, UserError> {
         let user: Option<Model> = Entity::find_by_id(id)
             .one(&self.state.db)
@@ -85,22 +197,45 @@ impl UserOperator {
         }
     }
 
+    /// # Errors
+    /// Returns `UserError::AlreadyExists` if another user has this name.
     pub async fn create(&self, form: UserForm) -> Result<Model, UserError> {
+        self.ensure_name_available(&form.name, None).await?;
+
+        let password_hash = match form.password {
+            Some(password) => Some(Self::hash_password(&password)?),
+            None => None,
+        };
+
         let user = ActiveModel {
             name: Set(form.name),
+            password_hash: Set(password_hash),
+            role: Set(form.role.unwrap_or(Role::Member)),
             ..Default::default()
         };
 
         user.insert(&self.state.db).await.context(DBSnafu)
     }
 
+    /// # Errors
+    /// Returns `UserError::AlreadyExists` if another user has this name.
     pub async fn update(&self, id: i32, form: UserForm) -> Result<Model, UserError> {
         let user_by_id = Self::find_by_id(self, id).await.context(UserSnafu);
 
         if let Ok(user) = user_by_id {
+            self.ensure_name_available(&form.name, Some(user.id))
+                .await?;
+
             let mut user: ActiveModel = user.into();
 
             user.name = Set(form.name);
+            // An empty password keeps the current one
+            if let Some(password) = form.password {
+                user.password_hash = Set(Some(Self::hash_password(&password)?));
+            }
+            if let Some(role) = form.role {
+                user.role = Set(role);
+            }
 
             user.update(&self.state.db).await.context(DBSnafu)
         } else {
@@ -108,52 +243,115 @@ impl UserOperator {
         }
     }
 
-    /// Delete user by ID.
-    /// Before deleting the user, you must search for all the books they own in order to delete them beforehand,
-    /// then search for all the books they have borrowed in order to update the current holder to None.
+    /// Delete user by ID, in a single transaction.
+    /// The books they own are deleted with their history, the books they borrowed
+    /// go back to their owners. The other loans stay in the history of the other
+    /// users, without them.
     pub async fn delete(&self, user_id: i32) -> Result<DeleteResult, UserError> {
-        // get all
-        let owner_books = book::BookOperator::new(self.state.clone())
+        let user: Model = self.find_by_id(user_id).await?;
+        let book_operator = book::BookOperator::new(self.state.clone());
+
+        let owned_books = book_operator
             .find_all_by_owner(user_id)
             .await
             .context(BookSnafu)?;
+        let borrowed_books: Vec<book::Model> = book_operator
+            .find_all_by_current_holder(user_id)
+            .await
+            .context(BookSnafu)?
+            .into_iter()
+            .filter(|book| book.owner_id != user_id)
+            .collect();
+        let cover_files: Vec<String> = owned_books
+            .iter()
+            .filter_map(|book| book.cover.clone())
+            .collect();
+
+        let txn = self.state.db.begin().await.context(DBSnafu)?;
 
-        // Delete all book with owner_id = current_user
-        for owner_book in owner_books {
-            book::BookOperator::new(self.state.clone())
-                .delete(owner_book.id)
+        for owned_book in owned_books {
+            book::BookOperator::delete_cascade(&txn, owned_book)
                 .await
                 .context(BookSnafu)?;
         }
 
-        let current_holder_books = book::BookOperator::new(self.state.clone())
-            .find_all_by_current_holder(user_id)
+        for borrowed_book in &borrowed_books {
+            let mut returned: book::ActiveModel = borrowed_book.clone().into();
+            returned.current_holder_id = Set(None);
+            returned.update(&txn).await.context(DBSnafu)?;
+
+            loan::LoanOperator::give_back(&txn, borrowed_book.id)
+                .await
+                .context(LoanSnafu)?;
+        }
+
+        session::SessionOperator::delete_all_by_user(&txn, user_id)
             .await
-            .context(BookSnafu)?;
+            .context(SessionSnafu)?;
+
+        loan::LoanOperator::detach_user(&txn, user_id)
+            .await
+            .context(LoanSnafu)?;
+
+        borrow_request::BorrowRequestOperator::delete_all_by_user(&txn, user_id)
+            .await
+            .context(BorrowRequestSnafu)?;
 
-        // Update all book with current Holder = current user
-        for current_holder_book in current_holder_books {
-            let form = BookForm {
-                title: current_holder_book.title,
-                authors: current_holder_book.authors,
-                owner_id: current_holder_book.owner_id,
-                description: current_holder_book.description,
-                comment: current_holder_book.comment,
-                current_holder_id: None,
-            };
-
-            book::BookOperator::new(self.state.clone())
-                .update(current_holder_book.id, form)
+        waitlist::WaitlistOperator::delete_all_by_user(&txn, user_id)
+            .await
+            .context(WaitlistSnafu)?;
+
+        let result = user.delete(&txn).await.context(DBSnafu)?;
+        txn.commit().await.context(DBSnafu)?;
+
+        // The books they borrowed are offered to their waitlist
+        let waitlist_operator = waitlist::WaitlistOperator::new(self.state.clone());
+        for borrowed_book in &borrowed_books {
+            waitlist_operator
+                .offer_next(borrowed_book.id)
                 .await
-                .context(BookSnafu)?;
+                .context(WaitlistSnafu)?;
         }
 
-        let user: Option<Model> = Entity::find_by_id(user_id)
-            .one(&self.state.db)
+        // Files can't be rolled back, they are removed once the books are gone
+        for cover in cover_files {
+            covers::remove_files(&self.state, &cover)
+                .await
+                .context(CoverSnafu)?;
+        }
+
+        Ok(result)
+    }
+
+    // private
+
+    /// The name is unique in the database, check it first for a readable error
+    async fn ensure_name_available(
+        &self,
+        name: &str,
+        except_id: Option<i32>,
+    ) -> Result<(), UserError> {
+        let mut conditions = Condition::all().add(Column::Name.eq(name));
+        if let Some(id) = except_id {
+            conditions = conditions.add(Column::Id.ne(id));
+        }
+
+        let taken = Entity::find()
+            .filter(conditions)
+            .count(&self.state.db)
             .await
             .context(DBSnafu)?;
-        let user: Model = user.unwrap();
+        ensure!(taken == 0, AlreadyExistsSnafu { name });
+
+        Ok(())
+    }
+
+    fn hash_password(password: &str) -> Result<String, UserError> {
+        let salt = SaltString::generate(&mut OsRng);
 
-        user.delete(&self.state.db).await.context(DBSnafu)
+        Argon2::default()
+            .hash_password(password.as_bytes(), &salt)
+            .map(|password_hash| password_hash.to_string())
+            .context(PasswordHashSnafu)
     }
 }
@@ -0,0 +1,339 @@
+use sea_orm::ActiveValue::Set;
+use sea_orm::ConnectionTrait;
+use sea_orm::DeleteResult;
+use sea_orm::QueryOrder;
+use sea_orm::TransactionTrait;
+use sea_orm::entity::prelude::*;
+use snafu::ResultExt;
+use snafu::prelude::*;
+
+use crate::models::book;
+use crate::state::AppState;
+
+#[sea_orm::model]
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "waitlist_entry")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub book_id: i32,
+    #[sea_orm(belongs_to, relation_enum = "Book", from = "book_id", to = "id")]
+    pub book: HasOne<super::book::Entity>,
+    pub user_id: i32,
+    #[sea_orm(belongs_to, relation_enum = "User", from = "user_id", to = "id")]
+    pub user: HasOne<super::user::Entity>,
+    /// Rank in the queue, the lowest is the next one to get the book
+    pub position: i32,
+    pub created_at: DateTime,
+    /// Set once the book is back and it is the turn of the user
+    pub offered_at: Option<DateTime>,
+    /// Request of the user once offered the book, which stays reserved
+    /// until the owner answers it
+    pub borrow_request_id: Option<i32>,
+}
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {}
+
+#[derive(Debug, Snafu)]
+#[snafu(visibility(pub))]
+pub enum WaitlistError {
+    /// Db Error from SeaOrm
+    #[snafu(display("Database error"))]
+    DB { source: sea_orm::DbErr },
+    /// When the entry with Id is not found
+    #[snafu(display("Waitlist entry with id {id} not found"))]
+    NotFound { id: i32 },
+    /// The book of the waitlist does not exist anymore
+    #[snafu(display("Book with id {id} not found"))]
+    BookNotFound { id: i32 },
+    #[snafu(display("You can't wait for your own book"))]
+    OwnBook,
+    #[snafu(display("You already hold this book"))]
+    Holder,
+    /// Nobody to wait for, the book can be requested right away
+    #[snafu(display("The book is not lent out"))]
+    NotLentOut,
+    #[snafu(display("You are already on the waitlist of this book"))]
+    AlreadyWaiting,
+    /// Nobody has their turn, the book is lent out or nobody waits for it
+    #[snafu(display("It is nobody's turn to borrow the book"))]
+    NotOffered,
+    /// The user whose turn it is already asked for the book
+    #[snafu(display("The book is kept for a borrow request, answer it instead"))]
+    Reserved,
+}
+
+/// Direction to move an entry of the waitlist
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Move {
+    Up,
+    Down,
+}
+
+#[derive(Debug)]
+/// Operator for the FIFO queues of users waiting for a lent out book
+pub struct WaitlistOperator {
+    pub state: AppState,
+}
+
+impl WaitlistOperator {
+    /// Creates a new `WaitlistOperator` with the given application state.
+    pub fn new(state: AppState) -> Self {
+        Self { state }
+    }
+
+    /// Finds a waitlist entry by its ID.
+    ///
+    /// # Errors
+    /// Returns `WaitlistError::NotFound` if no entry exists with the given ID.
+    pub async fn find_by_id(&self, id: i32) -> Result<Model, WaitlistError> {
+        Entity::find_by_id(id)
+            .one(&self.state.db)
+            .await
+            .context(DBSnafu)?
+            .context(NotFoundSnafu { id })
+    }
+
+    /// The queue of the book, the next one first
+    pub async fn find_all_by_book(&self, book_id: i32) -> Result<Vec<Model>, WaitlistError> {
+        Entity::find()
+            .filter(Column::BookId.eq(book_id))
+            .order_by_asc(Column::Position)
+            .order_by_asc(Column::Id)
+            .all(&self.state.db)
+            .await
+            .context(DBSnafu)
+    }
+
+    /// The user whose turn it is, while the book waits for them
+    pub async fn find_offered(&self, book: &book::Model) -> Result<Option<Model>, WaitlistError> {
+        Self::find_offered_in(&self.state.db, book).await
+    }
+
+    /// [`Self::find_offered`] on any connection, for example inside a transaction
+    pub async fn find_offered_in<C: ConnectionTrait>(
+        db: &C,
+        book: &book::Model,
+    ) -> Result<Option<Model>, WaitlistError> {
+        if !book.is_available() {
+            return Ok(None);
+        }
+
+        Entity::find()
+            .filter(Column::BookId.eq(book.id))
+            .filter(Column::OfferedAt.is_not_null())
+            .one(db)
+            .await
+            .context(DBSnafu)
+    }
+
+    /// Puts the user at the end of the queue of the book.
+    ///
+    /// # Errors
+    /// Fails when the user owns or holds the book, when they already wait for it,
+    /// or when the book can be requested right away.
+    pub async fn join(&self, book: &book::Model, user_id: i32) -> Result<Model, WaitlistError> {
+        ensure!(book.owner_id != user_id, OwnBookSnafu);
+        ensure!(book.current_holder_id != Some(user_id), HolderSnafu);
+
+        let entries = self.find_all_by_book(book.id).await?;
+        ensure!(!book.is_available() || !entries.is_empty(), NotLentOutSnafu);
+        ensure!(
+            !entries.iter().any(|entry| entry.user_id == user_id),
+            AlreadyWaitingSnafu
+        );
+
+        ActiveModel {
+            book_id: Set(book.id),
+            user_id: Set(user_id),
+            position: Set(entries.last().map_or(0, |entry| entry.position + 1)),
+            created_at: Set(chrono::Utc::now().naive_utc()),
+            offered_at: Set(None),
+            borrow_request_id: Set(None),
+            ..Default::default()
+        }
+        .insert(&self.state.db)
+        .await
+        .context(DBSnafu)
+    }
+
+    /// The user leaves the queue, the book is offered to the next one if it was their turn
+    pub async fn leave(&self, book_id: i32, user_id: i32) -> Result<(), WaitlistError> {
+        Self::remove(&self.state.db, book_id, user_id).await?;
+        self.offer_next(book_id).await?;
+
+        Ok(())
+    }
+
+    /// Takes the user out of the queue, without offering the book to anyone
+    pub async fn remove<C: ConnectionTrait>(
+        db: &C,
+        book_id: i32,
+        user_id: i32,
+    ) -> Result<DeleteResult, WaitlistError> {
+        Entity::delete_many()
+            .filter(Column::BookId.eq(book_id))
+            .filter(Column::UserId.eq(user_id))
+            .exec(db)
+            .await
+            .context(DBSnafu)
+    }
+
+    /// Keeps the book offered by the entry for the request of its user
+    pub async fn reserve<C: ConnectionTrait>(
+        db: &C,
+        entry: Model,
+        request_id: i32,
+    ) -> Result<Model, WaitlistError> {
+        let mut entry: ActiveModel = entry.into();
+        entry.borrow_request_id = Set(Some(request_id));
+        entry.update(db).await.context(DBSnafu)
+    }
+
+    /// Ends the turn reserved for the request, without offering the book to anyone
+    pub async fn remove_by_request<C: ConnectionTrait>(
+        db: &C,
+        request_id: i32,
+    ) -> Result<DeleteResult, WaitlistError> {
+        Entity::delete_many()
+            .filter(Column::BorrowRequestId.eq(request_id))
+            .exec(db)
+            .await
+            .context(DBSnafu)
+    }
+
+    /// Offers the book to the first user of the queue once it is back,
+    /// unless it is already somebody's turn
+    pub async fn offer_next(&self, book_id: i32) -> Result<Option<Model>, WaitlistError> {
+        let book = book::Entity::find_by_id(book_id)
+            .one(&self.state.db)
+            .await
+            .context(DBSnafu)?
+            .context(BookNotFoundSnafu { id: book_id })?;
+        if !book.is_available() || self.find_offered(&book).await?.is_some() {
+            return Ok(None);
+        }
+
+        let Some(next) = self.find_all_by_book(book_id).await?.into_iter().next() else {
+            return Ok(None);
+        };
+
+        let mut next: ActiveModel = next.into();
+        next.offered_at = Set(Some(chrono::Utc::now().naive_utc()));
+        next.update(&self.state.db).await.map(Some).context(DBSnafu)
+    }
+
+    /// Passes the turn to the next user when the offered one didn't ask for
+    /// the book within `waitlist.offer_days`
+    pub async fn expire_offer(&self, book: &book::Model) -> Result<(), WaitlistError> {
+        let Some(offered) = self.find_offered(book).await? else {
+            return Ok(());
+        };
+
+        let offer_days = chrono::Duration::days(self.state.config.waitlist.offer_days);
+        let expired = offered
+            .offered_at
+            .is_some_and(|offered_at| offered_at + offer_days <= chrono::Utc::now().naive_utc());
+        if offered.borrow_request_id.is_none() && expired {
+            self.pass_turn(offered).await?;
+        }
+
+        Ok(())
+    }
+
+    /// The owner gives the turn of the offered user to the next one
+    ///
+    /// # Errors
+    /// Fails when it is nobody's turn, or when the offered user already asked
+    /// for the book: the request has to be answered instead.
+    pub async fn skip(&self, book: &book::Model) -> Result<(), WaitlistError> {
+        let offered = self.find_offered(book).await?.context(NotOfferedSnafu)?;
+        ensure!(offered.borrow_request_id.is_none(), ReservedSnafu);
+
+        self.pass_turn(offered).await
+    }
+
+    /// Takes the offered user out of the queue and offers the book to the next one,
+    /// unless they asked for the book meanwhile
+    async fn pass_turn(&self, offered: Model) -> Result<(), WaitlistError> {
+        let removed = Entity::delete_many()
+            .filter(Column::Id.eq(offered.id))
+            .filter(Column::BorrowRequestId.is_null())
+            .exec(&self.state.db)
+            .await
+            .context(DBSnafu)?;
+
+        if removed.rows_affected == 1 {
+            self.offer_next(offered.book_id).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Swaps the entry with the previous or the next one of the queue
+    pub async fn move_entry(&self, id: i32, direction: Move) -> Result<Model, WaitlistError> {
+        let entry = self.find_by_id(id).await?;
+        let entries = self.find_all_by_book(entry.book_id).await?;
+
+        let index = entries
+            .iter()
+            .position(|other| other.id == entry.id)
+            .context(NotFoundSnafu { id })?;
+        let other = match direction {
+            Move::Up => index.checked_sub(1).and_then(|index| entries.get(index)),
+            Move::Down => entries.get(index + 1),
+        };
+        let Some(other) = other.cloned() else {
+            return Ok(entry);
+        };
+
+        // Entries joined at the same time may share a position
+        let (position, other_position) = if entry.position == other.position {
+            match direction {
+                Move::Up => (other.position, other.position + 1),
+                Move::Down => (other.position + 1, other.position),
+            }
+        } else {
+            (other.position, entry.position)
+        };
+
+        let txn = self.state.db.begin().await.context(DBSnafu)?;
+
+        let mut other: ActiveModel = other.into();
+        other.position = Set(other_position);
+        other.update(&txn).await.context(DBSnafu)?;
+
+        let mut entry: ActiveModel = entry.into();
+        entry.position = Set(position);
+        let entry = entry.update(&txn).await.context(DBSnafu)?;
+
+        txn.commit().await.context(DBSnafu)?;
+
+        Ok(entry)
+    }
+
+    /// Empties the queue of a book
+    pub async fn delete_all_by_book<C: ConnectionTrait>(
+        db: &C,
+        book_id: i32,
+    ) -> Result<DeleteResult, WaitlistError> {
+        Entity::delete_many()
+            .filter(Column::BookId.eq(book_id))
+            .exec(db)
+            .await
+            .context(DBSnafu)
+    }
+
+    /// Takes the user out of every queue
+    pub async fn delete_all_by_user<C: ConnectionTrait>(
+        db: &C,
+        user_id: i32,
+    ) -> Result<DeleteResult, WaitlistError> {
+        Entity::delete_many()
+            .filter(Column::UserId.eq(user_id))
+            .exec(db)
+            .await
+            .context(DBSnafu)
+    }
+}
@@ -0,0 +1,238 @@
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+    http::StatusCode,
+};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use snafu::prelude::*;
+use utoipa::ToSchema;
+
+use crate::{
+    auth::{self, CurrentUser, Permission},
+    models::book::{BookOperator, Model as BookModel},
+    routes::{
+        api::{ApiError, AuthSnafu, BookSnafu, ErrorBody},
+        book::{BookForm, IndexQuery},
+    },
+    state::AppState,
+};
+
+/// Book as returned by the API
+#[derive(Serialize, Debug, ToSchema)]
+pub struct BookResponse {
+    pub id: i32,
+    pub title: String,
+    pub authors: String,
+    pub isbn: Option<String>,
+    pub description: Option<String>,
+    pub comment: Option<String>,
+    pub owner_id: i32,
+    pub current_holder_id: Option<i32>,
+}
+
+impl From<BookModel> for BookResponse {
+    fn from(book: BookModel) -> Self {
+        BookResponse {
+            id: book.id,
+            title: book.title,
+            authors: book.authors,
+            isbn: book.isbn,
+            description: book.description,
+            comment: book.comment,
+            owner_id: book.owner_id,
+            current_holder_id: book.current_holder_id,
+        }
+    }
+}
+
+/// Page of books as returned by the API
+#[derive(Serialize, Debug, ToSchema)]
+pub struct BooksPageResponse {
+    pub books: Vec<BookResponse>,
+    pub current_page: u64,
+    pub total_page: u64,
+}
+
+/// Body to create or update a book.
+///
+/// Same fields as the HTML `BookForm`, but with JSON types instead of strings.
+#[derive(Deserialize, Debug, ToSchema)]
+pub struct BookInput {
+    pub title: String,
+    pub authors: String,
+    pub isbn: Option<String>,
+    pub owner_id: i32,
+    pub description: Option<String>,
+    pub comment: Option<String>,
+    pub current_holder_id: Option<i32>,
+    pub loan_notes: Option<String>,
+    pub due_date: Option<NaiveDate>,
+    /// Replaces the tags of the book, they are kept when missing
+    pub tags: Option<Vec<String>>,
+    /// Replaces the translators of the book, they are kept when missing
+    pub translators: Option<Vec<String>>,
+    /// Replaces the illustrators of the book, they are kept when missing
+    pub illustrators: Option<Vec<String>>,
+    /// Replaces the editors of the book, they are kept when missing
+    pub editors: Option<Vec<String>>,
+}
+
+impl From<BookInput> for BookForm {
+    fn from(input: BookInput) -> Self {
+        BookForm {
+            title: input.title,
+            authors: input.authors,
+            isbn: input.isbn,
+            owner_id: input.owner_id,
+            description: input.description,
+            comment: input.comment,
+            current_holder_id: input.current_holder_id,
+            loan_notes: input.loan_notes,
+            due_date: input.due_date,
+            tags: input.tags.map(|tags| tags.join(",")),
+            translators: input.translators.map(|names| names.join(",")),
+            illustrators: input.illustrators.map(|names| names.join(",")),
+            editors: input.editors.map(|names| names.join(",")),
+            cover_url: None,
+            remove_cover: None,
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/books",
+    tag = "books",
+    params(IndexQuery),
+    responses((status = 200, description = "Page of books matching the filters", body = BooksPageResponse))
+)]
+pub async fn index(
+    State(state): State<AppState>,
+    CurrentUser(current_user): CurrentUser,
+    Query(query): Query<IndexQuery>,
+) -> Result<Json<BooksPageResponse>, ApiError> {
+    let page: u64 = query.page.map(|p| p.max(1) as u64).unwrap_or(1);
+
+    let books_paginate = BookOperator::new(state)
+        .all_paginate(page, Some(query), current_user.map(|user| user.id))
+        .await
+        .context(BookSnafu)?;
+
+    Ok(Json(BooksPageResponse {
+        books: books_paginate
+            .books
+            .into_iter()
+            .map(BookResponse::from)
+            .collect(),
+        current_page: books_paginate.current_page,
+        total_page: books_paginate.total_page,
+    }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/books/{id}",
+    tag = "books",
+    params(("id" = i32, Path, description = "Book id")),
+    responses(
+        (status = 200, description = "The book", body = BookResponse),
+        (status = 404, description = "Book not found", body = ErrorBody)
+    )
+)]
+pub async fn show(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+) -> Result<Json<BookResponse>, ApiError> {
+    let book = BookOperator::new(state)
+        .find_by_id(id)
+        .await
+        .context(BookSnafu)?;
+
+    Ok(Json(book.into()))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/books",
+    tag = "books",
+    request_body = BookInput,
+    responses(
+        (status = 201, description = "The created book", body = BookResponse),
+        (status = 403, description = "Not allowed to add a book for this owner", body = ErrorBody),
+        (status = 422, description = "Invalid ISBN", body = ErrorBody)
+    )
+)]
+pub async fn create(
+    State(state): State<AppState>,
+    CurrentUser(current_user): CurrentUser,
+    Json(input): Json<BookInput>,
+) -> Result<(StatusCode, Json<BookResponse>), ApiError> {
+    auth::authorize(&current_user, Permission::CreateBookFor(input.owner_id)).context(AuthSnafu)?;
+
+    let book = BookOperator::new(state)
+        .create(input.into())
+        .await
+        .context(BookSnafu)?;
+
+    Ok((StatusCode::CREATED, Json(book.into())))
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/v1/books/{id}",
+    tag = "books",
+    params(("id" = i32, Path, description = "Book id")),
+    request_body = BookInput,
+    responses(
+        (status = 200, description = "The updated book", body = BookResponse),
+        (status = 403, description = "Not the owner of the book", body = ErrorBody),
+        (status = 404, description = "Book not found", body = ErrorBody),
+        (status = 422, description = "Invalid ISBN", body = ErrorBody)
+    )
+)]
+pub async fn update(
+    State(state): State<AppState>,
+    CurrentUser(current_user): CurrentUser,
+    Path(id): Path<i32>,
+    Json(input): Json<BookInput>,
+) -> Result<Json<BookResponse>, ApiError> {
+    let book_operator = BookOperator::new(state.clone());
+
+    let book = book_operator.find_by_id(id).await.context(BookSnafu)?;
+    auth::authorize(&current_user, Permission::EditBook(&book)).context(AuthSnafu)?;
+    auth::authorize(&current_user, Permission::CreateBookFor(input.owner_id)).context(AuthSnafu)?;
+
+    let book = book_operator
+        .update(id, input.into())
+        .await
+        .context(BookSnafu)?;
+
+    Ok(Json(book.into()))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/books/{id}",
+    tag = "books",
+    params(("id" = i32, Path, description = "Book id")),
+    responses(
+        (status = 204, description = "The book is deleted"),
+        (status = 403, description = "Not the owner of the book", body = ErrorBody),
+        (status = 404, description = "Book not found", body = ErrorBody)
+    )
+)]
+pub async fn delete(
+    State(state): State<AppState>,
+    CurrentUser(current_user): CurrentUser,
+    Path(id): Path<i32>,
+) -> Result<StatusCode, ApiError> {
+    let book_operator = BookOperator::new(state.clone());
+
+    let book = book_operator.find_by_id(id).await.context(BookSnafu)?;
+    auth::authorize(&current_user, Permission::EditBook(&book)).context(AuthSnafu)?;
+
+    book_operator.delete(id).await.context(BookSnafu)?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,128 @@
+use axum::{
+    Json, Router,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::get,
+};
+use log::error;
+use serde::Serialize;
+use snafu::prelude::*;
+use utoipa::{OpenApi, ToSchema};
+
+use crate::{
+    auth::AuthError,
+    models::{book::BookError, user::UserError},
+    state::AppState,
+};
+
+pub mod book;
+pub mod user;
+
+/// OpenAPI document of the JSON API, generated from the handlers and their types
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "BookForge API"),
+    paths(
+        book::index,
+        book::show,
+        book::create,
+        book::update,
+        book::delete,
+        user::index,
+        user::show,
+        user::create,
+        user::update,
+        user::delete,
+    ),
+    tags(
+        (name = "books", description = "Books of the library"),
+        (name = "users", description = "Users owning and borrowing books")
+    )
+)]
+pub struct ApiDoc;
+
+/// Router of the JSON API, nested under `/api/v1`
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/openapi.json", get(openapi))
+        .route("/books", get(book::index).post(book::create))
+        .route(
+            "/books/{id}",
+            get(book::show).put(book::update).delete(book::delete),
+        )
+        .route("/users", get(user::index).post(user::create))
+        .route(
+            "/users/{id}",
+            get(user::show).put(user::update).delete(user::delete),
+        )
+}
+
+#[derive(Snafu, Debug)]
+#[snafu(visibility(pub))]
+pub enum ApiError {
+    #[snafu(display("Book Model Error"))]
+    Book { source: BookError },
+    #[snafu(display("User Model Error"))]
+    User { source: UserError },
+    #[snafu(display("Authorization Error"))]
+    Auth { source: AuthError },
+}
+
+/// Body of every error returned by the API
+#[derive(Serialize, Debug, ToSchema)]
+pub struct ErrorBody {
+    pub status: u16,
+    pub error: String,
+}
+
+impl ApiError {
+    fn status(&self) -> StatusCode {
+        match self {
+            ApiError::Book {
+                source: BookError::NotFound { .. },
+            }
+            | ApiError::User {
+                source: UserError::NotFound { .. },
+            } => StatusCode::NOT_FOUND,
+            ApiError::Book {
+                source: BookError::Isbn { .. },
+            }
+            | ApiError::User {
+                source: UserError::AlreadyExists { .. },
+            } => StatusCode::UNPROCESSABLE_ENTITY,
+            ApiError::Auth {
+                source: AuthError::Forbidden,
+            } => StatusCode::FORBIDDEN,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn message(&self) -> String {
+        match self {
+            ApiError::Book { source } => source.to_string(),
+            ApiError::User { source } => source.to_string(),
+            ApiError::Auth { source } => source.to_string(),
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let status = self.status();
+        if status.is_server_error() {
+            error!("{:?}", self);
+        }
+
+        let body = ErrorBody {
+            status: status.as_u16(),
+            error: self.message(),
+        };
+
+        (status, Json(body)).into_response()
+    }
+}
+
+/// Serves the OpenAPI document
+pub async fn openapi() -> Json<utoipa::openapi::OpenApi> {
+    Json(ApiDoc::openapi())
+}
@@ -0,0 +1,181 @@
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+    http::StatusCode,
+};
+use serde::{Deserialize, Serialize};
+use snafu::prelude::*;
+use utoipa::ToSchema;
+
+use crate::{
+    auth::{self, CurrentUser, Permission},
+    models::user::{Model as UserModel, Role, UserOperator},
+    routes::{
+        api::{ApiError, AuthSnafu, ErrorBody, UserSnafu},
+        user::{IndexQuery, UserForm},
+    },
+    state::AppState,
+};
+
+/// User as returned by the API
+#[derive(Serialize, Debug, ToSchema)]
+pub struct UserResponse {
+    pub id: i32,
+    pub name: String,
+    pub role: Role,
+}
+
+impl From<UserModel> for UserResponse {
+    fn from(user: UserModel) -> Self {
+        UserResponse {
+            id: user.id,
+            name: user.name,
+            role: user.role,
+        }
+    }
+}
+
+/// Body to create or update a user
+#[derive(Deserialize, Debug, ToSchema)]
+pub struct UserInput {
+    pub name: String,
+    /// New password, a missing password keeps the current one
+    pub password: Option<String>,
+    /// Only admins can set the role
+    pub role: Option<Role>,
+}
+
+impl From<UserInput> for UserForm {
+    fn from(input: UserInput) -> Self {
+        UserForm {
+            name: input.name,
+            password: input.password,
+            role: input.role,
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/users",
+    tag = "users",
+    params(IndexQuery),
+    responses((status = 200, description = "Users matching the filters", body = Vec<UserResponse>))
+)]
+pub async fn index(
+    State(state): State<AppState>,
+    Query(query): Query<IndexQuery>,
+) -> Result<Json<Vec<UserResponse>>, ApiError> {
+    let users = UserOperator::new(state)
+        .all_filtered(query)
+        .await
+        .context(UserSnafu)?;
+
+    Ok(Json(users.into_iter().map(UserResponse::from).collect()))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/users/{id}",
+    tag = "users",
+    params(("id" = i32, Path, description = "User id")),
+    responses(
+        (status = 200, description = "The user", body = UserResponse),
+        (status = 404, description = "User not found", body = ErrorBody)
+    )
+)]
+pub async fn show(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+) -> Result<Json<UserResponse>, ApiError> {
+    let user = UserOperator::new(state)
+        .find_by_id(id)
+        .await
+        .context(UserSnafu)?;
+
+    Ok(Json(user.into()))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/users",
+    tag = "users",
+    request_body = UserInput,
+    responses(
+        (status = 201, description = "The created user", body = UserResponse),
+        (status = 403, description = "Only admins can manage users", body = ErrorBody),
+        (status = 422, description = "Another user has this name", body = ErrorBody)
+    )
+)]
+pub async fn create(
+    State(state): State<AppState>,
+    CurrentUser(current_user): CurrentUser,
+    Json(input): Json<UserInput>,
+) -> Result<(StatusCode, Json<UserResponse>), ApiError> {
+    auth::authorize(&current_user, Permission::ManageUsers).context(AuthSnafu)?;
+
+    let user = UserOperator::new(state)
+        .create(input.into())
+        .await
+        .context(UserSnafu)?;
+
+    Ok((StatusCode::CREATED, Json(user.into())))
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/v1/users/{id}",
+    tag = "users",
+    params(("id" = i32, Path, description = "User id")),
+    request_body = UserInput,
+    responses(
+        (status = 200, description = "The updated user", body = UserResponse),
+        (status = 403, description = "Not allowed to edit this user", body = ErrorBody),
+        (status = 404, description = "User not found", body = ErrorBody),
+        (status = 422, description = "Another user has this name", body = ErrorBody)
+    )
+)]
+pub async fn update(
+    State(state): State<AppState>,
+    CurrentUser(current_user): CurrentUser,
+    Path(id): Path<i32>,
+    Json(input): Json<UserInput>,
+) -> Result<Json<UserResponse>, ApiError> {
+    auth::authorize(&current_user, Permission::EditUser(id)).context(AuthSnafu)?;
+    if input.role.is_some() {
+        auth::authorize(&current_user, Permission::ManageUsers).context(AuthSnafu)?;
+    }
+
+    let user = UserOperator::new(state)
+        .update(id, input.into())
+        .await
+        .context(UserSnafu)?;
+
+    Ok(Json(user.into()))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/users/{id}",
+    tag = "users",
+    params(("id" = i32, Path, description = "User id")),
+    responses(
+        (status = 204, description = "The user and their books are deleted"),
+        (status = 403, description = "Only admins can manage users", body = ErrorBody),
+        (status = 404, description = "User not found", body = ErrorBody)
+    )
+)]
+pub async fn delete(
+    State(state): State<AppState>,
+    CurrentUser(current_user): CurrentUser,
+    Path(id): Path<i32>,
+) -> Result<StatusCode, ApiError> {
+    auth::authorize(&current_user, Permission::ManageUsers).context(AuthSnafu)?;
+
+    UserOperator::new(state)
+        .delete(id)
+        .await
+        .context(UserSnafu)?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
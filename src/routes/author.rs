@@ -0,0 +1,109 @@
+use askama::Template;
+use askama_web::WebTemplate;
+use axum::{
+    Form,
+    extract::{Path, State},
+    response::Redirect,
+};
+use serde::Deserialize;
+use snafu::prelude::*;
+
+use crate::{
+    auth::{self, CurrentUser, Permission},
+    models::author::{self, AuthorOperator, AuthorRole},
+    models::book::Model as BookModel,
+    routes::router::Router,
+    state::{
+        AppState,
+        error::{AppStateError, AuthSnafu, AuthorSnafu},
+    },
+};
+
+/// An author with the number of books they are credited on
+pub struct AuthorWithBookNumber {
+    pub author: author::Model,
+    pub book_number: usize,
+}
+
+#[derive(Template, WebTemplate)]
+#[template(path = "authors/index.html")]
+struct AuthorsIndexTemplate {
+    authors: Vec<AuthorWithBookNumber>,
+    router: Router,
+}
+
+pub async fn index(
+    State(state): State<AppState>,
+    router: Router,
+) -> Result<impl axum::response::IntoResponse, AppStateError> {
+    let authors = AuthorOperator::new(state)
+        .all_with_count()
+        .await
+        .context(AuthorSnafu)?
+        .into_iter()
+        .map(|(author, book_number)| AuthorWithBookNumber {
+            author,
+            book_number,
+        })
+        .collect();
+
+    Ok(AuthorsIndexTemplate { authors, router })
+}
+
+#[derive(Template, WebTemplate)]
+#[template(path = "authors/show.html")]
+struct ShowAuthorTemplate {
+    author: author::Model,
+    /// Books of the author with their role, by title
+    books: Vec<(BookModel, AuthorRole)>,
+    /// Admins can rename the author or merge it into another one
+    can_manage: bool,
+    router: Router,
+}
+
+pub async fn show(
+    State(state): State<AppState>,
+    router: Router,
+    Path(id): Path<i32>,
+) -> Result<impl axum::response::IntoResponse, AppStateError> {
+    let author_operator = AuthorOperator::new(state);
+    let author = author_operator.find_by_id(id).await.context(AuthorSnafu)?;
+    let books = author_operator
+        .find_books(author.id)
+        .await
+        .context(AuthorSnafu)?;
+
+    let can_manage = router
+        .current_user
+        .as_ref()
+        .is_some_and(|user| user.is_admin());
+
+    Ok(ShowAuthorTemplate {
+        author,
+        books,
+        can_manage,
+        router,
+    })
+}
+
+#[derive(Deserialize, Debug)]
+pub struct AuthorForm {
+    pub name: String,
+}
+
+/// Rename an author, merging it into the author already having the new name
+pub async fn update(
+    State(state): State<AppState>,
+    CurrentUser(current_user): CurrentUser,
+    Path(id): Path<i32>,
+    Form(form): Form<AuthorForm>,
+) -> Result<impl axum::response::IntoResponse, AppStateError> {
+    auth::authorize(&current_user, Permission::ManageAuthors).context(AuthSnafu)?;
+
+    let author = AuthorOperator::new(state)
+        .rename(id, &form.name)
+        .await
+        .context(AuthorSnafu)?;
+
+    Ok(Redirect::to(&format!("/authors/{}", author.id)))
+}
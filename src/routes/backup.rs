@@ -0,0 +1,99 @@
+use askama::Template;
+use askama_web::WebTemplate;
+use axum::{
+    body::Body,
+    extract::{Multipart, State},
+    http::header,
+    response::{IntoResponse, Redirect, Response},
+};
+use snafu::prelude::*;
+
+use crate::{
+    auth::{self, Permission, SESSION_COOKIE},
+    models::backup::BackupOperator,
+    routes::router::Router,
+    state::{
+        AppState,
+        error::{AppStateError, AuthSnafu, BackupJsonSnafu, BackupSnafu, MultipartSnafu},
+    },
+};
+
+#[derive(Template, WebTemplate)]
+#[template(path = "backup/index.html")]
+struct BackupTemplate {
+    router: Router,
+}
+
+/// Page to download a backup or restore one, for admins only
+pub async fn index(router: Router) -> Result<impl axum::response::IntoResponse, AppStateError> {
+    auth::authorize(&router.current_user, Permission::ManageUsers).context(AuthSnafu)?;
+
+    Ok(BackupTemplate { router })
+}
+
+/// Download the whole library as a JSON archive
+pub async fn download(
+    State(state): State<AppState>,
+    router: Router,
+) -> Result<impl axum::response::IntoResponse, AppStateError> {
+    auth::authorize(&router.current_user, Permission::ManageUsers).context(AuthSnafu)?;
+
+    let backup = BackupOperator::new(state)
+        .export()
+        .await
+        .context(BackupSnafu)?;
+    let json = serde_json::to_vec_pretty(&backup).context(BackupJsonSnafu)?;
+
+    let file_name = format!(
+        "bookforge-backup-{}.json",
+        backup.created_at.format("%Y%m%d-%H%M%S")
+    );
+
+    Ok(Response::builder()
+        .header(header::CONTENT_TYPE, "application/json")
+        .header(
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"{}\"", file_name),
+        )
+        .body(Body::from(json))
+        .unwrap())
+}
+
+/// Replace the whole library with an uploaded archive.
+///
+/// Sessions are not part of the backup, so everybody is logged out.
+pub async fn restore(
+    State(state): State<AppState>,
+    router: Router,
+    mut multipart: Multipart,
+) -> Result<impl axum::response::IntoResponse, AppStateError> {
+    auth::authorize(&router.current_user, Permission::ManageUsers).context(AuthSnafu)?;
+
+    let mut file = vec![];
+    while let Some(field) = multipart.next_field().await.context(MultipartSnafu)? {
+        if field.name() == Some("file") {
+            file = field.bytes().await.context(MultipartSnafu)?.to_vec();
+        }
+    }
+
+    let backup = BackupOperator::parse(&file).context(BackupSnafu)?;
+    let summary = BackupOperator::new(state)
+        .restore(backup)
+        .await
+        .context(BackupSnafu)?;
+
+    log::info!(
+        "Backup restored: {} users, {} books, {} loans",
+        summary.users,
+        summary.books,
+        summary.loans
+    );
+
+    let expired_session = format!("{}=; Path=/; Max-Age=0", SESSION_COOKIE);
+
+    Ok((
+        [(header::SET_COOKIE, expired_session)],
+        Redirect::to("/login"),
+    )
+        .into_response())
+}
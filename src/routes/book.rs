@@ -3,21 +3,34 @@ use std::collections::HashMap;
 use askama::Template;
 use askama_web::WebTemplate;
 use axum::{
-    Form,
     body::Body,
-    extract::{Path, Query, State},
+    extract::{Multipart, Path, Query, State},
+    http::header,
     response::{IntoResponse, Redirect, Response},
 };
+use chrono::NaiveDate;
 use csv::Writer;
-use googlebooks_rs::{GoogleBooks, models::VolumeResponse, queries::VolumeQuery};
 use serde::Deserialize;
 use serde_with::{NoneAsEmptyString, serde_as};
 use snafu::prelude::*;
+use utoipa::{IntoParams, ToSchema};
 
 use crate::{
-    models::book::Model as BookModel,
+    auth::{self, CurrentUser, Permission},
+    covers::{self, CoverSize},
+    metadata::{self, BookMetadata, MetadataQuery},
+    models::author::{AuthorOperator, AuthorRole, BookCredits, Model as AuthorModel},
+    models::book::{Availability, BookSort, Model as BookModel},
+    models::borrow_request::{BorrowRequestOperator, Model as BorrowRequestModel},
+    models::filter::SortDirection,
+    models::isbn,
+    models::loan::{LoanOperator, Model as LoanModel},
+    models::tag::{Model as TagModel, TagMode, TagOperator},
+    models::waitlist::{Model as WaitlistModel, WaitlistOperator},
     routes::router::Router,
-    state::error::{CSVSnafu, GoogleBookSnafu},
+    state::error::{
+        CSVSnafu, CoverSnafu, InvalidFormSnafu, IsbnSnafu, MetadataSnafu, MultipartSnafu,
+    },
 };
 use crate::{models::user::Model as UserModel, state::error::IOSnafu};
 
@@ -25,7 +38,10 @@ use crate::{
     models::{book::BookOperator, user::UserOperator},
     state::{
         AppState,
-        error::{AppStateError, BookSnafu, UserSnafu},
+        error::{
+            AppStateError, AuthSnafu, AuthorSnafu, BookSnafu, BorrowRequestSnafu, LoanSnafu,
+            TagSnafu, UserSnafu, WaitlistSnafu,
+        },
     },
 };
 
@@ -34,21 +50,93 @@ struct BookWithUser {
     pub book: BookModel,
     pub owner: UserModel,
     pub current_holder: Option<UserModel>,
+    pub loan: Option<LoanModel>,
+    /// Highlighted extract matching the full-text search
+    pub snippet: Option<String>,
+}
+
+// Loan with the borrower and the lender inside
+pub struct LoanWithUser {
+    pub loan: LoanModel,
+    /// None once the user is deleted
+    pub borrower: Option<UserModel>,
+    pub lender: Option<UserModel>,
+}
+
+// Entry of the waitlist with the user waiting
+pub struct WaitlistEntryWithUser {
+    pub entry: WaitlistModel,
+    pub user: UserModel,
+}
+
+/// How the book list is shown
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BookView {
+    #[default]
+    Table,
+    /// Covers side by side
+    Grid,
+}
+
+impl BookView {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BookView::Table => "table",
+            BookView::Grid => "grid",
+        }
+    }
 }
 
 /// Query for filter search query
 #[serde_as]
-#[derive(Deserialize, Clone, Debug)]
+#[derive(Deserialize, Clone, Debug, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct IndexQuery {
+    /// Full-text search in the title, authors, description and comment
+    #[serde(default)]
+    #[serde_as(as = "NoneAsEmptyString")]
+    pub q: Option<String>,
     pub title: Option<String>,
     pub page: Option<usize>,
     pub authors: Option<String>,
+    /// Books crediting this author, whatever their role
+    #[serde(default)]
+    #[serde_as(as = "NoneAsEmptyString")]
+    pub author_id: Option<i32>,
+    #[serde(default)]
+    #[serde_as(as = "NoneAsEmptyString")]
+    pub isbn: Option<String>,
     #[serde(default)]
     #[serde_as(as = "NoneAsEmptyString")]
     pub owner_id: Option<i32>,
     #[serde(default)]
     #[serde_as(as = "NoneAsEmptyString")]
     pub current_holder_id: Option<i32>,
+    /// Only keep books whose ongoing loan is past its due date
+    #[serde(default)]
+    #[serde_as(as = "NoneAsEmptyString")]
+    pub overdue: Option<bool>,
+    #[serde(default)]
+    #[serde_as(as = "NoneAsEmptyString")]
+    pub availability: Option<Availability>,
+    /// Comma separated tags
+    #[serde(default)]
+    #[serde_as(as = "NoneAsEmptyString")]
+    pub tags: Option<String>,
+    /// Books with all the tags (default) or any of them
+    #[serde(default)]
+    pub tag_mode: Option<TagMode>,
+    #[serde(default)]
+    pub sort: Option<BookSort>,
+    #[serde(default)]
+    pub direction: Option<SortDirection>,
+    /// Books per page, capped by `pagination.max_per_page`
+    #[serde(default)]
+    #[serde_as(as = "NoneAsEmptyString")]
+    pub per_page: Option<u64>,
+    #[serde(default)]
+    pub view: Option<BookView>,
 }
 
 #[derive(Template, WebTemplate)]
@@ -59,12 +147,65 @@ struct BookIndexTemplate {
     users: Vec<UserModel>,
     current_page: u64,
     total_page: u64,
+    per_page: u64,
+    max_per_page: u64,
+    /// Filters of the query, to change the sort
+    filter_query: String,
+    /// Filters and sort of the query, to change the page
     base_query: String,
+    /// Filters and sort of the query without the view, to change the view
+    view_query: String,
     router: Router,
 }
 
+impl BookIndexTemplate {
+    /// Sort by the column, or reverse the direction if already sorted by it
+    fn sort_path(&self, sort: BookSort) -> String {
+        let direction = match (self.query.sort, self.query.direction) {
+            (Some(current), direction) if current == sort => {
+                direction.unwrap_or(sort.default_direction()).reverse()
+            }
+            _ => sort.default_direction(),
+        };
+
+        format!(
+            "{}?{}sort={}&direction={}",
+            self.router.root_path(),
+            self.filter_query,
+            sort.as_str(),
+            direction.as_str()
+        )
+    }
+
+    /// Font Awesome icon showing how the column is sorted
+    fn sort_icon(&self, sort: BookSort) -> &'static str {
+        if self.query.sort != Some(sort) {
+            return "fa-sort";
+        }
+
+        match self.query.direction.unwrap_or(sort.default_direction()) {
+            SortDirection::Asc => "fa-sort-up",
+            SortDirection::Desc => "fa-sort-down",
+        }
+    }
+
+    fn view(&self) -> BookView {
+        self.query.view.unwrap_or_default()
+    }
+
+    fn view_path(&self, view: BookView) -> String {
+        format!(
+            "{}?{}view={}",
+            self.router.root_path(),
+            self.view_query,
+            view.as_str()
+        )
+    }
+}
+
 pub async fn index(
     State(state): State<AppState>,
+    router: Router,
     Query(query): Query<IndexQuery>,
 ) -> Result<impl axum::response::IntoResponse, AppStateError> {
     let page: u64 = query
@@ -80,7 +221,11 @@ pub async fn index(
 
     // Get all Book filtered with query
     let books_paginate = BookOperator::new(state.clone())
-        .all_paginate(page, Some(query.clone()))
+        .all_paginate(
+            page,
+            Some(query.clone()),
+            router.current_user.as_ref().map(|user| user.id),
+        )
         .await
         .context(BookSnafu)?;
 
@@ -92,7 +237,17 @@ pub async fn index(
         .map(|user| (user.id, user))
         .collect();
 
+    // Mapping between a book_id and its ongoing loan
+    let ongoing_loan_by_book_id: HashMap<i32, LoanModel> = LoanOperator::new(state.clone())
+        .all_ongoing()
+        .await
+        .context(LoanSnafu)?
+        .into_iter()
+        .map(|loan| (loan.book_id, loan))
+        .collect();
+
     // Build object of Book with his relation Owner (User) and current_holder (User)
+    let mut snippets = books_paginate.snippets;
     let result: Vec<BookWithUser> = books_paginate
         .books
         .into_iter()
@@ -101,29 +256,77 @@ pub async fn index(
             let current_holder = book
                 .current_holder_id
                 .and_then(|id| user_by_id.get(&id).cloned());
+            let loan = ongoing_loan_by_book_id.get(&book.id).cloned();
+            let snippet = snippets.remove(&book.id);
 
             Some(BookWithUser {
                 book,
                 owner,
                 current_holder,
+                loan,
+                snippet,
             })
         })
         .collect();
 
     // build original search to be sure to keep
     // search when we change page
-    let mut base_query = String::new();
+    let mut filter_query = String::new();
+    if let Some(q) = &query.q {
+        // Free text, may contain any character
+        let encoded = serde_urlencoded::to_string([("q", q)]).unwrap_or_default();
+        filter_query.push_str(&format!("{}&", encoded));
+    }
     if let Some(title) = &query.title {
-        base_query.push_str(&format!("title={}&", title));
+        let encoded = serde_urlencoded::to_string([("title", title)]).unwrap_or_default();
+        filter_query.push_str(&format!("{}&", encoded));
     }
     if let Some(authors) = &query.authors {
-        base_query.push_str(&format!("authors={}&", authors));
+        let encoded = serde_urlencoded::to_string([("authors", authors)]).unwrap_or_default();
+        filter_query.push_str(&format!("{}&", encoded));
+    }
+    if let Some(author_id) = &query.author_id {
+        filter_query.push_str(&format!("author_id={}&", author_id));
+    }
+    if let Some(isbn) = &query.isbn {
+        let encoded = serde_urlencoded::to_string([("isbn", isbn)]).unwrap_or_default();
+        filter_query.push_str(&format!("{}&", encoded));
     }
     if let Some(owner_id) = &query.owner_id {
-        base_query.push_str(&format!("owner_id={}&", owner_id));
+        filter_query.push_str(&format!("owner_id={}&", owner_id));
     }
     if let Some(current_holder_id) = &query.current_holder_id {
-        base_query.push_str(&format!("current_holder_id={}&", current_holder_id));
+        filter_query.push_str(&format!("current_holder_id={}&", current_holder_id));
+    }
+    if let Some(overdue) = &query.overdue {
+        filter_query.push_str(&format!("overdue={}&", overdue));
+    }
+    if let Some(availability) = &query.availability {
+        filter_query.push_str(&format!("availability={}&", availability.as_str()));
+    }
+    if let Some(tags) = &query.tags {
+        let encoded = serde_urlencoded::to_string([("tags", tags)]).unwrap_or_default();
+        filter_query.push_str(&format!("{}&", encoded));
+    }
+    if let Some(tag_mode) = &query.tag_mode {
+        filter_query.push_str(&format!("tag_mode={}&", tag_mode.as_str()));
+    }
+    if let Some(per_page) = &query.per_page {
+        filter_query.push_str(&format!("per_page={}&", per_page));
+    }
+
+    let mut base_query = filter_query.clone();
+    if let Some(sort) = &query.sort {
+        base_query.push_str(&format!("sort={}&", sort.as_str()));
+    }
+    if let Some(direction) = &query.direction {
+        base_query.push_str(&format!("direction={}&", direction.as_str()));
+    }
+
+    let view_query = base_query.clone();
+    if let Some(view) = &query.view {
+        filter_query.push_str(&format!("view={}&", view.as_str()));
+        base_query.push_str(&format!("view={}&", view.as_str()));
     }
 
     Ok(BookIndexTemplate {
@@ -132,10 +335,12 @@ pub async fn index(
         users,
         current_page: books_paginate.current_page,
         total_page: books_paginate.total_page,
+        per_page: books_paginate.per_page,
+        max_per_page: state.config.pagination.max_per_page,
+        filter_query,
         base_query,
-        router: Router {
-            base_path: state.config.base_path,
-        },
+        view_query,
+        router,
     })
 }
 
@@ -145,12 +350,43 @@ struct ShowBookTemplate {
     book: BookModel,
     owner: UserModel,
     current_holder: Option<UserModel>,
+    loans: Vec<LoanWithUser>,
+    duplicates: Vec<BookModel>,
+    tags: Vec<TagModel>,
+    credits: BookCredits,
+    /// Pending request of the logged in user to borrow the book
+    borrow_request: Option<BorrowRequestModel>,
+    /// The logged in user can ask the owner to lend them the book
+    can_request: bool,
+    /// Number of requests waiting for an answer, only for the owner
+    pending_requests: usize,
+    /// Users waiting for the book, the next one first
+    waitlist: Vec<WaitlistEntryWithUser>,
+    /// Place of the logged in user in the waitlist, from 1
+    waitlist_position: Option<usize>,
+    /// The book is back and it is the turn of the logged in user
+    offered: bool,
+    /// Day the turn of the logged in user ends, when offered the book
+    offer_deadline: Option<chrono::NaiveDate>,
+    can_join_waitlist: bool,
+    /// The owner reorders and clears the waitlist
+    can_manage_waitlist: bool,
     router: Router,
 }
 
+/// Query of the show page
+#[derive(Deserialize, Debug)]
+pub struct ShowQuery {
+    /// Set after a creation when another book has the same ISBN
+    #[serde(default)]
+    pub duplicate_isbn: bool,
+}
+
 pub async fn show(
     State(state): State<AppState>,
+    router: Router,
     Path(id): Path<i32>,
+    Query(query): Query<ShowQuery>,
 ) -> Result<impl axum::response::IntoResponse, AppStateError> {
     let book = BookOperator::new(state.clone())
         .find_by_id(id)
@@ -174,37 +410,302 @@ pub async fn show(
         None
     };
 
+    let users_by_id: HashMap<i32, UserModel> = UserOperator::new(state.clone())
+        .all()
+        .await
+        .context(UserSnafu)?
+        .into_iter()
+        .map(|user| (user.id, user))
+        .collect();
+
+    let loans: Vec<LoanWithUser> = LoanOperator::new(state.clone())
+        .find_all_by_book(book.id)
+        .await
+        .context(LoanSnafu)?
+        .into_iter()
+        .map(|loan| {
+            let borrower = loan
+                .borrower_id
+                .and_then(|id| users_by_id.get(&id).cloned());
+            let lender = loan.lender_id.and_then(|id| users_by_id.get(&id).cloned());
+
+            LoanWithUser {
+                loan,
+                borrower,
+                lender,
+            }
+        })
+        .collect();
+
+    let duplicates: Vec<BookModel> = match &book.isbn {
+        Some(isbn) if query.duplicate_isbn => BookOperator::new(state.clone())
+            .find_all_by_isbn(isbn)
+            .await
+            .context(BookSnafu)?
+            .into_iter()
+            .filter(|other| other.id != book.id)
+            .collect(),
+        _ => vec![],
+    };
+
+    let tags = TagOperator::new(state.clone())
+        .find_all_by_book(book.id)
+        .await
+        .context(TagSnafu)?;
+
+    let credits = AuthorOperator::new(state.clone())
+        .find_credits_by_book(book.id)
+        .await
+        .context(AuthorSnafu)?;
+
+    let waitlist_operator = WaitlistOperator::new(state.clone());
+    waitlist_operator
+        .expire_offer(&book)
+        .await
+        .context(WaitlistSnafu)?;
+    let waitlist: Vec<WaitlistEntryWithUser> = waitlist_operator
+        .find_all_by_book(book.id)
+        .await
+        .context(WaitlistSnafu)?
+        .into_iter()
+        .filter_map(|entry| {
+            let user = users_by_id.get(&entry.user_id).cloned()?;
+
+            Some(WaitlistEntryWithUser { entry, user })
+        })
+        .collect();
+    let offered_entry = waitlist_operator
+        .find_offered(&book)
+        .await
+        .context(WaitlistSnafu)?;
+    let offered_to = offered_entry.as_ref().map(|entry| entry.user_id);
+
+    let request_operator = BorrowRequestOperator::new(state.clone());
+    let (borrow_request, can_request, pending_requests) = match &router.current_user {
+        Some(current_user) if current_user.id == book.owner_id => {
+            let pending_requests = request_operator
+                .find_incoming(current_user.id)
+                .await
+                .context(BorrowRequestSnafu)?
+                .into_iter()
+                .filter(|request| request.book_id == book.id)
+                .count();
+            (None, false, pending_requests)
+        }
+        Some(current_user) => {
+            let borrow_request = request_operator
+                .find_pending(book.id, current_user.id)
+                .await
+                .context(BorrowRequestSnafu)?;
+            // Once the book is back, it is kept for the next one in line
+            let can_request = borrow_request.is_none()
+                && book.is_available()
+                && offered_to.is_none_or(|user_id| user_id == current_user.id);
+            (borrow_request, can_request, 0)
+        }
+        None => (None, false, 0),
+    };
+
+    let current_user_id = router.current_user.as_ref().map(|user| user.id);
+    let waitlist_position = waitlist
+        .iter()
+        .position(|waiting| Some(waiting.entry.user_id) == current_user_id)
+        .map(|index| index + 1);
+    // Once asked, the book is kept for the request
+    let offered = offered_to.is_some() && offered_to == current_user_id && borrow_request.is_none();
+    let offer_deadline = offered_entry
+        .and_then(|entry| entry.offered_at)
+        .filter(|_| offered)
+        .map(|offered_at| {
+            (offered_at + chrono::Duration::days(state.config.waitlist.offer_days)).date()
+        });
+    let can_join_waitlist = current_user_id.is_some_and(|user_id| {
+        user_id != book.owner_id
+            && book.current_holder_id != Some(user_id)
+            && borrow_request.is_none()
+            && waitlist_position.is_none()
+            && (!book.is_available() || !waitlist.is_empty())
+    });
+    let can_manage_waitlist = router
+        .current_user
+        .as_ref()
+        .is_some_and(|user| user.is_admin() || user.id == book.owner_id);
+
     Ok(ShowBookTemplate {
         book,
         owner,
         current_holder,
-        router: Router {
-            base_path: state.config.base_path,
-        },
+        loans,
+        duplicates,
+        tags,
+        credits,
+        borrow_request,
+        can_request,
+        pending_requests,
+        waitlist,
+        waitlist_position,
+        offered,
+        offer_deadline,
+        can_join_waitlist,
+        can_manage_waitlist,
+        router,
     })
 }
 
 /// Form to build a new book or an update
 #[serde_as]
-#[derive(Deserialize)]
+#[derive(Deserialize, Clone, Debug)]
 pub struct BookForm {
     pub title: String,
     pub authors: String,
+    /// ISBN-10 or ISBN-13, with or without hyphens
+    #[serde(default)]
+    #[serde_as(as = "NoneAsEmptyString")]
+    pub isbn: Option<String>,
     pub owner_id: i32,
     pub description: Option<String>,
     pub comment: Option<String>,
     #[serde_as(as = "NoneAsEmptyString")]
     pub current_holder_id: Option<i32>,
+    /// Notes recorded on the loan opened when the current holder changes
+    #[serde(default)]
+    #[serde_as(as = "NoneAsEmptyString")]
+    pub loan_notes: Option<String>,
+    /// Date before which the current holder should give the book back
+    #[serde(default)]
+    #[serde_as(as = "NoneAsEmptyString")]
+    pub due_date: Option<NaiveDate>,
+    /// Comma separated tags, the tags of the book are kept when missing
+    #[serde(default)]
+    pub tags: Option<String>,
+    /// Comma separated translators, they are kept when missing
+    #[serde(default)]
+    pub translators: Option<String>,
+    /// Comma separated illustrators, they are kept when missing
+    #[serde(default)]
+    pub illustrators: Option<String>,
+    /// Comma separated editors, they are kept when missing
+    #[serde(default)]
+    pub editors: Option<String>,
+    /// Cover given by a metadata provider, downloaded when no file is uploaded
+    #[serde(default)]
+    #[serde_as(as = "NoneAsEmptyString")]
+    pub cover_url: Option<String>,
+    /// Remove the current cover
+    #[serde(default)]
+    #[serde_as(as = "NoneAsEmptyString")]
+    pub remove_cover: Option<bool>,
+}
+
+/// Biggest body of the new and edit forms, which can carry a cover
+pub const MAX_FORM_SIZE: usize = covers::MAX_COVER_SIZE + 1024 * 1024;
+
+/// Reads the new or edit form, sent as multipart because of the cover file
+async fn read_book_form(
+    mut multipart: Multipart,
+) -> Result<(BookForm, Option<Vec<u8>>), AppStateError> {
+    let mut fields: Vec<(String, String)> = vec![];
+    let mut cover = None;
+
+    while let Some(field) = multipart.next_field().await.context(MultipartSnafu)? {
+        let name = field.name().unwrap_or_default().to_string();
+
+        if name == "cover" {
+            // The browser sends an empty part when no file is chosen
+            let bytes = field.bytes().await.context(MultipartSnafu)?;
+            if !bytes.is_empty() {
+                cover = Some(bytes.to_vec());
+            }
+        } else {
+            fields.push((name, field.text().await.context(MultipartSnafu)?));
+        }
+    }
+
+    // Same parsing as an urlencoded form, for the empty strings
+    let encoded = serde_urlencoded::to_string(&fields).unwrap_or_default();
+    let form = serde_urlencoded::from_str(&encoded).context(InvalidFormSnafu)?;
+
+    Ok((form, cover))
+}
+
+/// Changes the cover after saving the book: an uploaded file wins over the
+/// removal, then over the cover URL of a metadata provider
+async fn apply_cover(
+    state: &AppState,
+    book: BookModel,
+    upload: Option<covers::Cover>,
+    remove: bool,
+    cover_url: Option<String>,
+) -> Result<BookModel, AppStateError> {
+    if let Some(cover) = upload {
+        return covers::save(state, book, cover).await.context(CoverSnafu);
+    }
+
+    if remove {
+        return covers::delete(state, book).await.context(CoverSnafu);
+    }
+
+    match cover_url {
+        // The book is saved, a provider being down must not lose it
+        Some(url) if book.cover.is_none() => {
+            match covers::download(state, book.clone(), &url).await {
+                Ok(book) => Ok(book),
+                Err(error) => {
+                    log::warn!(
+                        "Failed to download the cover of book {}: {}",
+                        book.id,
+                        error
+                    );
+                    Ok(book)
+                }
+            }
+        }
+        _ => Ok(book),
+    }
 }
 
 pub async fn create(
     State(state): State<AppState>,
-    Form(form): Form<BookForm>,
+    CurrentUser(current_user): CurrentUser,
+    multipart: Multipart,
 ) -> Result<impl axum::response::IntoResponse, AppStateError> {
-    let _ = BookOperator::new(state)
-        .create(form)
-        .await
-        .context(BookSnafu)?;
+    let (form, cover) = read_book_form(multipart).await?;
+
+    auth::authorize(&current_user, Permission::CreateBookFor(form.owner_id)).context(AuthSnafu)?;
+
+    // Decoded before the book is created, a wrong file must not leave a book behind
+    let cover = match cover {
+        Some(bytes) => Some(covers::decode(bytes).await.context(CoverSnafu)?),
+        None => None,
+    };
+
+    let book_operator = BookOperator::new(state.clone());
+
+    let remove_cover = form.remove_cover.unwrap_or(false);
+    let cover_url = form.cover_url.clone();
+    let book = book_operator.create(form).await.context(BookSnafu)?;
+    // The book is created, an error page would make the user send it again
+    let book = match apply_cover(&state, book.clone(), cover, remove_cover, cover_url).await {
+        Ok(book) => book,
+        Err(error) => {
+            log::warn!("Failed to save the cover of book {}: {}", book.id, error);
+            book
+        }
+    };
+
+    // Warn when another book of the library already has the same ISBN
+    if let Some(isbn) = &book.isbn {
+        let same_isbn_books = book_operator
+            .find_all_by_isbn(isbn)
+            .await
+            .context(BookSnafu)?;
+
+        if same_isbn_books.iter().any(|other| other.id != book.id) {
+            return Ok(
+                Redirect::to(&format!("/books/{}?duplicate_isbn=true", book.id)).into_response(),
+            );
+        }
+    }
 
     Ok(Redirect::to("/").into_response())
 }
@@ -213,22 +714,36 @@ pub async fn create(
 #[template(path = "books/new.html")]
 struct NewBookTemplate {
     users: Vec<UserModel>,
+    all_tags: Vec<TagModel>,
+    /// Nobody is credited yet, to share the inputs with the edit form
+    credits: BookCredits,
+    all_authors: Vec<AuthorModel>,
     router: Router,
 }
 
 pub async fn new(
     State(state): State<AppState>,
+    router: Router,
 ) -> Result<impl axum::response::IntoResponse, AppStateError> {
     let users = UserOperator::new(state.clone())
         .all()
         .await
         .context(UserSnafu)?;
+    let all_tags = TagOperator::new(state.clone())
+        .all()
+        .await
+        .context(TagSnafu)?;
+    let all_authors = AuthorOperator::new(state.clone())
+        .all()
+        .await
+        .context(AuthorSnafu)?;
 
     Ok(NewBookTemplate {
         users,
-        router: Router {
-            base_path: state.config.base_path,
-        },
+        all_tags,
+        credits: BookCredits::default(),
+        all_authors,
+        router,
     })
 }
 
@@ -237,11 +752,18 @@ pub async fn new(
 struct EditBookTemplate {
     users: Vec<UserModel>,
     book: BookModel,
+    ongoing_loan: Option<LoanModel>,
+    /// Comma separated tags of the book
+    tags: String,
+    all_tags: Vec<TagModel>,
+    credits: BookCredits,
+    all_authors: Vec<AuthorModel>,
     router: Router,
 }
 
 pub async fn edit(
     State(state): State<AppState>,
+    router: Router,
     Path(id): Path<i32>,
 ) -> Result<impl axum::response::IntoResponse, AppStateError> {
     let users = UserOperator::new(state.clone())
@@ -252,25 +774,120 @@ pub async fn edit(
         .find_by_id(id)
         .await
         .context(BookSnafu)?;
+    auth::authorize(&router.current_user, Permission::EditBook(&book)).context(AuthSnafu)?;
+    let ongoing_loan = LoanOperator::new(state.clone())
+        .find_ongoing_by_book(book.id)
+        .await
+        .context(LoanSnafu)?;
+    let tag_operator = TagOperator::new(state.clone());
+    let tags = tag_operator
+        .find_all_by_book(book.id)
+        .await
+        .context(TagSnafu)?
+        .into_iter()
+        .map(|tag| tag.name)
+        .collect::<Vec<_>>()
+        .join(", ");
+    let all_tags = tag_operator.all().await.context(TagSnafu)?;
+    let author_operator = AuthorOperator::new(state.clone());
+    let credits = author_operator
+        .find_credits_by_book(book.id)
+        .await
+        .context(AuthorSnafu)?;
+    let all_authors = author_operator.all().await.context(AuthorSnafu)?;
 
     Ok(EditBookTemplate {
         users,
         book,
-        router: Router {
-            base_path: state.config.base_path,
-        },
+        ongoing_loan,
+        tags,
+        all_tags,
+        credits,
+        all_authors,
+        router,
     })
 }
 
 pub async fn update(
+    State(state): State<AppState>,
+    CurrentUser(current_user): CurrentUser,
+    Path(id): Path<i32>,
+    multipart: Multipart,
+) -> Result<impl axum::response::IntoResponse, AppStateError> {
+    let (form, cover) = read_book_form(multipart).await?;
+    let cover = match cover {
+        Some(bytes) => Some(covers::decode(bytes).await.context(CoverSnafu)?),
+        None => None,
+    };
+
+    let book_operator = BookOperator::new(state.clone());
+
+    let book = book_operator.find_by_id(id).await.context(BookSnafu)?;
+    auth::authorize(&current_user, Permission::EditBook(&book)).context(AuthSnafu)?;
+    // Giving the book to someone else is like creating a book for them
+    auth::authorize(&current_user, Permission::CreateBookFor(form.owner_id)).context(AuthSnafu)?;
+
+    let remove_cover = form.remove_cover.unwrap_or(false);
+    let cover_url = form.cover_url.clone();
+    let book = book_operator.update(id, form).await.context(BookSnafu)?;
+    let _ = apply_cover(&state, book, cover, remove_cover, cover_url).await?;
+
+    Ok(Redirect::to(&format!("/books/{}", id)).into_response())
+}
+
+/// Original cover of the book
+pub async fn cover(
     State(state): State<AppState>,
     Path(id): Path<i32>,
-    Form(form): Form<BookForm>,
 ) -> Result<impl axum::response::IntoResponse, AppStateError> {
-    let _ = BookOperator::new(state)
-        .update(id, form)
+    serve_cover(&state, id, CoverSize::Original).await
+}
+
+/// Thumbnail of the cover of the book
+pub async fn thumbnail(
+    State(state): State<AppState>,
+    Path(id): Path<i32>,
+) -> Result<impl axum::response::IntoResponse, AppStateError> {
+    serve_cover(&state, id, CoverSize::Thumbnail).await
+}
+
+async fn serve_cover(
+    state: &AppState,
+    id: i32,
+    size: CoverSize,
+) -> Result<Response, AppStateError> {
+    let book = BookOperator::new(state.clone())
+        .find_by_id(id)
         .await
         .context(BookSnafu)?;
+    let (bytes, mime_type) = covers::read(state, &book, size).await.context(CoverSnafu)?;
+
+    // The URL changes with the cover, see `Router::cover_path`
+    Ok((
+        [
+            (header::CONTENT_TYPE, mime_type),
+            (
+                header::CACHE_CONTROL,
+                "private, max-age=31536000, immutable",
+            ),
+        ],
+        bytes,
+    )
+        .into_response())
+}
+
+/// Mark the book as given back to its owner
+pub async fn give_back(
+    State(state): State<AppState>,
+    CurrentUser(current_user): CurrentUser,
+    Path(id): Path<i32>,
+) -> Result<impl axum::response::IntoResponse, AppStateError> {
+    let book_operator = BookOperator::new(state.clone());
+
+    let book = book_operator.find_by_id(id).await.context(BookSnafu)?;
+    auth::authorize(&current_user, Permission::ReturnBook(&book)).context(AuthSnafu)?;
+
+    let _ = book_operator.give_back(id).await.context(BookSnafu)?;
 
     Ok(Redirect::to(&format!("/books/{}", id)).into_response())
 }
@@ -278,54 +895,66 @@ pub async fn update(
 #[derive(Template, WebTemplate)]
 #[template(path = "books/search.html")]
 struct SearchBookTemplate {
-    result: VolumeResponse,
+    results: Vec<BookMetadata>,
     owner_id: i32,
     router: Router,
 }
 
+/// Search on the metadata providers, either by ISBN or by title (and authors)
+#[serde_as]
 #[derive(Deserialize, Debug)]
 pub struct SearchForm {
-    pub title: String,
+    #[serde(default)]
+    #[serde_as(as = "NoneAsEmptyString")]
+    pub title: Option<String>,
     pub owner_id: i32,
+    #[serde(default)]
+    #[serde_as(as = "NoneAsEmptyString")]
     pub authors: Option<String>,
+    #[serde(default)]
+    #[serde_as(as = "NoneAsEmptyString")]
+    pub isbn: Option<String>,
 }
 
 pub async fn search(
     State(state): State<AppState>,
+    router: Router,
     Query(form): Query<SearchForm>,
 ) -> Result<impl axum::response::IntoResponse, AppStateError> {
-    println!("{:#?}", form);
-
-    let api_key = state.config.api_config.google_books_api_key;
-    let client = GoogleBooks::new(Some(api_key.to_string()));
-
-    let query = if let Some(authors) = form.authors {
-        VolumeQuery::title(form.title)
-            .and_author(authors)
-            .max_results(5)
-    } else {
-        VolumeQuery::title(form.title).max_results(5)
+    // The ISBN is enough to find the book, the title and authors are ignored
+    let query = match (form.isbn, form.title) {
+        (Some(isbn), _) => MetadataQuery::Isbn(isbn::normalize(&isbn).context(IsbnSnafu)?),
+        (None, Some(title)) => MetadataQuery::Title {
+            title,
+            authors: form.authors,
+        },
+        (None, None) => return Ok(Redirect::to("/books/new").into_response()),
     };
 
-    let result = client.search(query).await.context(GoogleBookSnafu)?;
+    let providers = metadata::providers(&state.config.metadata, &state.config.api_config);
+    let results = metadata::search(&providers, &query, 5)
+        .await
+        .context(MetadataSnafu)?;
 
     Ok(SearchBookTemplate {
-        result,
+        results,
         owner_id: form.owner_id,
-        router: Router {
-            base_path: state.config.base_path,
-        },
-    })
+        router,
+    }
+    .into_response())
 }
 
 pub async fn delete(
     State(state): State<AppState>,
+    CurrentUser(current_user): CurrentUser,
     Path(id): Path<i32>,
 ) -> Result<impl axum::response::IntoResponse, AppStateError> {
-    let _ = BookOperator::new(state)
-        .delete(id)
-        .await
-        .context(BookSnafu)?;
+    let book_operator = BookOperator::new(state.clone());
+
+    let book = book_operator.find_by_id(id).await.context(BookSnafu)?;
+    auth::authorize(&current_user, Permission::EditBook(&book)).context(AuthSnafu)?;
+
+    let _ = book_operator.delete(id).await.context(BookSnafu)?;
 
     Ok(Redirect::to("/").into_response())
 }
@@ -333,26 +962,62 @@ pub async fn delete(
 /// Download CSV filter (no paginate) of all books
 pub async fn download_csv(
     State(state): State<AppState>,
+    CurrentUser(current_user): CurrentUser,
     Query(query): Query<IndexQuery>,
 ) -> Result<impl axum::response::IntoResponse, AppStateError> {
+    let csv_bytes = books_csv(state, Some(query), current_user.map(|user| user.id)).await?;
+
+    Ok(Response::builder()
+        .header("Content-Type", "text/csv")
+        .body(Body::from(csv_bytes))
+        .unwrap())
+}
+
+/// CSV of the books matching the query, also written by `bookforge book export`
+pub async fn books_csv(
+    state: AppState,
+    query: Option<IndexQuery>,
+    viewer_id: Option<i32>,
+) -> Result<Vec<u8>, AppStateError> {
     let books = BookOperator::new(state.clone())
-        .all_filtered(Some(query))
+        .all_filtered(query, viewer_id)
         .await
         .context(BookSnafu)?;
 
-    let users = UserOperator::new(state).all().await.context(UserSnafu)?;
+    let users = UserOperator::new(state.clone())
+        .all()
+        .await
+        .context(UserSnafu)?;
 
     let users_by_id: HashMap<i32, UserModel> = users.into_iter().map(|u| (u.id, u)).collect();
 
+    let ongoing_loan_by_book_id: HashMap<i32, LoanModel> = LoanOperator::new(state.clone())
+        .all_ongoing()
+        .await
+        .context(LoanSnafu)?
+        .into_iter()
+        .map(|loan| (loan.book_id, loan))
+        .collect();
+
+    let book_ids: Vec<i32> = books.iter().map(|book| book.id).collect();
+    let mut tags_by_book_id = TagOperator::new(state)
+        .find_all_by_books(&book_ids)
+        .await
+        .context(TagSnafu)?;
+
     let mut wtr = Writer::from_writer(vec![]);
     wtr.write_record([
         "ID",
         "Title",
         "Author(s)",
+        "ISBN",
         "Description",
         "Owner",
         "Current Holder",
         "Comment",
+        "Due Date",
+        "Overdue",
+        "Tags",
     ])
     .context(CSVSnafu)?;
 
@@ -373,27 +1038,43 @@ pub async fn download_csv(
             Err(_) => "-".to_string(),
         };
 
+        let loan = ongoing_loan_by_book_id.get(&book.id);
+        let due_date = match loan.and_then(|loan| loan.due_date) {
+            Some(due_date) => due_date.to_string(),
+            None => "-".to_string(),
+        };
+        let overdue = if loan.is_some_and(|loan| loan.is_overdue()) {
+            "yes".to_string()
+        } else {
+            "no".to_string()
+        };
+        let tags = tags_by_book_id
+            .remove(&book.id)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|tag| tag.name)
+            .collect::<Vec<_>>()
+            .join(", ");
+
         wtr.write_record(&[
             book.id.to_string(),
             book.title,
             book.authors,
+            book.isbn.unwrap_or_default(),
             book.description.unwrap_or_default(),
             owner_format,
             current_holder,
             book.comment.unwrap_or_default(),
+            due_date,
+            overdue,
+            tags,
         ])
         .context(CSVSnafu)?;
     }
 
     wtr.flush().context(IOSnafu)?;
 
-    let csv_bytes = wtr.into_inner();
-
-    match csv_bytes {
-        Ok(csv_bytes) => Ok(Response::builder()
-            .header("Content-Type", "text/csv")
-            .body(Body::from(csv_bytes))
-            .unwrap()),
-        Err(_) => Ok(Redirect::to("/").into_response()),
-    }
+    wtr.into_inner()
+        .map_err(|error| error.into_error())
+        .context(IOSnafu)
 }
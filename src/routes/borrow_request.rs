@@ -0,0 +1,223 @@
+use std::collections::HashMap;
+
+use askama::Template;
+use askama_web::WebTemplate;
+use axum::{
+    Form,
+    extract::{Path, State},
+    response::Redirect,
+};
+use chrono::NaiveDate;
+use serde::Deserialize;
+use serde_with::{NoneAsEmptyString, serde_as};
+use snafu::prelude::*;
+
+use crate::{
+    auth::{self, CurrentUser, ForbiddenSnafu, Permission},
+    models::book::{BookOperator, Model as BookModel},
+    models::borrow_request::{self, BorrowRequestOperator, RequestStatus},
+    models::user::{Model as UserModel, UserOperator},
+    routes::router::Router,
+    state::{
+        AppState,
+        error::{AppStateError, AuthSnafu, BookSnafu, BorrowRequestSnafu, UserSnafu},
+    },
+};
+
+/// Borrow request with its book, the requester and the owner of the book
+pub struct RequestWithBook {
+    pub request: borrow_request::Model,
+    pub book: BookModel,
+    pub requester: UserModel,
+    pub owner: UserModel,
+}
+
+#[derive(Template, WebTemplate)]
+#[template(path = "borrow_requests/index.html")]
+struct BorrowRequestsIndexTemplate {
+    /// Pending requests on the books of the logged in user
+    incoming: Vec<RequestWithBook>,
+    /// Requests made by the logged in user, whatever their status
+    outgoing: Vec<RequestWithBook>,
+    router: Router,
+}
+
+/// Inbox of the owner and status of the requests made by the logged in user
+pub async fn index(
+    State(state): State<AppState>,
+    router: Router,
+) -> Result<impl axum::response::IntoResponse, AppStateError> {
+    let (incoming, outgoing) = match &router.current_user {
+        Some(current_user) => {
+            let request_operator = BorrowRequestOperator::new(state.clone());
+            let incoming = request_operator
+                .find_incoming(current_user.id)
+                .await
+                .context(BorrowRequestSnafu)?;
+            let outgoing = request_operator
+                .find_outgoing(current_user.id)
+                .await
+                .context(BorrowRequestSnafu)?;
+
+            (
+                with_books(&state, incoming).await?,
+                with_books(&state, outgoing).await?,
+            )
+        }
+        None => (vec![], vec![]),
+    };
+
+    Ok(BorrowRequestsIndexTemplate {
+        incoming,
+        outgoing,
+        router,
+    })
+}
+
+#[serde_as]
+#[derive(Deserialize, Debug)]
+pub struct BorrowRequestForm {
+    /// A word for the owner, kept as the notes of the loan
+    #[serde(default)]
+    #[serde_as(as = "NoneAsEmptyString")]
+    pub message: Option<String>,
+}
+
+/// The logged in user asks the owner to lend them the book
+pub async fn create(
+    State(state): State<AppState>,
+    CurrentUser(current_user): CurrentUser,
+    Path(book_id): Path<i32>,
+    Form(form): Form<BorrowRequestForm>,
+) -> Result<impl axum::response::IntoResponse, AppStateError> {
+    let requester = current_user.context(ForbiddenSnafu).context(AuthSnafu)?;
+    let book = BookOperator::new(state.clone())
+        .find_by_id(book_id)
+        .await
+        .context(BookSnafu)?;
+
+    BorrowRequestOperator::new(state)
+        .create(&book, requester.id, form.message)
+        .await
+        .context(BorrowRequestSnafu)?;
+
+    Ok(Redirect::to(&format!("/books/{}", book_id)))
+}
+
+#[serde_as]
+#[derive(Deserialize, Debug)]
+pub struct ApproveForm {
+    #[serde(default)]
+    #[serde_as(as = "NoneAsEmptyString")]
+    pub due_date: Option<NaiveDate>,
+}
+
+/// The owner lends the book to the requester
+pub async fn approve(
+    State(state): State<AppState>,
+    CurrentUser(current_user): CurrentUser,
+    Path(id): Path<i32>,
+    Form(form): Form<ApproveForm>,
+) -> Result<impl axum::response::IntoResponse, AppStateError> {
+    authorize_answer(&state, &current_user, id).await?;
+
+    BorrowRequestOperator::new(state)
+        .approve(id, form.due_date)
+        .await
+        .context(BorrowRequestSnafu)?;
+
+    Ok(Redirect::to("/requests"))
+}
+
+/// The owner refuses to lend the book
+pub async fn decline(
+    State(state): State<AppState>,
+    CurrentUser(current_user): CurrentUser,
+    Path(id): Path<i32>,
+) -> Result<impl axum::response::IntoResponse, AppStateError> {
+    authorize_answer(&state, &current_user, id).await?;
+
+    BorrowRequestOperator::new(state)
+        .decline(id)
+        .await
+        .context(BorrowRequestSnafu)?;
+
+    Ok(Redirect::to("/requests"))
+}
+
+/// The requester withdraws the request
+pub async fn cancel(
+    State(state): State<AppState>,
+    CurrentUser(current_user): CurrentUser,
+    Path(id): Path<i32>,
+) -> Result<impl axum::response::IntoResponse, AppStateError> {
+    let request_operator = BorrowRequestOperator::new(state.clone());
+    let request = request_operator
+        .find_by_id(id)
+        .await
+        .context(BorrowRequestSnafu)?;
+    auth::authorize(&current_user, Permission::CancelBorrowRequest(&request)).context(AuthSnafu)?;
+
+    request_operator
+        .cancel(id)
+        .await
+        .context(BorrowRequestSnafu)?;
+
+    Ok(Redirect::to("/requests"))
+}
+
+// private
+
+/// Only the owner of the book answers the requests to borrow it
+async fn authorize_answer(
+    state: &AppState,
+    current_user: &Option<UserModel>,
+    id: i32,
+) -> Result<(), AppStateError> {
+    let request = BorrowRequestOperator::new(state.clone())
+        .find_by_id(id)
+        .await
+        .context(BorrowRequestSnafu)?;
+    let book = BookOperator::new(state.clone())
+        .find_by_id(request.book_id)
+        .await
+        .context(BookSnafu)?;
+
+    auth::authorize(current_user, Permission::AnswerBorrowRequest(&book)).context(AuthSnafu)
+}
+
+async fn with_books(
+    state: &AppState,
+    requests: Vec<borrow_request::Model>,
+) -> Result<Vec<RequestWithBook>, AppStateError> {
+    let users_by_id: HashMap<i32, UserModel> = UserOperator::new(state.clone())
+        .all()
+        .await
+        .context(UserSnafu)?
+        .into_iter()
+        .map(|user| (user.id, user))
+        .collect();
+    let books_by_id: HashMap<i32, BookModel> = BookOperator::new(state.clone())
+        .all()
+        .await
+        .context(BookSnafu)?
+        .into_iter()
+        .map(|book| (book.id, book))
+        .collect();
+
+    Ok(requests
+        .into_iter()
+        .filter_map(|request| {
+            let book = books_by_id.get(&request.book_id).cloned()?;
+            let requester = users_by_id.get(&request.requester_id).cloned()?;
+            let owner = users_by_id.get(&book.owner_id).cloned()?;
+
+            Some(RequestWithBook {
+                request,
+                book,
+                requester,
+                owner,
+            })
+        })
+        .collect())
+}
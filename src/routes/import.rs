@@ -0,0 +1,197 @@
+use askama::Template;
+use askama_web::WebTemplate;
+use axum::{
+    Form,
+    extract::{Multipart, State},
+    response::{IntoResponse, Redirect},
+};
+use serde::Deserialize;
+use snafu::prelude::*;
+
+use crate::{
+    auth::{self, Permission},
+    covers,
+    import::{self, ImportFormat, ImportRow, ImportedBook, RowError},
+    models::{book::BookOperator, user::Model as UserModel, user::UserOperator},
+    routes::{book::BookForm, router::Router},
+    state::{
+        AppState,
+        error::{AppStateError, BookSnafu, ImportSnafu, MultipartSnafu, UserSnafu},
+    },
+};
+
+/// Maximum size of an uploaded export, a Calibre library can be quite big
+pub const MAX_UPLOAD_SIZE: usize = 64 * 1024 * 1024;
+
+#[derive(Template, WebTemplate)]
+#[template(path = "books/import.html")]
+struct ImportTemplate {
+    users: Vec<UserModel>,
+    formats: [ImportFormat; 4],
+    router: Router,
+}
+
+/// Upload page of the import
+pub async fn new(
+    State(state): State<AppState>,
+    router: Router,
+) -> Result<impl axum::response::IntoResponse, AppStateError> {
+    let users = UserOperator::new(state.clone())
+        .all()
+        .await
+        .context(UserSnafu)?;
+
+    Ok(ImportTemplate {
+        users,
+        formats: ImportFormat::all(),
+        router,
+    })
+}
+
+#[derive(Template, WebTemplate)]
+#[template(path = "books/import_preview.html")]
+struct ImportPreviewTemplate {
+    rows: Vec<ImportRow>,
+    /// Books of the uploaded file as CSV, sent back to confirm the import
+    content: String,
+    router: Router,
+}
+
+impl ImportPreviewTemplate {
+    fn new_count(&self) -> usize {
+        self.rows.iter().filter(|row| row.is_new()).count()
+    }
+
+    fn duplicate_count(&self) -> usize {
+        self.rows
+            .iter()
+            .filter(|row| row.is_valid() && row.duplicate_of.is_some())
+            .count()
+    }
+
+    fn invalid_count(&self) -> usize {
+        self.rows.iter().filter(|row| !row.is_valid()).count()
+    }
+}
+
+/// Dry run of the import: reads the uploaded file and shows what would be
+/// created, with the errors and the duplicates of every row. Nothing is written.
+pub async fn preview(
+    State(state): State<AppState>,
+    router: Router,
+    mut multipart: Multipart,
+) -> Result<impl axum::response::IntoResponse, AppStateError> {
+    let mut file = vec![];
+    let mut format = ImportFormat::default();
+    let mut owner = None;
+
+    while let Some(field) = multipart.next_field().await.context(MultipartSnafu)? {
+        match field.name() {
+            Some("file") => file = field.bytes().await.context(MultipartSnafu)?.to_vec(),
+            Some("format") => {
+                let value = field.text().await.context(MultipartSnafu)?;
+                format = ImportFormat::from_name(&value).unwrap_or_default();
+            }
+            Some("owner_id") => {
+                owner = import::non_empty(Some(&field.text().await.context(MultipartSnafu)?))
+            }
+            _ => {}
+        }
+    }
+
+    let books = import::parse(format, &file, owner)
+        .await
+        .context(ImportSnafu)?;
+    let content = import::to_csv(&books).context(ImportSnafu)?;
+    let rows = preview_rows(&state, &router.current_user, books).await?;
+
+    Ok(ImportPreviewTemplate {
+        rows,
+        content,
+        router,
+    })
+}
+
+#[derive(Deserialize)]
+pub struct ImportForm {
+    pub content: String,
+}
+
+/// Imports the books previewed before, only if every row is valid.
+/// Duplicates of existing books are skipped.
+pub async fn create(
+    State(state): State<AppState>,
+    router: Router,
+    Form(form): Form<ImportForm>,
+) -> Result<impl axum::response::IntoResponse, AppStateError> {
+    let books = import::bookforge::parse(&form.content).context(ImportSnafu)?;
+    let rows = preview_rows(&state, &router.current_user, books).await?;
+
+    // The data changed since the preview, show the errors again
+    if rows.iter().any(|row| !row.is_valid()) {
+        return Ok(ImportPreviewTemplate {
+            rows,
+            content: form.content,
+            router,
+        }
+        .into_response());
+    }
+
+    let forms: Vec<BookForm> = rows
+        .into_iter()
+        .filter(|row| row.is_new())
+        .filter_map(|row| row.form.ok())
+        .collect();
+    let cover_urls: Vec<Option<String>> = forms.iter().map(|form| form.cover_url.clone()).collect();
+
+    let books = BookOperator::new(state.clone())
+        .import(forms)
+        .await
+        .context(BookSnafu)?;
+
+    // Covers are downloaded once every book is saved, a missing one is not worth
+    // failing the import
+    for (book, cover_url) in books.into_iter().zip(cover_urls) {
+        if let Some(url) = cover_url {
+            let id = book.id;
+            if let Err(error) = covers::download(&state, book, &url).await {
+                log::warn!("Failed to download the cover of book {}: {}", id, error);
+            }
+        }
+    }
+
+    Ok(Redirect::to("/").into_response())
+}
+
+/// Validates the books, looks for duplicates and checks that the current
+/// user can create every book
+async fn preview_rows(
+    state: &AppState,
+    current_user: &Option<UserModel>,
+    books: Vec<ImportedBook>,
+) -> Result<Vec<ImportRow>, AppStateError> {
+    let users = UserOperator::new(state.clone())
+        .all()
+        .await
+        .context(UserSnafu)?;
+    let existing = BookOperator::new(state.clone())
+        .all()
+        .await
+        .context(BookSnafu)?;
+
+    let mut rows = import::preview(books, &users, &existing);
+
+    for row in rows.iter_mut() {
+        let Ok(owner_id) = row.form.as_ref().map(|form| form.owner_id) else {
+            continue;
+        };
+
+        if auth::authorize(current_user, Permission::CreateBookFor(owner_id)).is_err() {
+            row.form = Err(vec![RowError::Forbidden {
+                owner: row.book.owner.clone().unwrap_or_default(),
+            }]);
+        }
+    }
+
+    Ok(rows)
+}
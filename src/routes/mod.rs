@@ -1,3 +1,11 @@
+pub mod api;
+pub mod author;
+pub mod backup;
 pub mod book;
+pub mod borrow_request;
+pub mod import;
 pub mod router;
+pub mod session;
+pub mod tag;
 pub mod user;
+pub mod waitlist;
@@ -1,6 +1,43 @@
+use axum::{extract::FromRequestParts, http::request::Parts};
+
+use crate::{
+    auth::CurrentUser,
+    csrf::CsrfToken,
+    models::{book::Model as BookModel, user},
+    state::AppState,
+};
+
+/// Context shared by every template: paths, the logged in user and the CSRF token
 #[derive(Clone)]
 pub struct Router {
     pub base_path: String,
+    pub current_user: Option<user::Model>,
+    pub csrf_token: String,
+}
+
+impl FromRequestParts<AppState> for Router {
+    type Rejection = std::convert::Infallible;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let current_user = parts
+            .extensions
+            .get::<CurrentUser>()
+            .and_then(|current_user| current_user.0.clone());
+        let csrf_token = parts
+            .extensions
+            .get::<CsrfToken>()
+            .map(|csrf_token| csrf_token.0.clone())
+            .unwrap_or_default();
+
+        Ok(Router {
+            base_path: state.config.base_path.clone(),
+            current_user,
+            csrf_token,
+        })
+    }
 }
 
 impl Router {
@@ -26,18 +63,164 @@ impl Router {
         format!("{}/books", &self.base_path)
     }
 
+    pub fn show_book_path(&self, id: &i32) -> String {
+        format!("{}/books/{}", &self.base_path, id)
+    }
+
     pub fn update_book_path(&self, id: &i32) -> String {
         format!("{}/books/{}", &self.base_path, id)
     }
 
+    pub fn return_book_path(&self, id: &i32) -> String {
+        format!("{}/books/{}/return", &self.base_path, id)
+    }
+
     pub fn download_csv_book_path(&self) -> String {
         format!("{}/books/download_csv", &self.base_path)
     }
 
+    pub fn import_books_path(&self) -> String {
+        format!("{}/books/import", &self.base_path)
+    }
+
+    pub fn preview_import_books_path(&self) -> String {
+        format!("{}/books/import/preview", &self.base_path)
+    }
+
     pub fn search_books_path(&self) -> String {
         format!("{}/books/search", &self.base_path)
     }
 
+    /// Original cover, the name of the file makes the URL change with the cover
+    pub fn cover_path(&self, book: &BookModel) -> String {
+        format!(
+            "{}/books/{}/cover?v={}",
+            &self.base_path,
+            book.id,
+            book.cover.as_deref().unwrap_or_default()
+        )
+    }
+
+    pub fn thumbnail_path(&self, book: &BookModel) -> String {
+        format!(
+            "{}/books/{}/thumbnail?v={}",
+            &self.base_path,
+            book.id,
+            book.cover.as_deref().unwrap_or_default()
+        )
+    }
+
+    // BORROW REQUESTS
+
+    pub fn index_borrow_request_path(&self) -> String {
+        format!("{}/requests", &self.base_path)
+    }
+
+    pub fn create_borrow_request_path(&self, book_id: &i32) -> String {
+        format!("{}/books/{}/requests", &self.base_path, book_id)
+    }
+
+    pub fn approve_borrow_request_path(&self, id: &i32) -> String {
+        format!("{}/requests/{}/approve", &self.base_path, id)
+    }
+
+    pub fn decline_borrow_request_path(&self, id: &i32) -> String {
+        format!("{}/requests/{}/decline", &self.base_path, id)
+    }
+
+    pub fn cancel_borrow_request_path(&self, id: &i32) -> String {
+        format!("{}/requests/{}/cancel", &self.base_path, id)
+    }
+
+    // WAITLIST
+
+    pub fn join_waitlist_path(&self, book_id: &i32) -> String {
+        format!("{}/books/{}/waitlist", &self.base_path, book_id)
+    }
+
+    pub fn leave_waitlist_path(&self, book_id: &i32) -> String {
+        format!("{}/books/{}/waitlist/leave", &self.base_path, book_id)
+    }
+
+    pub fn clear_waitlist_path(&self, book_id: &i32) -> String {
+        format!("{}/books/{}/waitlist/clear", &self.base_path, book_id)
+    }
+
+    pub fn skip_waitlist_path(&self, book_id: &i32) -> String {
+        format!("{}/books/{}/waitlist/skip", &self.base_path, book_id)
+    }
+
+    pub fn move_up_waitlist_path(&self, id: &i32) -> String {
+        format!("{}/waitlist/{}/up", &self.base_path, id)
+    }
+
+    pub fn move_down_waitlist_path(&self, id: &i32) -> String {
+        format!("{}/waitlist/{}/down", &self.base_path, id)
+    }
+
+    // TAGS
+
+    pub fn index_tag_path(&self) -> String {
+        format!("{}/tags", &self.base_path)
+    }
+
+    pub fn update_tag_path(&self, id: &i32) -> String {
+        format!("{}/tags/{}", &self.base_path, id)
+    }
+
+    pub fn delete_tag_path(&self, id: &i32) -> String {
+        format!("{}/tags/{}/delete", &self.base_path, id)
+    }
+
+    /// Book list filtered on a tag
+    pub fn books_tagged_path(&self, name: &str) -> String {
+        let query = serde_urlencoded::to_string([("tags", name)]).unwrap_or_default();
+        format!("{}/?{}", &self.base_path, query)
+    }
+
+    // AUTHORS
+
+    pub fn index_author_path(&self) -> String {
+        format!("{}/authors", &self.base_path)
+    }
+
+    pub fn show_author_path(&self, id: &i32) -> String {
+        format!("{}/authors/{}", &self.base_path, id)
+    }
+
+    pub fn update_author_path(&self, id: &i32) -> String {
+        format!("{}/authors/{}", &self.base_path, id)
+    }
+
+    /// Book list filtered on an author
+    pub fn books_by_author_path(&self, id: &i32) -> String {
+        format!("{}/?author_id={}", &self.base_path, id)
+    }
+
+    // BACKUP
+
+    pub fn backup_path(&self) -> String {
+        format!("{}/backup", &self.base_path)
+    }
+
+    pub fn download_backup_path(&self) -> String {
+        format!("{}/backup/download", &self.base_path)
+    }
+
+    pub fn restore_backup_path(&self) -> String {
+        format!("{}/backup/restore", &self.base_path)
+    }
+
+    // SESSIONS
+
+    pub fn login_path(&self) -> String {
+        format!("{}/login", &self.base_path)
+    }
+
+    pub fn logout_path(&self) -> String {
+        format!("{}/logout", &self.base_path)
+    }
+
     // USERS
 
     pub fn index_user_path(&self) -> String {
@@ -52,6 +235,10 @@ impl Router {
         format!("{}/users", &self.base_path)
     }
 
+    pub fn show_user_path(&self, id: &i32) -> String {
+        format!("{}/users/{}", &self.base_path, id)
+    }
+
     pub fn update_user_path(&self, id: &i32) -> String {
         format!("{}/users/{}", &self.base_path, id)
     }
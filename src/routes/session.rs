@@ -0,0 +1,96 @@
+use askama::Template;
+use askama_web::WebTemplate;
+use axum::{
+    Form,
+    extract::State,
+    response::{IntoResponse, Redirect},
+};
+use axum_extra::extract::{
+    CookieJar,
+    cookie::{Cookie, SameSite},
+};
+use serde::Deserialize;
+use snafu::prelude::*;
+
+use crate::{
+    auth::SESSION_COOKIE,
+    models::{session::SessionOperator, user::UserOperator},
+    routes::router::Router,
+    state::{
+        AppState,
+        error::{AppStateError, SessionSnafu, UserSnafu},
+    },
+};
+
+#[derive(Template, WebTemplate)]
+#[template(path = "sessions/new.html")]
+struct LoginTemplate {
+    failed: bool,
+    router: Router,
+}
+
+#[derive(Deserialize)]
+pub struct LoginForm {
+    pub name: String,
+    pub password: String,
+}
+
+pub async fn new(router: Router) -> impl axum::response::IntoResponse {
+    LoginTemplate {
+        failed: false,
+        router,
+    }
+}
+
+pub async fn create(
+    State(state): State<AppState>,
+    router: Router,
+    jar: CookieJar,
+    Form(form): Form<LoginForm>,
+) -> Result<impl axum::response::IntoResponse, AppStateError> {
+    let user = UserOperator::new(state.clone())
+        .authenticate(&form.name, &form.password)
+        .await
+        .context(UserSnafu)?;
+
+    let Some(user) = user else {
+        return Ok(LoginTemplate {
+            failed: true,
+            router,
+        }
+        .into_response());
+    };
+
+    let session = SessionOperator::new(state.clone())
+        .create(user.id)
+        .await
+        .context(SessionSnafu)?;
+
+    let cookie = Cookie::build((SESSION_COOKIE, session.id))
+        .path("/")
+        .http_only(true)
+        .same_site(SameSite::Lax)
+        .secure(state.config.auth.secure_cookie)
+        .max_age(time::Duration::days(state.config.auth.session_days));
+
+    // The session comes with its own CSRF token
+    let jar = jar.add(cookie);
+
+    Ok((jar, Redirect::to("/")).into_response())
+}
+
+pub async fn delete(
+    State(state): State<AppState>,
+    jar: CookieJar,
+) -> Result<impl axum::response::IntoResponse, AppStateError> {
+    if let Some(cookie) = jar.get(SESSION_COOKIE) {
+        SessionOperator::new(state)
+            .delete(cookie.value())
+            .await
+            .context(SessionSnafu)?;
+    }
+
+    let jar = jar.remove(Cookie::build(SESSION_COOKIE).path("/"));
+
+    Ok((jar, Redirect::to("/login")))
+}
@@ -0,0 +1,93 @@
+use askama::Template;
+use askama_web::WebTemplate;
+use axum::{
+    Form,
+    extract::{Path, State},
+    response::Redirect,
+};
+use serde::Deserialize;
+use snafu::prelude::*;
+
+use crate::{
+    auth::{self, CurrentUser, Permission},
+    models::tag::{self, TagOperator},
+    routes::router::Router,
+    state::{
+        AppState,
+        error::{AppStateError, AuthSnafu, TagSnafu},
+    },
+};
+
+/// A tag with the number of books it is on
+pub struct TagWithBookNumber {
+    pub tag: tag::Model,
+    pub book_number: usize,
+}
+
+#[derive(Template, WebTemplate)]
+#[template(path = "tags/index.html")]
+struct TagsIndexTemplate {
+    tags: Vec<TagWithBookNumber>,
+    /// Admins can rename and delete the tags
+    can_manage: bool,
+    router: Router,
+}
+
+pub async fn index(
+    State(state): State<AppState>,
+    router: Router,
+) -> Result<impl axum::response::IntoResponse, AppStateError> {
+    let tags = TagOperator::new(state.clone())
+        .all_with_count()
+        .await
+        .context(TagSnafu)?
+        .into_iter()
+        .map(|(tag, book_number)| TagWithBookNumber { tag, book_number })
+        .collect();
+
+    let can_manage = router
+        .current_user
+        .as_ref()
+        .is_some_and(|user| user.is_admin());
+
+    Ok(TagsIndexTemplate {
+        tags,
+        can_manage,
+        router,
+    })
+}
+
+#[derive(Deserialize, Debug)]
+pub struct TagForm {
+    pub name: String,
+}
+
+/// Rename a tag on every book
+pub async fn update(
+    State(state): State<AppState>,
+    CurrentUser(current_user): CurrentUser,
+    Path(id): Path<i32>,
+    Form(form): Form<TagForm>,
+) -> Result<impl axum::response::IntoResponse, AppStateError> {
+    auth::authorize(&current_user, Permission::ManageTags).context(AuthSnafu)?;
+
+    TagOperator::new(state)
+        .rename(id, &form.name)
+        .await
+        .context(TagSnafu)?;
+
+    Ok(Redirect::to("/tags"))
+}
+
+/// Remove a tag from every book and delete it
+pub async fn delete(
+    State(state): State<AppState>,
+    CurrentUser(current_user): CurrentUser,
+    Path(id): Path<i32>,
+) -> Result<impl axum::response::IntoResponse, AppStateError> {
+    auth::authorize(&current_user, Permission::ManageTags).context(AuthSnafu)?;
+
+    let _ = TagOperator::new(state).delete(id).await.context(TagSnafu)?;
+
+    Ok(Redirect::to("/tags"))
+}
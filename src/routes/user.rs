@@ -10,16 +10,19 @@ use axum::{
 use serde::Deserialize;
 use serde_with::{NoneAsEmptyString, serde_as};
 use snafu::prelude::*;
+use utoipa::IntoParams;
 
 use crate::{
+    auth::{self, CurrentUser, Permission},
     models::{
-        book::BookOperator,
-        user::{self, UserOperator},
+        book::{self, BookOperator},
+        loan::{self, LoanOperator},
+        user::{self, Role, UserOperator},
     },
     routes::router::Router,
     state::{
         AppState,
-        error::{AppStateError, BookSnafu, UserSnafu},
+        error::{AppStateError, AuthSnafu, BookSnafu, LoanSnafu, UserSnafu},
     },
 };
 
@@ -41,7 +44,8 @@ pub struct UserWithBookNumber {
 }
 
 #[serde_as]
-#[derive(Deserialize, Clone)]
+#[derive(Deserialize, Clone, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct IndexQuery {
     #[serde(default)]
     #[serde_as(as = "NoneAsEmptyString")]
@@ -50,6 +54,7 @@ pub struct IndexQuery {
 
 pub async fn index(
     State(state): State<AppState>,
+    router: Router,
     Query(query): Query<IndexQuery>,
 ) -> Result<impl axum::response::IntoResponse, AppStateError> {
     let users = UserOperator::new(state.clone())
@@ -88,21 +93,30 @@ pub async fn index(
     Ok(UsersIndexTemplate {
         users_with_books_number: result,
         query,
-        router: Router {
-            base_path: state.config.base_path,
-        },
+        router,
     })
 }
 
+#[serde_as]
 #[derive(Deserialize)]
 pub struct UserForm {
     pub name: String,
+    /// New password, an empty password keeps the current one
+    #[serde(default)]
+    #[serde_as(as = "NoneAsEmptyString")]
+    pub password: Option<String>,
+    /// Only taken into account for admins
+    #[serde(default)]
+    pub role: Option<Role>,
 }
 
 pub async fn create(
     State(state): State<AppState>,
+    CurrentUser(current_user): CurrentUser,
     Form(form): Form<UserForm>,
 ) -> Result<impl axum::response::IntoResponse, AppStateError> {
+    auth::authorize(&current_user, Permission::ManageUsers).context(AuthSnafu)?;
+
     let _ = UserOperator::new(state)
         .create(form)
         .await
@@ -113,9 +127,17 @@ pub async fn create(
 
 pub async fn update(
     State(state): State<AppState>,
+    CurrentUser(current_user): CurrentUser,
     Path(id): Path<i32>,
     Form(form): Form<UserForm>,
 ) -> Result<impl axum::response::IntoResponse, AppStateError> {
+    auth::authorize(&current_user, Permission::EditUser(id)).context(AuthSnafu)?;
+
+    // Only admins can change roles
+    if form.role.is_some() {
+        auth::authorize(&current_user, Permission::ManageUsers).context(AuthSnafu)?;
+    }
+
     let _ = UserOperator::new(state)
         .update(id, form)
         .await
@@ -126,8 +148,11 @@ pub async fn update(
 
 pub async fn delete(
     State(state): State<AppState>,
+    CurrentUser(current_user): CurrentUser,
     Path(id): Path<i32>,
 ) -> Result<impl axum::response::IntoResponse, AppStateError> {
+    auth::authorize(&current_user, Permission::ManageUsers).context(AuthSnafu)?;
+
     let _user = UserOperator::new(state)
         .delete(id)
         .await
@@ -136,6 +161,77 @@ pub async fn delete(
     Ok(Redirect::to("/users"))
 }
 
+/// A loan of the user history with the book and the other users involved
+pub struct LoanWithBook {
+    pub loan: loan::Model,
+    pub book: book::Model,
+    /// None once the user is deleted
+    pub borrower: Option<user::Model>,
+    pub lender: Option<user::Model>,
+}
+
+#[derive(Template, WebTemplate)]
+#[template(path = "users/show.html")]
+struct ShowTemplate {
+    user: user::Model,
+    loans: Vec<LoanWithBook>,
+    router: Router,
+}
+
+pub async fn show(
+    State(state): State<AppState>,
+    router: Router,
+    Path(id): Path<i32>,
+) -> Result<impl axum::response::IntoResponse, AppStateError> {
+    let user = UserOperator::new(state.clone())
+        .find_by_id(id)
+        .await
+        .context(UserSnafu)?;
+
+    let users_by_id: HashMap<i32, user::Model> = UserOperator::new(state.clone())
+        .all()
+        .await
+        .context(UserSnafu)?
+        .into_iter()
+        .map(|user| (user.id, user))
+        .collect();
+
+    let books_by_id: HashMap<i32, book::Model> = BookOperator::new(state.clone())
+        .all()
+        .await
+        .context(BookSnafu)?
+        .into_iter()
+        .map(|book| (book.id, book))
+        .collect();
+
+    let loans: Vec<LoanWithBook> = LoanOperator::new(state.clone())
+        .find_all_by_user(user.id)
+        .await
+        .context(LoanSnafu)?
+        .into_iter()
+        .filter_map(|loan| {
+            let book = books_by_id.get(&loan.book_id).cloned()?;
+            let borrower = loan
+                .borrower_id
+                .and_then(|id| users_by_id.get(&id).cloned());
+            let lender = loan.lender_id.and_then(|id| users_by_id.get(&id).cloned());
+
+            Some(LoanWithBook {
+                loan,
+                book,
+                borrower,
+                lender,
+            })
+        })
+        .collect();
+
+    Ok(ShowTemplate {
+        user,
+        loans,
+        router,
+    })
+}
+
 #[derive(Template, WebTemplate)]
 #[template(path = "users/edit.html")]
 struct EditTemplate {
@@ -145,19 +241,17 @@ struct EditTemplate {
 
 pub async fn edit(
     State(state): State<AppState>,
+    router: Router,
     Path(id): Path<i32>,
 ) -> Result<impl axum::response::IntoResponse, AppStateError> {
+    auth::authorize(&router.current_user, Permission::EditUser(id)).context(AuthSnafu)?;
+
     let user = UserOperator::new(state.clone())
         .find_by_id(id)
         .await
         .context(UserSnafu)?;
 
-    Ok(EditTemplate {
-        user,
-        router: Router {
-            base_path: state.config.base_path,
-        },
-    })
+    Ok(EditTemplate { user, router })
 }
 
 #[derive(Template, WebTemplate)]
@@ -166,10 +260,8 @@ struct NewTemplate {
     router: Router,
 }
 
-pub async fn new(State(state): State<AppState>) -> impl axum::response::IntoResponse {
-    NewTemplate {
-        router: Router {
-            base_path: state.config.base_path,
-        },
-    }
+pub async fn new(router: Router) -> Result<impl axum::response::IntoResponse, AppStateError> {
+    auth::authorize(&router.current_user, Permission::ManageUsers).context(AuthSnafu)?;
+
+    Ok(NewTemplate { router })
 }
@@ -0,0 +1,136 @@
+use axum::{
+    extract::{Path, State},
+    response::Redirect,
+};
+use snafu::prelude::*;
+
+use crate::{
+    auth::{self, CurrentUser, ForbiddenSnafu, Permission},
+    models::book::BookOperator,
+    models::user::Model as UserModel,
+    models::waitlist::{Move, WaitlistOperator},
+    state::{
+        AppState,
+        error::{AppStateError, AuthSnafu, BookSnafu, WaitlistSnafu},
+    },
+};
+
+/// The logged in user gets in line for the book
+pub async fn join(
+    State(state): State<AppState>,
+    CurrentUser(current_user): CurrentUser,
+    Path(book_id): Path<i32>,
+) -> Result<impl axum::response::IntoResponse, AppStateError> {
+    let user = current_user.context(ForbiddenSnafu).context(AuthSnafu)?;
+    let book = BookOperator::new(state.clone())
+        .find_by_id(book_id)
+        .await
+        .context(BookSnafu)?;
+
+    WaitlistOperator::new(state)
+        .join(&book, user.id)
+        .await
+        .context(WaitlistSnafu)?;
+
+    Ok(Redirect::to(&format!("/books/{}", book_id)))
+}
+
+/// The logged in user leaves the waitlist, or turns down the book when it is their turn
+pub async fn leave(
+    State(state): State<AppState>,
+    CurrentUser(current_user): CurrentUser,
+    Path(book_id): Path<i32>,
+) -> Result<impl axum::response::IntoResponse, AppStateError> {
+    let user = current_user.context(ForbiddenSnafu).context(AuthSnafu)?;
+
+    WaitlistOperator::new(state)
+        .leave(book_id, user.id)
+        .await
+        .context(WaitlistSnafu)?;
+
+    Ok(Redirect::to(&format!("/books/{}", book_id)))
+}
+
+/// The owner empties the waitlist of the book
+pub async fn clear(
+    State(state): State<AppState>,
+    CurrentUser(current_user): CurrentUser,
+    Path(book_id): Path<i32>,
+) -> Result<impl axum::response::IntoResponse, AppStateError> {
+    let book = BookOperator::new(state.clone())
+        .find_by_id(book_id)
+        .await
+        .context(BookSnafu)?;
+    auth::authorize(&current_user, Permission::ManageWaitlist(&book)).context(AuthSnafu)?;
+
+    WaitlistOperator::delete_all_by_book(&state.db, book_id)
+        .await
+        .context(WaitlistSnafu)?;
+
+    Ok(Redirect::to(&format!("/books/{}", book_id)))
+}
+
+/// The owner passes the turn of the user offered the book to the next one
+pub async fn skip(
+    State(state): State<AppState>,
+    CurrentUser(current_user): CurrentUser,
+    Path(book_id): Path<i32>,
+) -> Result<impl axum::response::IntoResponse, AppStateError> {
+    let book = BookOperator::new(state.clone())
+        .find_by_id(book_id)
+        .await
+        .context(BookSnafu)?;
+    auth::authorize(&current_user, Permission::ManageWaitlist(&book)).context(AuthSnafu)?;
+
+    WaitlistOperator::new(state)
+        .skip(&book)
+        .await
+        .context(WaitlistSnafu)?;
+
+    Ok(Redirect::to(&format!("/books/{}", book_id)))
+}
+
+/// The owner moves someone one place closer to the book
+pub async fn move_up(
+    State(state): State<AppState>,
+    CurrentUser(current_user): CurrentUser,
+    Path(id): Path<i32>,
+) -> Result<impl axum::response::IntoResponse, AppStateError> {
+    move_entry(state, current_user, id, Move::Up).await
+}
+
+/// The owner moves someone one place further from the book
+pub async fn move_down(
+    State(state): State<AppState>,
+    CurrentUser(current_user): CurrentUser,
+    Path(id): Path<i32>,
+) -> Result<impl axum::response::IntoResponse, AppStateError> {
+    move_entry(state, current_user, id, Move::Down).await
+}
+
+// private
+
+async fn move_entry(
+    state: AppState,
+    current_user: Option<UserModel>,
+    id: i32,
+    direction: Move,
+) -> Result<Redirect, AppStateError> {
+    let waitlist_operator = WaitlistOperator::new(state.clone());
+    let entry = waitlist_operator
+        .find_by_id(id)
+        .await
+        .context(WaitlistSnafu)?;
+    let book = BookOperator::new(state.clone())
+        .find_by_id(entry.book_id)
+        .await
+        .context(BookSnafu)?;
+    auth::authorize(&current_user, Permission::ManageWaitlist(&book)).context(AuthSnafu)?;
+
+    waitlist_operator
+        .move_entry(id, direction)
+        .await
+        .context(WaitlistSnafu)?;
+
+    Ok(Redirect::to(&format!("/books/{}", book.id)))
+}
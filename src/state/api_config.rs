@@ -1,7 +1,9 @@
 use serde::{Deserialize, Serialize};
 
 #[derive(Clone, Deserialize, Serialize, Debug)]
+#[serde(deny_unknown_fields)]
 pub struct ApiConfig {
+    #[serde(default)]
     pub google_books_api_key: String,
 }
 
@@ -0,0 +1,37 @@
+use serde::{Deserialize, Serialize};
+
+/// Authentication settings.
+///
+/// Mutating routes always require a logged in user, `public_catalogue`
+/// decides if the books and users can be browsed anonymously. Behind HTTPS,
+/// `secure_cookie` keeps the session cookie from being sent over plain HTTP.
+#[derive(Clone, Deserialize, Serialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct AuthConfig {
+    #[serde(default = "AuthConfig::default_public_catalogue")]
+    pub public_catalogue: bool,
+    #[serde(default = "AuthConfig::default_session_days")]
+    pub session_days: i64,
+    #[serde(default)]
+    pub secure_cookie: bool,
+}
+
+impl Default for AuthConfig {
+    fn default() -> Self {
+        AuthConfig {
+            public_catalogue: Self::default_public_catalogue(),
+            session_days: Self::default_session_days(),
+            secure_cookie: false,
+        }
+    }
+}
+
+impl AuthConfig {
+    fn default_public_catalogue() -> bool {
+        true
+    }
+
+    fn default_session_days() -> i64 {
+        30
+    }
+}
@@ -1,20 +1,30 @@
 use askama::Template;
 use askama_web::WebTemplate;
+use axum::http::StatusCode;
 use axum::response::{IntoResponse, Response};
 use log::error;
 use snafu::prelude::*;
 
 use crate::{
-    models::{book::BookError, user::UserError},
+    auth::AuthError,
+    covers::CoverError,
+    import::ImportError,
+    metadata::MetadataError,
+    models::{
+        author::AuthorError, backup::BackupError, book::BookError,
+        borrow_request::BorrowRequestError, isbn::IsbnError, loan::LoanError,
+        session::SessionError, tag::TagError, user::UserError, waitlist::WaitlistError,
+    },
     routes::router::Router,
     state::config::ConfigError,
+    storage::StorageError,
 };
 
 #[derive(Snafu, Debug)]
 #[snafu(visibility(pub))]
 pub enum AppStateError {
     Error,
-    #[snafu(display("Sqlite Error"))]
+    #[snafu(display("Database Error"))]
     Sqlite {
         source: sea_orm::error::DbErr,
     },
@@ -34,6 +44,60 @@ pub enum AppStateError {
     Book {
         source: BookError,
     },
+    #[snafu(display("Loan Model Error"))]
+    Loan {
+        source: LoanError,
+    },
+    #[snafu(display("Tag Error: {source}"))]
+    Tag {
+        source: TagError,
+    },
+    #[snafu(display("Author Error: {source}"))]
+    Author {
+        source: AuthorError,
+    },
+    #[snafu(display("Borrow Request Error: {source}"))]
+    BorrowRequest {
+        source: BorrowRequestError,
+    },
+    #[snafu(display("Waitlist Error: {source}"))]
+    Waitlist {
+        source: WaitlistError,
+    },
+    #[snafu(display("Cover Error: {source}"))]
+    Cover {
+        source: CoverError,
+    },
+    #[snafu(display("Invalid form: {source}"))]
+    InvalidForm {
+        source: serde_urlencoded::de::Error,
+    },
+    #[snafu(display("Session Model Error"))]
+    Session {
+        source: SessionError,
+    },
+    #[snafu(display("Authorization Error"))]
+    Auth {
+        source: AuthError,
+    },
+    #[snafu(display("Invalid or missing CSRF token, reload the page and try again"))]
+    Csrf,
+    #[snafu(display("Backup Error: {source}"))]
+    Backup {
+        source: BackupError,
+    },
+    #[snafu(display("Failed to write the backup"))]
+    BackupJson {
+        source: serde_json::Error,
+    },
+    #[snafu(display("Import Error: {source}"))]
+    Import {
+        source: ImportError,
+    },
+    #[snafu(display("Upload Error"))]
+    Multipart {
+        source: axum::extract::multipart::MultipartError,
+    },
     #[snafu(display("CSV Error"))]
     CSV {
         source: csv::Error,
@@ -42,9 +106,13 @@ pub enum AppStateError {
     IO {
         source: std::io::Error,
     },
-    #[snafu(display("Google Books Error"))]
-    GoogleBook {
-        source: googlebooks_rs::errors::AppError,
+    #[snafu(display("ISBN Error"))]
+    Isbn {
+        source: IsbnError,
+    },
+    #[snafu(display("Metadata Provider Error"))]
+    Metadata {
+        source: MetadataError,
     },
 }
 
@@ -67,15 +135,97 @@ impl From<AppStateError> for AppStateErrorContext {
     }
 }
 
+impl AppStateError {
+    fn status(&self) -> StatusCode {
+        match self {
+            AppStateError::Auth {
+                source: AuthError::Forbidden,
+            }
+            | AppStateError::Csrf => StatusCode::FORBIDDEN,
+            AppStateError::Book {
+                source: BookError::NotFound { .. },
+            }
+            | AppStateError::User {
+                source: UserError::NotFound { .. },
+            }
+            | AppStateError::Tag {
+                source: TagError::NotFound { .. },
+            }
+            | AppStateError::Author {
+                source: AuthorError::NotFound { .. },
+            }
+            | AppStateError::BorrowRequest {
+                source:
+                    BorrowRequestError::NotFound { .. } | BorrowRequestError::BookNotFound { .. },
+            }
+            | AppStateError::Waitlist {
+                source: WaitlistError::NotFound { .. } | WaitlistError::BookNotFound { .. },
+            }
+            | AppStateError::Cover {
+                source:
+                    CoverError::NotFound { .. }
+                    | CoverError::Storage {
+                        source: StorageError::NotFound { .. },
+                    },
+            } => StatusCode::NOT_FOUND,
+            AppStateError::Import { .. }
+            | AppStateError::Tag {
+                source: TagError::EmptyName | TagError::AlreadyExists { .. },
+            }
+            | AppStateError::Author {
+                source: AuthorError::EmptyName,
+            }
+            | AppStateError::User {
+                source: UserError::AlreadyExists { .. },
+            }
+            | AppStateError::BorrowRequest {
+                source:
+                    BorrowRequestError::OwnBook
+                    | BorrowRequestError::Unavailable
+                    | BorrowRequestError::AlreadyRequested
+                    | BorrowRequestError::Reserved
+                    | BorrowRequestError::NotPending { .. },
+            }
+            | AppStateError::Waitlist {
+                source:
+                    WaitlistError::OwnBook
+                    | WaitlistError::Holder
+                    | WaitlistError::NotLentOut
+                    | WaitlistError::AlreadyWaiting
+                    | WaitlistError::NotOffered
+                    | WaitlistError::Reserved,
+            }
+            | AppStateError::Cover {
+                source: CoverError::Image { .. } | CoverError::TooLarge,
+            }
+            | AppStateError::InvalidForm { .. }
+            | AppStateError::Backup {
+                source:
+                    BackupError::Json { .. }
+                    | BackupError::UnsupportedVersion { .. }
+                    | BackupError::UnknownUser { .. }
+                    | BackupError::UnknownBook { .. }
+                    | BackupError::UnknownBorrowRequest { .. },
+            } => StatusCode::UNPROCESSABLE_ENTITY,
+            AppStateError::Multipart { .. } => StatusCode::BAD_REQUEST,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
 impl IntoResponse for AppStateError {
     fn into_response(self) -> Response {
+        let status = self.status();
         let error_context = AppStateErrorContext::from(self);
-        ErrorTemplate {
+        let template = ErrorTemplate {
             state: error_context,
             router: Router {
                 base_path: "".to_string(),
+                current_user: None,
+                csrf_token: String::new(),
             },
-        }
-        .into_response()
+        };
+
+        (status, template).into_response()
     }
 }
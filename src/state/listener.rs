@@ -1,18 +1,21 @@
 use snafu::prelude::*;
-use std::net::{AddrParseError, SocketAddr};
+use std::net::{AddrParseError, IpAddr, SocketAddr};
+use std::num::TryFromIntError;
 
 use serde::{Deserialize, Serialize};
 
 /// Errors related to listener configuration and socket binding.
 #[derive(Snafu, Debug)]
 pub enum ListenerError {
-    /// The provided bind address and/or port could not be parsed into a valid
-    /// `SocketAddr`.
-    #[snafu(display("Socket address is wrong"))]
-    SocketAddrInvalid {
-        socket_addr: String,
+    /// The provided bind address could not be parsed into a valid IP address.
+    #[snafu(display("Bind address {bind_addr} is wrong"))]
+    BindAddrInvalid {
+        bind_addr: String,
         source: AddrParseError,
     },
+    /// The provided port doesn't fit in 16 bits.
+    #[snafu(display("Port {port} is wrong"))]
+    PortInvalid { port: u32, source: TryFromIntError },
 }
 
 /// Listener configuration used to bind a TCP socket.
@@ -20,8 +23,11 @@ pub enum ListenerError {
 /// `bind_addr` represents the interface to bind to (e.g. `0.0.0.0`,
 /// `127.0.0.1`, `::`), and `port` is the TCP port.
 #[derive(Clone, Deserialize, Serialize, Debug)]
+#[serde(deny_unknown_fields)]
 pub struct Listener {
+    #[serde(default = "Listener::default_port")]
     pub port: u32,
+    #[serde(default = "Listener::default_bind_addr")]
     pub bind_addr: String,
 }
 
@@ -51,12 +57,58 @@ impl Listener {
     /// Computes the socket address used for binding.
     ///
     /// # Errors
-    /// Returns `ListenerError::SocketAddrInvalid` if the address or port
-    /// cannot be parsed into a valid `SocketAddr`.
+    /// Returns `ListenerError::BindAddrInvalid` if the address is not an IPv4
+    /// or IPv6 address, or `ListenerError::PortInvalid` if the port is above 65535.
     pub fn socket_addr(&self) -> Result<SocketAddr, ListenerError> {
-        let socket_addr = format!("{}:{}", self.bind_addr, self.port);
-        socket_addr
-            .parse()
-            .context(SocketAddrInvalidSnafu { socket_addr })
+        let ip: IpAddr = self.bind_addr.parse().context(BindAddrInvalidSnafu {
+            bind_addr: &self.bind_addr,
+        })?;
+        let port = u16::try_from(self.port).context(PortInvalidSnafu { port: self.port })?;
+
+        Ok(SocketAddr::new(ip, port))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn listener(bind_addr: &str, port: u32) -> Listener {
+        Listener {
+            port,
+            bind_addr: bind_addr.to_string(),
+        }
+    }
+
+    #[test]
+    fn ipv4_socket_addr() {
+        assert_eq!(
+            listener("127.0.0.1", 8000).socket_addr().unwrap(),
+            "127.0.0.1:8000".parse().unwrap()
+        );
+    }
+
+    #[test]
+    fn ipv6_socket_addr() {
+        assert_eq!(
+            listener("::", 8000).socket_addr().unwrap(),
+            "[::]:8000".parse().unwrap()
+        );
+        assert_eq!(
+            listener("::1", 8080).socket_addr().unwrap(),
+            "[::1]:8080".parse().unwrap()
+        );
+    }
+
+    #[test]
+    fn invalid_socket_addr() {
+        assert!(matches!(
+            listener("localhost", 8000).socket_addr(),
+            Err(ListenerError::BindAddrInvalid { .. })
+        ));
+        assert!(matches!(
+            listener("::", 70000).socket_addr(),
+            Err(ListenerError::PortInvalid { .. })
+        ));
     }
 }
@@ -0,0 +1,68 @@
+use serde::{Deserialize, Serialize};
+
+use crate::metadata::MetadataProviderKind;
+
+/// Which metadata providers are queried when searching a book, and in what order.
+#[derive(Clone, Deserialize, Serialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct MetadataConfig {
+    #[serde(default = "MetadataConfig::default_providers")]
+    pub providers: Vec<MetadataProviderKind>,
+    #[serde(default = "MetadataConfig::default_google_books_url")]
+    pub google_books_url: String,
+    #[serde(default = "MetadataConfig::default_open_library_url")]
+    pub open_library_url: String,
+    /// Hosts the covers given by the providers are downloaded from, with their
+    /// subdomains. Any other URL is refused, it could reach the local network.
+    #[serde(default = "MetadataConfig::default_cover_hosts")]
+    pub cover_hosts: Vec<String>,
+}
+
+impl Default for MetadataConfig {
+    fn default() -> Self {
+        MetadataConfig {
+            providers: Self::default_providers(),
+            google_books_url: Self::default_google_books_url(),
+            open_library_url: Self::default_open_library_url(),
+            cover_hosts: Self::default_cover_hosts(),
+        }
+    }
+}
+
+impl MetadataConfig {
+    fn default_providers() -> Vec<MetadataProviderKind> {
+        vec![
+            MetadataProviderKind::GoogleBooks,
+            MetadataProviderKind::OpenLibrary,
+        ]
+    }
+
+    fn default_google_books_url() -> String {
+        "https://www.googleapis.com/books/v1".to_string()
+    }
+
+    fn default_open_library_url() -> String {
+        "https://openlibrary.org".to_string()
+    }
+
+    /// Open Library redirects its covers to the Internet Archive
+    fn default_cover_hosts() -> Vec<String> {
+        [
+            "books.google.com",
+            "books.googleusercontent.com",
+            "covers.openlibrary.org",
+            "archive.org",
+        ]
+        .map(String::from)
+        .to_vec()
+    }
+
+    /// Whether the covers can be downloaded from this host
+    pub fn allows_cover_host(&self, host: &str) -> bool {
+        let host = host.to_lowercase();
+        self.cover_hosts.iter().any(|allowed| {
+            let allowed = allowed.to_lowercase();
+            host == allowed || host.ends_with(&format!(".{}", allowed))
+        })
+    }
+}
@@ -1,35 +1,68 @@
-use sea_orm::{Database, DatabaseConnection};
+use std::sync::Arc;
+
+use camino::Utf8PathBuf;
+use sea_orm::{ConnectionTrait, Database, DatabaseConnection};
 use snafu::prelude::*;
 
-use crate::{migrations::Migrator, state::config::AppConfig};
+use crate::{
+    migrations::Migrator,
+    state::config::AppConfig,
+    storage::{self, Storage},
+};
 use error::*;
 use sea_orm_migration::MigratorTrait;
 
 pub mod api_config;
+pub mod auth_config;
 pub mod config;
 pub mod error;
 pub mod listener;
+pub mod metadata_config;
+pub mod pagination_config;
+pub mod storage_config;
+pub mod waitlist_config;
 
 #[derive(Clone, Debug)]
 pub struct AppState {
     pub config: AppConfig,
     pub db: DatabaseConnection,
+    /// Backend of the uploaded files, built once from `config.storage`
+    pub storage: Arc<dyn Storage>,
 }
 
 impl AppState {
-    pub async fn new() -> Result<Self, AppStateError> {
+    /// Loads the configuration (see [`AppConfig::new`] for `config_path`),
+    /// opens the database and applies the pending migrations
+    pub async fn new(config_path: Option<Utf8PathBuf>) -> Result<Self, AppStateError> {
+        let state = Self::without_migrations(config_path).await?;
+
+        Migrator::up(&state.db, None)
+            .await
+            .context(MigrationSnafu)?;
+
+        Ok(state)
+    }
+
+    /// Loads the configuration and opens the database, leaving the
+    /// pending migrations to `bookforge migrate`
+    pub async fn without_migrations(
+        config_path: Option<Utf8PathBuf>,
+    ) -> Result<Self, AppStateError> {
         log::info!("Load configurations...");
-        let config: AppConfig = AppConfig::new().await.context(ConfigSnafu)?;
+        let config: AppConfig = AppConfig::new(config_path).await.context(ConfigSnafu)?;
 
-        let db: DatabaseConnection =
-            Database::connect(format!("sqlite:{}?mode=rwc", &config.database_path))
-                .await
-                .context(SqliteSnafu)?;
+        let db: DatabaseConnection = Database::connect(config.database_url())
+            .await
+            .context(SqliteSnafu)?;
 
-        log::info!("Database Loaded at : {}", config.database_path.clone());
+        log::info!("Database Loaded with : {:?}", db.get_database_backend());
 
-        Migrator::up(&db, None).await.context(MigrationSnafu)?;
+        let storage = storage::from_config(&config);
 
-        Ok(Self { config, db })
+        Ok(Self {
+            config,
+            db,
+            storage,
+        })
     }
 }
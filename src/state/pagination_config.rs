@@ -0,0 +1,40 @@
+use serde::{Deserialize, Serialize};
+
+/// Page size of the book list.
+///
+/// `per_page` is used when the `per_page` query parameter is missing, and
+/// `max_per_page` caps what the query parameter can ask for.
+#[derive(Clone, Deserialize, Serialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct PaginationConfig {
+    #[serde(default = "PaginationConfig::default_per_page")]
+    pub per_page: u64,
+    #[serde(default = "PaginationConfig::default_max_per_page")]
+    pub max_per_page: u64,
+}
+
+impl Default for PaginationConfig {
+    fn default() -> Self {
+        PaginationConfig {
+            per_page: Self::default_per_page(),
+            max_per_page: Self::default_max_per_page(),
+        }
+    }
+}
+
+impl PaginationConfig {
+    fn default_per_page() -> u64 {
+        100
+    }
+
+    fn default_max_per_page() -> u64 {
+        500
+    }
+
+    /// Page size to use for the requested one, between 1 and `max_per_page`
+    pub fn page_size(&self, requested: Option<u64>) -> u64 {
+        requested
+            .unwrap_or(self.per_page)
+            .clamp(1, self.max_per_page.max(1))
+    }
+}
@@ -0,0 +1,65 @@
+use serde::{Deserialize, Serialize};
+
+/// Where the uploaded files are stored
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StorageBackend {
+    /// A local directory, see `storage.path`
+    #[default]
+    Filesystem,
+    /// A bucket of an S3-compatible object storage, see `[storage.s3]`
+    S3,
+}
+
+/// Storage of the uploaded files, like the book covers.
+#[derive(Clone, Deserialize, Serialize, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct StorageConfig {
+    #[serde(default)]
+    pub backend: StorageBackend,
+    /// Directory of the filesystem backend. When empty, the directory of the
+    /// SQLite database is used.
+    #[serde(default)]
+    pub path: String,
+    #[serde(default)]
+    pub s3: S3Config,
+}
+
+/// Bucket of the S3 backend, e.g. a local MinIO at `http://localhost:9000`
+#[derive(Clone, Deserialize, Serialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct S3Config {
+    #[serde(default)]
+    pub endpoint: String,
+    #[serde(default = "S3Config::default_bucket")]
+    pub bucket: String,
+    #[serde(default = "S3Config::default_region")]
+    pub region: String,
+    #[serde(default)]
+    pub access_key: String,
+    #[serde(default)]
+    pub secret_key: String,
+}
+
+impl Default for S3Config {
+    fn default() -> Self {
+        S3Config {
+            endpoint: String::new(),
+            bucket: Self::default_bucket(),
+            region: Self::default_region(),
+            access_key: String::new(),
+            secret_key: String::new(),
+        }
+    }
+}
+
+impl S3Config {
+    fn default_bucket() -> String {
+        "bookforge".to_string()
+    }
+
+    /// MinIO and most S3-compatible storages accept any region
+    fn default_region() -> String {
+        "us-east-1".to_string()
+    }
+}
@@ -0,0 +1,26 @@
+use serde::{Deserialize, Serialize};
+
+/// Waitlist settings.
+///
+/// Once the book is back, the first user of the waitlist has `offer_days`
+/// to ask for it before their turn goes to the next one.
+#[derive(Clone, Deserialize, Serialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct WaitlistConfig {
+    #[serde(default = "WaitlistConfig::default_offer_days")]
+    pub offer_days: i64,
+}
+
+impl Default for WaitlistConfig {
+    fn default() -> Self {
+        WaitlistConfig {
+            offer_days: Self::default_offer_days(),
+        }
+    }
+}
+
+impl WaitlistConfig {
+    fn default_offer_days() -> i64 {
+        3
+    }
+}
@@ -0,0 +1,71 @@
+use async_trait::async_trait;
+use camino::Utf8PathBuf;
+use snafu::prelude::*;
+
+use crate::storage::{IOSnafu, NotFoundSnafu, Storage, StorageError, check_key};
+
+/// Files stored in a local directory, keys are paths relative to it
+#[derive(Debug)]
+pub struct FilesystemStorage {
+    root: Utf8PathBuf,
+}
+
+impl FilesystemStorage {
+    pub fn new(root: Utf8PathBuf) -> Self {
+        Self { root }
+    }
+
+    fn path(&self, key: &str) -> Result<Utf8PathBuf, StorageError> {
+        check_key(key)?;
+
+        Ok(self.root.join(key))
+    }
+}
+
+#[async_trait]
+impl Storage for FilesystemStorage {
+    async fn put(&self, key: &str, bytes: &[u8]) -> Result<(), StorageError> {
+        let path = self.path(key)?;
+
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent)
+                .await
+                .context(IOSnafu { path: parent })?;
+        }
+
+        // Written aside then renamed, so a reader never sees half a file
+        let partial = path.with_extension("partial");
+        tokio::fs::write(&partial, bytes).await.context(IOSnafu {
+            path: partial.clone(),
+        })?;
+        tokio::fs::rename(&partial, &path)
+            .await
+            .context(IOSnafu { path })
+    }
+
+    async fn get(&self, key: &str) -> Result<Vec<u8>, StorageError> {
+        let path = self.path(key)?;
+
+        match tokio::fs::read(&path).await {
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+                NotFoundSnafu { key }.fail()
+            }
+            result => result.context(IOSnafu { path }),
+        }
+    }
+
+    async fn exists(&self, key: &str) -> Result<bool, StorageError> {
+        let path = self.path(key)?;
+
+        tokio::fs::try_exists(&path).await.context(IOSnafu { path })
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), StorageError> {
+        let path = self.path(key)?;
+
+        match tokio::fs::remove_file(&path).await {
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            result => result.context(IOSnafu { path }),
+        }
+    }
+}
@@ -0,0 +1,108 @@
+//! Where the uploaded files (covers, ...) are kept, see [`StorageConfig`].
+//!
+//! Files are content addressed: their name is the SHA-256 of their content,
+//! so the same file uploaded twice is only stored once.
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use sha2::{Digest, Sha256};
+use snafu::prelude::*;
+
+use crate::state::config::AppConfig;
+use crate::state::storage_config::{StorageBackend, StorageConfig};
+
+pub mod filesystem;
+pub mod s3;
+
+pub use filesystem::FilesystemStorage;
+pub use s3::S3Storage;
+
+#[derive(Snafu, Debug)]
+#[snafu(visibility(pub))]
+pub enum StorageError {
+    /// Keys are built by BookForge, but some come from restored backups
+    #[snafu(display("Invalid storage key {key:?}"))]
+    InvalidKey { key: String },
+    #[snafu(display("No file stored at {key}"))]
+    NotFound { key: String },
+    #[snafu(display("Could not access {path}"))]
+    IO {
+        path: camino::Utf8PathBuf,
+        source: std::io::Error,
+    },
+    #[snafu(display("Request to the object storage failed for {key}"))]
+    Request { key: String, source: reqwest::Error },
+    #[snafu(display("The object storage answered {status} for {key}"))]
+    Status {
+        key: String,
+        status: reqwest::StatusCode,
+    },
+}
+
+/// A place to put files, addressed by a `/` separated key like `covers/originals/<name>`
+#[async_trait]
+pub trait Storage: Send + Sync + std::fmt::Debug {
+    /// Writes the file, replacing an existing one
+    async fn put(&self, key: &str, bytes: &[u8]) -> Result<(), StorageError>;
+
+    /// Reads the file, `StorageError::NotFound` if there is none
+    async fn get(&self, key: &str) -> Result<Vec<u8>, StorageError>;
+
+    /// Whether a file is stored at the key
+    async fn exists(&self, key: &str) -> Result<bool, StorageError>;
+
+    /// Removes the file, a missing file is not an error
+    async fn delete(&self, key: &str) -> Result<(), StorageError>;
+}
+
+/// Builds the storage backend selected in the configuration
+pub fn from_config(config: &AppConfig) -> Arc<dyn Storage> {
+    let storage: &StorageConfig = &config.storage;
+
+    match storage.backend {
+        StorageBackend::Filesystem => Arc::new(FilesystemStorage::new(config.storage_path())),
+        StorageBackend::S3 => Arc::new(S3Storage::new(storage.s3.clone())),
+    }
+}
+
+/// Name of a file from its content: the SHA-256 of the bytes and the extension
+pub fn content_name(bytes: &[u8], extension: &str) -> String {
+    format!("{}.{}", hex(&Sha256::digest(bytes)), extension)
+}
+
+/// Stores the file under `prefix` with its content name, unless the same
+/// content is already there. Returns the name.
+pub async fn put_content(
+    storage: &dyn Storage,
+    prefix: &str,
+    bytes: &[u8],
+    extension: &str,
+) -> Result<String, StorageError> {
+    let name = content_name(bytes, extension);
+    let key = format!("{}/{}", prefix, name);
+
+    if !storage.exists(&key).await? {
+        storage.put(&key, bytes).await?;
+    }
+
+    Ok(name)
+}
+
+/// Rejects the keys which could escape the storage root
+pub(crate) fn check_key(key: &str) -> Result<(), StorageError> {
+    ensure!(
+        !key.is_empty()
+            && !key.starts_with('/')
+            && key
+                .split('/')
+                .all(|part| !part.is_empty() && part != "." && part != ".."),
+        InvalidKeySnafu { key }
+    );
+
+    Ok(())
+}
+
+pub(crate) fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}